@@ -0,0 +1,114 @@
+// TransitTrail - Navigate Winnipeg Transit with a different style
+// Copyright (C) - 2023 Foxx Azalea Pinkerton, Max Fehlinger
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{error_string, ClientState, SettingsState};
+use log::warn;
+use tauri::api::{notification::Notification, path::config_dir};
+use tauri::{AppHandle, Manager, Runtime};
+use transit_api_client::advisories::{AdvisoryImpact, Confidence};
+use transit_api_client::prelude::*;
+use transit_api_client::store::{
+    advisories::{AdvisoryEvent, Tracker},
+    Store,
+};
+
+/// Checks the service advisories in the background, and shows a desktop notification for every
+/// new, updated or removed advisory, that affects one of the saved routes.
+///
+/// The advisories seen before are kept in `advisories.sqlite` in the config directory. When it
+/// is created, the advisories are only saved, so the first start does not show a notification
+/// for every current advisory. Failed checks are logged and retried after the interval of the
+/// [Tracker].
+///
+/// # Arguments
+///
+/// * `app`: The handle of the app, to get the client and the settings
+pub async fn watch<R: Runtime>(app: AppHandle<R>) {
+    let path = match config_dir() {
+        Some(dir) => dir.join("wpg-transit-client").join("advisories.sqlite"),
+        None => {
+            warn!("Failed to get config directory, service advisories are not watched");
+            return;
+        }
+    };
+    let store = match Store::open(path) {
+        Ok(store) => store,
+        Err(why) => {
+            error_string(&why, "Could not open the service advisory store");
+            return;
+        }
+    };
+    let mut first = matches!(store.advisories(), Ok(seen) if seen.is_empty());
+    let mut tracker = Tracker::new(store).category(Category::Transit);
+
+    loop {
+        let events = {
+            let client = app.state::<ClientState>();
+            let client = client.0.lock().await;
+            tracker.check(&client).await
+        };
+        match events {
+            Ok(events) if !first => notify(&app, events).await,
+            Ok(_) => first = false,
+            Err(why) => {
+                error_string(&why, "Could not check the service advisories");
+            }
+        }
+        tracker.wait().await;
+    }
+}
+
+/// Shows a notification for every event, whose advisory affects one of the saved routes
+async fn notify<R: Runtime>(app: &AppHandle<R>, events: Vec<AdvisoryEvent>) {
+    let saved_routes = app
+        .state::<SettingsState>()
+        .0
+        .lock()
+        .await
+        .saved_routes
+        .clone();
+    for event in events {
+        let advisory = event.advisory();
+        let impact = AdvisoryImpact::from(advisory);
+        let routes: Vec<&str> = saved_routes
+            .iter()
+            .filter(|route| impact.affects_route(&bus_type(route), Confidence::Medium))
+            .map(String::as_str)
+            .collect();
+        if routes.is_empty() {
+            continue;
+        }
+
+        let title = match &event {
+            AdvisoryEvent::New(_) => "New service advisory",
+            AdvisoryEvent::Updated { .. } => "Updated service advisory",
+            AdvisoryEvent::Removed(_) => "Service advisory ended",
+        };
+        if let Err(why) = Notification::new(&app.config().tauri.bundle.identifier)
+            .title(format!("{title}: {}", advisory.title))
+            .body(format!("Affects route {}", routes.join(", ")))
+            .show()
+        {
+            error_string(&why, "Could not show a service advisory notification");
+        }
+    }
+}
+
+/// The route of a saved route key, e.g. "16" or "BLUE"
+fn bus_type(route: &str) -> BusType {
+    match route.parse() {
+        Ok(number) => BusType::Regular(number),
+        Err(_) => BusType::Blue(route.to_string()),
+    }
+}
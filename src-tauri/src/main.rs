@@ -15,6 +15,7 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod advisory_notifications;
 mod service_advisory;
 mod settings;
 mod stop_info;
@@ -134,6 +135,7 @@ fn main() {
             trip_planner::trip_planner,
             trip_planner::search_locations,
             trip_planner::google_trip_planner,
+            trip_planner::save_trip_calendar,
         ])
         .plugin(
             tauri_plugin_log::Builder::default()
@@ -145,6 +147,8 @@ fn main() {
                 .build(),
         )
         .setup(|app| {
+            tauri::async_runtime::spawn(advisory_notifications::watch(app.handle()));
+
             #[cfg(debug_assertions)] // only include this code on debug builds
             {
                 let window = app.get_window("main").unwrap();
@@ -12,7 +12,7 @@
 // You should have received a copy of the GNU General Public License along with
 // this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::ClientState;
+use crate::{ClientState, SettingsState};
 use tauri::State;
 use transit_api_client::prelude::*;
 
@@ -29,13 +29,12 @@ use transit_api_client::prelude::*;
 /// ```
 pub async fn service_advisorie_html(
     client: State<'_, ClientState>,
+    settings: State<'_, SettingsState>,
     filters: Vec<filters::ServiceAdvisory>,
 ) -> Result<String, String> {
+    let usage = settings.0.lock().await.usage.clone();
     let client = client.0.lock().await;
-    let service_advisories = client
-        .service_advisories(filters, Usage::Normal)
-        .await
-        .unwrap();
+    let service_advisories = client.service_advisories(filters, usage).await.unwrap();
 
     let mut out = String::new();
     for service_advisory in service_advisories {
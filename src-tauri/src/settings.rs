@@ -32,6 +32,8 @@ pub struct Settings {
     pub max_walking_time: u32,
     pub walking_speed: f32,
     pub search_interval: u64,
+    pub usage: Usage,
+    pub saved_routes: Vec<String>,
 }
 
 impl Default for Settings {
@@ -46,6 +48,8 @@ impl Default for Settings {
             max_walking_time: 30,
 
             search_interval: 5000,
+            usage: Usage::Normal,
+            saved_routes: Vec::new(),
         }
     }
 }
@@ -12,17 +12,22 @@
 // You should have received a copy of the GNU General Public License along with
 // this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{error_string, ClientState};
+use crate::{error_string, ClientState, SettingsState};
 use tauri::State;
 use transit_api_client::prelude::*;
 
 #[tauri::command]
-pub async fn stop_info(id: u32, client: State<'_, ClientState>) -> Result<Stop, &'static str> {
+pub async fn stop_info(
+    id: u32,
+    client: State<'_, ClientState>,
+    settings: State<'_, SettingsState>,
+) -> Result<Stop, &'static str> {
+    let usage = settings.0.lock().await.usage.clone();
     client
         .0
         .lock()
         .await
-        .stop_info(id, Usage::Normal)
+        .stop_info(id, usage)
         .await
         .map_err(|why| error_string(&why, "Could not get stop info"))
 }
@@ -16,10 +16,12 @@ use serde::{Deserialize, Serialize};
 use tauri::State;
 use time::format_description::FormatItem;
 use time::macros::format_description;
-use time::PrimitiveDateTime;
+use time::{OffsetDateTime, PrimitiveDateTime};
 use transit_api_client::prelude::*;
+use transit_api_client::structs::Error;
+use transit_api_client::timezone;
 
-use crate::ClientState;
+use crate::{ClientState, SettingsState};
 
 /// Data to be turned into HTML
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,16 +36,12 @@ struct Html {
     scheduled_time: PrimitiveDateTime,
 
     class_names: Vec<String>,
-}
 
-impl Html {
-    fn delayed(&self) -> bool {
-        self.arrival_time > self.scheduled_time
-    }
+    /// Whether the bus is early, on time or late
+    status: DepartureStatus,
 
-    fn early(&self) -> bool {
-        self.arrival_time < self.scheduled_time
-    }
+    /// In how many minutes the bus departs
+    minutes: Option<i64>,
 }
 
 /// Gets all the stops for a given stop number
@@ -51,22 +49,26 @@ impl Html {
 /// # Arguments
 ///
 /// * `transit_client`: The transit client to use
+/// * `settings`: The settings, holding the usage of the requests
 /// * `stop`: The stop number to get the schedule for
+/// * `query`: The time range, routes and limits of the schedule
 ///
 /// returns: Result<String, Error>
 
 #[tauri::command]
 pub async fn stop_schedule(
     transit_client: State<'_, ClientState>,
+    settings: State<'_, SettingsState>,
     stop: u32,
-    filter: Vec<filters::Stop>,
+    query: filters::StopScheduleQuery,
 ) -> Result<String, String> {
+    let usage = settings.0.lock().await.usage.clone();
     // Get the schedule for the stop
     match transit_client
         .0
         .lock()
         .await
-        .stop_schedule(stop, filter, Usage::Normal)
+        .query_stop_schedule(stop, &query, usage)
         .await
     {
         Ok(Schedule {
@@ -74,30 +76,36 @@ pub async fn stop_schedule(
             route_schedules,
         }) => {
             // Handle the success case
+            let now = timezone::local(OffsetDateTime::now_utc());
+            let thresholds = Thresholds::default();
             let mut busses: Vec<Html> = Vec::new();
 
             for route_schedules in route_schedules {
                 for stops in route_schedules.scheduled_stops {
-                    if !stops.cancelled {
-                        busses.push(Html {
-                            bus_name: route_schedules
-                                .route
-                                .name
-                                .clone()
-                                .unwrap_or("BLUE".to_string()),
-                            arrival_time: stops.times.departure.estimated,
-                            scheduled_time: stops.times.departure.scheduled,
-                            class_names: route_schedules
-                                .route
-                                .badge_style
-                                .class_names
-                                .class_name
-                                .clone()
-                                .into_iter()
-                                .map(|x| x.to_string())
-                                .collect(),
-                        });
-                    }
+                    let time = match stops.times.time() {
+                        Some(time) if !stops.cancelled => time,
+                        _ => continue,
+                    };
+                    busses.push(Html {
+                        bus_name: route_schedules
+                            .route
+                            .name
+                            .clone()
+                            .unwrap_or("BLUE".to_string()),
+                        arrival_time: time.best(),
+                        scheduled_time: time.scheduled,
+                        class_names: route_schedules
+                            .route
+                            .badge_style
+                            .class_names
+                            .class_name
+                            .clone()
+                            .into_iter()
+                            .map(|x| x.to_string())
+                            .collect(),
+                        status: stops.status(now, &thresholds),
+                        minutes: stops.minutes_until_departure(now),
+                    });
                 }
             }
 
@@ -127,6 +135,7 @@ pub async fn stop_schedule(
 
             Ok(html)
         }
+        Err(Error::InvalidQuery(why)) => Err(why.to_string()),
         Err(err) => {
             // Handle the error case
             println!("Error: {:?}", err);
@@ -151,38 +160,34 @@ fn bus_to_html(data: Html) -> String {
     //     <div class="bus-time">12:00</div>
     // </div>
 
+    let (class, status) = match data.status {
+        DepartureStatus::Early => ("early", "EARLY"),
+        DepartureStatus::OnTime => ("on-time", "ON-TIME"),
+        DepartureStatus::Late => ("late", "LATE"),
+        DepartureStatus::Cancelled => ("cancelled", "CANCELLED"),
+        DepartureStatus::Departed => ("departed", "DEPARTED"),
+    };
+    let minutes = match data.minutes {
+        Some(0) => " (due)".to_string(),
+        Some(minutes) if minutes > 0 => format!(" (in {minutes} min)"),
+        _ => String::new(),
+    };
+    let scheduled = if matches!(data.status, DepartureStatus::Early | DepartureStatus::Late) {
+        format!("was {}", data.scheduled_time.format(TIME_FORMAT).unwrap())
+    } else {
+        String::new()
+    };
+
     let mut html = String::new();
     html.push_str("<tr class=\"bus\">");
-    if data.delayed() {
-        html.push_str(&format!(
-            r#"
-            <td class="time late">{}</td>
-            <td class="late">LATE</td>
-            <td class="scheduled">was {}</td>
-            "#,
-            data.arrival_time.format(DATETIME_FORMAT).unwrap(),
-            data.scheduled_time.format(TIME_FORMAT).unwrap()
-        ));
-    } else if data.early() {
-        html.push_str(&format!(
-            r#"
-            <td class="time early">{}</td>
-            <td class="early">EARLY</td>
-            <td class="scheduled">was {}</td>
-            "#,
-            data.arrival_time.format(DATETIME_FORMAT).unwrap(),
-            data.scheduled_time.format(TIME_FORMAT).unwrap()
-        ));
-    } else {
-        html.push_str(&format!(
-            r#"
-            <td class="time on-time">{}</td>
-            <td class="on-time">ON-TIME</td>
-            <td class="scheduled"></td>
-            "#,
-            data.arrival_time.format(DATETIME_FORMAT).unwrap()
-        ));
-    }
+    html.push_str(&format!(
+        r#"
+        <td class="time {class}">{}{minutes}</td>
+        <td class="{class}">{status}</td>
+        <td class="scheduled">{scheduled}</td>
+        "#,
+        data.arrival_time.format(DATETIME_FORMAT).unwrap(),
+    ));
     html.push_str(&format!(
         r#"
         <td class="name {}">{}</td>
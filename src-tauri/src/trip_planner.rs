@@ -14,11 +14,13 @@
 
 use crate::{error_string, ClientState, GoogleMapsState, SettingsState};
 use google_maps_api_client::{GeocodeResult, GoogleMapsClient, TravelMode};
+use tauri::api::dialog::blocking::FileDialogBuilder;
 use tauri::State;
 use time::macros::{format_description, offset};
-use time::{Date, OffsetDateTime};
+use time::{Date, OffsetDateTime, Time};
 use tokio::sync::MutexGuard;
-use transit_api_client::filters::{Mode, TripPlan as TripPlanFilters};
+use transit_api_client::export::calendar;
+use transit_api_client::filters::{Mode, TripPlanQuery};
 use transit_api_client::prelude::trip::Plan;
 use transit_api_client::prelude::*;
 
@@ -38,47 +40,75 @@ pub async fn search_locations(
 
 #[tauri::command]
 pub async fn trip_planner(
-    origin: PartialLocation<'_>,
-    destination: PartialLocation<'_>,
+    origin: OwnedPartialLocation,
+    destination: OwnedPartialLocation,
     date: Option<&str>,
     time: Option<(u8, u8)>,
     mode: Mode,
     client: State<'_, ClientState>,
     settings: State<'_, SettingsState>,
 ) -> Result<Vec<Plan>, &'static str> {
-    let trip_filters = {
+    let query = {
         let settings = settings.0.lock().await;
 
         let now = OffsetDateTime::now_utc().to_offset(offset!(-5));
-        let now_time = time.unwrap_or((now.time().hour(), now.time().minute()));
+        let date = match date {
+            Some(d) => Date::parse(d, format_description!("[year]-[month]-[day]"))
+                .map_err(|_| "Invalid date")?,
+            None => now.date(),
+        };
+        let time = match time {
+            Some((hour, minute)) => Time::from_hms(hour, minute, 0).map_err(|_| "Invalid time")?,
+            None => now.time(),
+        };
 
-        vec![
+        TripPlanQuery::new()
             // Specified filters from user
-            TripPlanFilters::Mode(mode),
-            TripPlanFilters::Date(match date {
-                Some(d) => Date::parse(d, format_description!("[year]-[month]-[day]"))
-                    .map_err(|_| "Invalid date")?,
-                None => now.date(),
-            }),
-            TripPlanFilters::Time(now_time.0, now_time.1),
+            .mode(mode)
+            .date(date)
+            .time(time)
             // Specified filters from settings
-            TripPlanFilters::MaxTransfers(settings.max_transfers),
-            TripPlanFilters::MinTransferWait(settings.min_waiting_time),
-            TripPlanFilters::MaxTransferWait(settings.max_waiting_time),
-            TripPlanFilters::WalkSpeed(settings.walking_speed),
-            TripPlanFilters::MaxWalkTime(settings.max_walking_time),
-        ]
+            .max_transfers(settings.max_transfers)
+            .min_transfer_wait(settings.min_waiting_time)
+            .max_transfer_wait(settings.max_waiting_time)
+            .walk_speed(settings.walking_speed)
+            .max_walk_time(settings.max_walking_time)
     };
+    query.validate()?;
 
     client
         .0
         .lock()
         .await
-        .trip_planner(origin, destination, trip_filters, Usage::Normal)
+        .plan_trip(origin, destination, &query, Usage::Normal)
         .await
         .map_err(|why| error_string(&why, "Could not get trip plan from the API"))
 }
 
+/// Asks where to save the plan, and saves it as an iCalendar file there.
+///
+/// Returns `false`, if no file was chosen.
+#[tauri::command]
+pub async fn save_trip_calendar(plan: Plan) -> Result<bool, &'static str> {
+    let path = match FileDialogBuilder::new()
+        .set_title("Add trip to calendar")
+        .set_file_name("trip.ics")
+        .add_filter("iCalendar", &["ics"])
+        .save_file()
+    {
+        Some(path) => path,
+        None => return Ok(false),
+    };
+
+    let ics = calendar::to_ics(
+        &plan,
+        &calendar::Options::default(),
+        OffsetDateTime::now_utc(),
+    );
+    std::fs::write(path, ics).map_err(|why| error_string(&why, "Could not save the calendar"))?;
+    Ok(true)
+}
+
 // Google Maps API
 async fn get_geocode_from_string(
     client: &GoogleMapsClient,
@@ -91,16 +121,16 @@ async fn get_geocode_from_string(
 }
 
 async fn partial_location_to_geocode(
-    partial_location: PartialLocation<'_>,
+    partial_location: OwnedPartialLocation,
     google_client: &GoogleMapsClient,
     transit_client: &TransitClient,
 ) -> Result<Option<GeocodeResult>, &'static str> {
     match partial_location {
-        PartialLocation::Point(lat, lng) => google_client
+        OwnedPartialLocation::Point(lat, lng) => google_client
             .reverse_geocode(lat, lng)
             .await
             .map_err(|why| error_string(&why, "Could not get geocode from point")),
-        PartialLocation::Stop(key) => {
+        OwnedPartialLocation::Stop(key) => {
             let stop = transit_client
                 .stop_info(key, Usage::Normal)
                 .await
@@ -111,20 +141,22 @@ async fn partial_location_to_geocode(
                 .await
                 .map_err(|why| error_string(&why, "Could not get geocode from point"))
         }
-        PartialLocation::Address(address) => get_geocode_from_string(google_client, address).await,
-        PartialLocation::Monument(monument) => {
-            get_geocode_from_string(google_client, monument).await
+        OwnedPartialLocation::Address(address) => {
+            get_geocode_from_string(google_client, &address).await
+        }
+        OwnedPartialLocation::Monument(monument) => {
+            get_geocode_from_string(google_client, &monument).await
         }
-        PartialLocation::Intersection(intersection) => {
-            get_geocode_from_string(google_client, intersection).await
+        OwnedPartialLocation::Intersection(intersection) => {
+            get_geocode_from_string(google_client, &intersection).await
         }
     }
 }
 
 #[tauri::command]
 pub async fn google_trip_planner(
-    origin: PartialLocation<'_>,
-    destination: PartialLocation<'_>,
+    origin: OwnedPartialLocation,
+    destination: OwnedPartialLocation,
     google_client: State<'_, GoogleMapsState>,
     transit_client: State<'_, ClientState>,
 ) -> Result<Vec<Plan>, &'static str> {
@@ -187,3 +187,41 @@ export type Stop =
            */
           MaxResultsPerRoute: number;
       };
+
+/**
+ * The filters of a stop schedule, checked for invalid or conflicting values
+ */
+export interface StopScheduleQuery {
+    /**
+     * Only return results after this date and time, formatted as YYYY-MM-DDTHH:MM:SS
+     *
+     * Defaults to now
+     */
+    start?: string;
+
+    /**
+     * Only return results before this date and time, formatted as YYYY-MM-DDTHH:MM:SS
+     *
+     * Defaults to two hours after the start
+     */
+    end?: string;
+
+    /**
+     * Also return results for these routes
+     *
+     * Defaults to all routes
+     */
+    routes?: (number | string)[];
+
+    /**
+     * Also return results for these variants of routes, e.g. "16-1-K"
+     *
+     * Defaults to all variants
+     */
+    variants?: string[];
+
+    /**
+     * Limit the results per returned route
+     */
+    max_results_per_route?: number;
+}
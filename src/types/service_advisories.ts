@@ -48,7 +48,7 @@ export interface ServiceAdvisory {
     /**
      * Timestamp of when the advisory was last updated.
      */
-    updated_at: Date;
+    updated_at?: Date;
 }
 
 /**
@@ -20,9 +20,11 @@ export class Settings {
     max_transfers: number;
     max_walking_time: number;
     walking_speed: number;
+    saved_routes: string[];
 
     // In the advanced section
     search_interval: number;
+    usage: Usage;
 
     toString(): string {
         return (
@@ -34,7 +36,9 @@ export class Settings {
             `max_transfers: ${this.max_transfers}; ` +
             `max_walking_time: ${this.max_walking_time}; ` +
             `walking_speed: ${this.walking_speed} ` +
+            `saved_routes: ${this.saved_routes} ` +
             `search_interval: ${this.search_interval} ` +
+            `usage: ${this.usage} ` +
             "}"
         );
     }
@@ -47,7 +51,9 @@ export class Settings {
         max_transfers: number,
         max_walking_time: number,
         walking_speed: number,
+        saved_routes: string[],
         search_interval: number,
+        usage: Usage,
     ) {
         return {
             api_key: api_key,
@@ -57,7 +63,14 @@ export class Settings {
             max_transfers: max_transfers,
             max_walking_time: max_walking_time,
             walking_speed: walking_speed,
+            saved_routes: saved_routes,
             search_interval: search_interval,
+            usage: usage,
         };
     }
 }
+
+/**
+ * How verbose the responses of the Transit API are. Short responses are the smallest.
+ */
+export type Usage = "normal" | "long" | "short";
@@ -291,12 +291,12 @@ export interface ScheduledTimes {
     /**
      * Times of when the bus is scheduled and estimated to arrive
      */
-    arrival: Time;
+    arrival?: Time;
 
     /**
      * Times of when the bus is scheduled and estimated to depart
      */
-    departure: Time;
+    departure?: Time;
 }
 
 /**
@@ -311,7 +311,7 @@ export interface Time {
     /**
      * When the bus is estimated
      */
-    estimated: Date;
+    estimated?: Date;
 }
 
 /**
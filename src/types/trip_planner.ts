@@ -37,14 +37,14 @@ export interface Plan {
  */
 export interface Times {
     /**
-     * When the ride/walk of the plan/segment starts
+     * When the ride/walk of the plan/segment starts, null if it is not known
      */
-    start: Date;
+    start: Date | null;
 
     /**
-     * When the ride/walk of the plan/segment end
+     * When the ride/walk of the plan/segment end, null if it is not known
      */
-    end: Date;
+    end: Date | null;
 
     /**
      * How much time is spent on different transport options (walking, riding, waiting, total time)
@@ -27,3 +27,17 @@ export function error(message: string, ...args: any[]) {
         .then(() => console.error(message, args))
         .catch((err) => console.error("Could not call log function!", err));
 }
+
+/**
+ * The local time of a date, or "?" if the time is not known
+ */
+export function formatTime(date: Date | null): string {
+    return date === null ? "?" : new Date(date).toLocaleTimeString();
+}
+
+/**
+ * The local date of a date, or "?" if the date is not known
+ */
+export function formatDate(date: Date | null): string {
+    return date === null ? "?" : new Date(date).toLocaleDateString();
+}
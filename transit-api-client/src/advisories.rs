@@ -0,0 +1,693 @@
+// TransitTrail - Navigate Winnipeg Transit with a different style
+// Copyright (C) - 2023 Foxx Azalea Pinkerton, Max Fehlinger
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! What a service advisory affects, read from its free-form title and body.
+//!
+//! The routes, stops, streets and dates are found in the prose, e.g. "Routes 16 and 18 will
+//! detour from June 5 to June 9" or a `** 16 Selkirk-Osborne` bullet. Every mention has a
+//! [Confidence]: numbers right after "Route" or "Stop" are certain, a lone five digit number is
+//! probably a stop.
+//!
+//! # Example
+//!
+//! ```
+//! use transit_api_client::advisories::{AdvisoryImpact, Confidence};
+//! use transit_api_client::prelude::*;
+//!
+//! let impact = AdvisoryImpact::parse(
+//!     "Routes 16 and 18 will detour via Main Street from June 5 to June 9. Stop #10064 is closed.",
+//!     Some(2023),
+//! );
+//! assert!(impact.affects_route(&BusType::Regular(18), Confidence::High));
+//! assert!(impact.affects_stop(10064, Confidence::Medium));
+//! assert_eq!(impact.streets[0].value, "Main Street");
+//! ```
+//!
+
+use serde::{Deserialize, Serialize};
+use time::{Date, Month};
+
+use crate::filters::{street_type_of, STREET_TYPES};
+use crate::search::words;
+use crate::structs::{service_advisories::ServiceAdvisory, stops::BusType};
+
+/// How sure the parser is, that a mention is what it is taken for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Confidence {
+    /// A guess, e.g. a five digit number without any context
+    Low,
+
+    /// Likely, e.g. a number after `#` or at the start of a bullet point
+    Medium,
+
+    /// Certain, e.g. a number right after "Route" or "Stop"
+    High,
+}
+
+/// Something found in an advisory, and how sure the parser is about it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mention<T> {
+    /// What was found
+    pub value: T,
+
+    /// How sure the parser is about it
+    pub confidence: Confidence,
+}
+
+/// When an advisory is in effect. Either bound may be open, e.g. for "until June 9".
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Period {
+    /// The first day the advisory is in effect
+    #[serde(default, with = "date_formatter::option")]
+    pub start: Option<Date>,
+
+    /// The last day the advisory is in effect
+    #[serde(default, with = "date_formatter::option")]
+    pub end: Option<Date>,
+}
+
+impl Period {
+    /// Whether the date lies in the period. Open bounds contain every date.
+    ///
+    /// # Arguments
+    ///
+    /// * `date`: The date to check
+    ///
+    /// returns: bool
+    pub fn contains(&self, date: Date) -> bool {
+        !matches!(self.start, Some(start) if date < start)
+            && !matches!(self.end, Some(end) if date > end)
+    }
+}
+
+/// The routes, stops, streets and periods a service advisory affects
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdvisoryImpact {
+    /// The affected routes
+    pub routes: Vec<Mention<BusType>>,
+
+    /// The numbers of the affected stops
+    pub stops: Vec<Mention<u32>>,
+
+    /// The affected streets, with their full street type, e.g. "Main Street"
+    pub streets: Vec<Mention<String>>,
+
+    /// When the advisory is in effect
+    pub periods: Vec<Mention<Period>>,
+}
+
+impl AdvisoryImpact {
+    /// Reads the affected routes, stops, streets and periods from the text of an advisory.
+    /// A value mentioned more than once keeps its highest confidence.
+    ///
+    /// # Arguments
+    ///
+    /// * `text`: The text of the advisory
+    /// * `year`: The year of dates without a year, usually the one the advisory was updated in.
+    ///   Dates without a year are left out, if [None].
+    ///
+    /// returns: AdvisoryImpact
+    pub fn parse(text: &str, year: Option<i32>) -> Self {
+        let tokens = tokenize(text);
+        let mut impact = Self::default();
+        let mut used = vec![false; tokens.len()];
+
+        for (index, token) in tokens.iter().enumerate() {
+            match token.to_lowercase().as_str() {
+                "route" | "routes" => {
+                    for (at, route) in list(&tokens, index + 1, route_number) {
+                        used[at] = true;
+                        mention(&mut impact.routes, route, Confidence::High);
+                    }
+                }
+                "stop" | "stops" => {
+                    for (at, stop) in list(&tokens, index + 1, stop_number) {
+                        used[at] = true;
+                        mention(&mut impact.stops, stop, Confidence::High);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for (index, token) in tokens.iter().enumerate() {
+            if used[index] {
+                continue;
+            }
+            if *token == "BLUE" {
+                let route = BusType::Blue(token.to_string());
+                mention(&mut impact.routes, route, Confidence::Medium);
+            } else if let Some(stop) = stop_number(token) {
+                let confidence = match index.checked_sub(1).map(|before| tokens[before]) {
+                    Some("#") => Confidence::Medium,
+                    _ => Confidence::Low,
+                };
+                mention(&mut impact.stops, stop, confidence);
+            }
+        }
+
+        // Bullet points of a detour often start with the affected route, e.g. "** 16 Selkirk"
+        for line in text.lines() {
+            if let Some(bullet) = line.trim_start().strip_prefix("**") {
+                if let Some(route) = tokenize(bullet).first().copied().and_then(route_number) {
+                    mention(&mut impact.routes, route, Confidence::Medium);
+                }
+            }
+        }
+
+        for index in 0..tokens.len() {
+            if let Some((street, confidence)) = street_at(&tokens, index) {
+                mention(&mut impact.streets, street, confidence);
+            }
+        }
+
+        let mut index = 0;
+        while index < tokens.len() {
+            match period_at(&tokens, index, year) {
+                Some((period, confidence, next)) => {
+                    mention(&mut impact.periods, period, confidence);
+                    index = next;
+                }
+                None => index += 1,
+            }
+        }
+
+        impact
+    }
+
+    /// Whether the advisory affects the route with at least the given confidence
+    ///
+    /// # Arguments
+    ///
+    /// * `route`: The route to check
+    /// * `confidence`: The lowest confidence to accept
+    ///
+    /// returns: bool
+    pub fn affects_route(&self, route: &BusType, confidence: Confidence) -> bool {
+        self.routes
+            .iter()
+            .any(|mention| &mention.value == route && mention.confidence >= confidence)
+    }
+
+    /// Whether the advisory affects the stop with at least the given confidence
+    ///
+    /// # Arguments
+    ///
+    /// * `stop`: The number of the stop to check
+    /// * `confidence`: The lowest confidence to accept
+    ///
+    /// returns: bool
+    pub fn affects_stop(&self, stop: u32, confidence: Confidence) -> bool {
+        self.stops
+            .iter()
+            .any(|mention| mention.value == stop && mention.confidence >= confidence)
+    }
+
+    /// Whether the advisory is in effect on a date. Advisories without any dates are always in
+    /// effect.
+    ///
+    /// # Arguments
+    ///
+    /// * `date`: The date to check
+    ///
+    /// returns: bool
+    pub fn is_active(&self, date: Date) -> bool {
+        self.periods.is_empty()
+            || self
+                .periods
+                .iter()
+                .any(|period| period.value.contains(date))
+    }
+}
+
+impl From<&ServiceAdvisory> for AdvisoryImpact {
+    /// Parses the title and body of the advisory. Dates without a year are taken to be in the
+    /// year the advisory was updated in.
+    fn from(advisory: &ServiceAdvisory) -> Self {
+        Self::parse(
+            &format!("{}\n{}", advisory.title, advisory.body),
+            advisory.updated_at.map(|updated_at| updated_at.year()),
+        )
+    }
+}
+
+/// Adds a mention, or raises the confidence of an earlier mention of the same value
+fn mention<T: PartialEq>(mentions: &mut Vec<Mention<T>>, value: T, confidence: Confidence) {
+    match mentions.iter_mut().find(|mention| mention.value == value) {
+        Some(mention) => mention.confidence = mention.confidence.max(confidence),
+        None => mentions.push(Mention { value, confidence }),
+    }
+}
+
+/// Splits a text into words, numbers and single punctuation marks. Apostrophes stay in words.
+fn tokenize(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (index, c) in text.char_indices() {
+        if c.is_alphanumeric() || (start.is_some() && matches!(c, '\'' | '’')) {
+            start.get_or_insert(index);
+            continue;
+        }
+        if let Some(start) = start.take() {
+            tokens.push(&text[start..index]);
+        }
+        if !c.is_whitespace() {
+            tokens.push(&text[index..index + c.len_utf8()]);
+        }
+    }
+    if let Some(start) = start {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+/// A route number, e.g. "16" or "BLUE"
+fn route_number(token: &str) -> Option<BusType> {
+    match token {
+        "BLUE" => Some(BusType::Blue(token.to_string())),
+        _ if token.len() <= 3 && token.bytes().all(|b| b.is_ascii_digit()) => {
+            token.parse().ok().map(BusType::Regular)
+        }
+        _ => None,
+    }
+}
+
+/// A stop number, which always has five digits
+fn stop_number(token: &str) -> Option<u32> {
+    if token.len() == 5 && token.bytes().all(|b| b.is_ascii_digit()) {
+        token.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Reads a list like "16, 18 and 20" or "#10064 & #10065" starting at `index`. Returns the
+/// index of each item with the item.
+fn list<T>(tokens: &[&str], index: usize, parse: fn(&str) -> Option<T>) -> Vec<(usize, T)> {
+    let mut items = Vec::new();
+    let mut abbreviated = false;
+    for (at, token) in tokens.iter().enumerate().skip(index) {
+        // The dot of "No." or "Nos."
+        if abbreviated && *token == "." {
+            abbreviated = false;
+            continue;
+        }
+        let lowercase = token.to_lowercase();
+        abbreviated = matches!(lowercase.as_str(), "no" | "nos");
+        if matches!(
+            lowercase.as_str(),
+            "," | "&" | "#" | "/" | "and" | "or" | "no" | "nos" | "number" | "numbers"
+        ) {
+            continue;
+        }
+        match parse(token) {
+            Some(item) => items.push((at, item)),
+            None => break,
+        }
+    }
+    items
+}
+
+/// Capitalised words, that are not part of the name of a street in front of them
+const NOT_STREET_NAMES: [&str; 20] = [
+    "The", "On", "At", "Of", "And", "Or", "Via", "To", "From", "Between", "Near", "Along",
+    "Detour", "Detours", "Closed", "Closure", "Route", "Routes", "Stop", "Stops",
+];
+
+/// A street, whose street type is at `index`, e.g. "Main St" as "Main Street". The name may be
+/// up to three capitalised words long. Abbreviated street types are less certain, as "St" may
+/// also be "Saint". A "St." in front of the name is "Saint", e.g. in "St. Mary's Road".
+fn street_at(tokens: &[&str], index: usize) -> Option<(String, Confidence)> {
+    let token = tokens[index];
+    if !token.starts_with(char::is_uppercase) {
+        return None;
+    }
+    let street_type = street_type_of(&STREET_TYPES, token)?;
+    let start = (index.saturating_sub(3)..index)
+        .rev()
+        .take_while(|at| {
+            let word = tokens[*at];
+            word.starts_with(char::is_uppercase)
+                && word
+                    .chars()
+                    .all(|c| c.is_alphabetic() || matches!(c, '\'' | '’'))
+                && !NOT_STREET_NAMES.contains(&word)
+                && street_type_of(&STREET_TYPES, word).is_none()
+        })
+        .last()?;
+    let confidence = if token.eq_ignore_ascii_case(street_type) {
+        Confidence::High
+    } else {
+        Confidence::Medium
+    };
+    let name = tokens[start..index].join(" ");
+    let street = match tokens[..start] {
+        [.., "St", "."] | [.., "St"] => format!("St. {name} {street_type}"),
+        _ => format!("{name} {street_type}"),
+    };
+    Some((street, confidence))
+}
+
+/// A capitalised month, e.g. "June", "Jun" or "Sept"
+fn month(token: &str) -> Option<Month> {
+    if !token.starts_with(char::is_uppercase) {
+        return None;
+    }
+    Some(match words(token).concat().as_str() {
+        "january" | "jan" => Month::January,
+        "february" | "feb" => Month::February,
+        "march" | "mar" => Month::March,
+        "april" | "apr" => Month::April,
+        "may" => Month::May,
+        "june" | "jun" => Month::June,
+        "july" | "jul" => Month::July,
+        "august" | "aug" => Month::August,
+        "september" | "sept" | "sep" => Month::September,
+        "october" | "oct" => Month::October,
+        "november" | "nov" => Month::November,
+        "december" | "dec" => Month::December,
+        _ => return None,
+    })
+}
+
+/// A day of the month, e.g. "5" or "5th"
+fn day(token: &str) -> Option<u8> {
+    let digits = token.trim_end_matches(|c: char| c.is_alphabetic());
+    let suffix = &token[digits.len()..];
+    if digits.is_empty() || !matches!(suffix, "" | "st" | "nd" | "rd" | "th") {
+        return None;
+    }
+    digits.parse().ok().filter(|day| (1..=31).contains(day))
+}
+
+/// A date like "June 5", "Jun. 5th" or "June 5, 2023", starting at `index`. Returns the date,
+/// whether the year was given, and the index after the date.
+fn date_at(tokens: &[&str], index: usize, year: Option<i32>) -> Option<(Date, bool, usize)> {
+    let month = month(tokens.get(index)?)?;
+    let mut next = index + 1;
+    if tokens.get(next) == Some(&".") {
+        next += 1;
+    }
+    let day = day(tokens.get(next)?)?;
+    next += 1;
+
+    let after_comma = match tokens.get(next) {
+        Some(&",") => next + 1,
+        _ => next,
+    };
+    let given = tokens
+        .get(after_comma)
+        .filter(|token| token.len() == 4)
+        .and_then(|token| token.parse::<i32>().ok());
+    let (year, next) = match given {
+        Some(given) => (given, after_comma + 1),
+        None => (year?, next),
+    };
+    let date = Date::from_calendar_date(year, month, day).ok()?;
+    Some((date, given.is_some(), next))
+}
+
+/// A period starting at `index`: a range like "June 5 to June 9" or "June 5-9", a date after
+/// a word like "until" or "starting", or a single day. Returns the period, how sure the parser
+/// is about it, and the index after it.
+fn period_at(
+    tokens: &[&str],
+    index: usize,
+    year: Option<i32>,
+) -> Option<(Period, Confidence, usize)> {
+    let (start, start_given, next) = date_at(tokens, index, year)?;
+
+    let connector = tokens.get(next).map(|token| token.to_lowercase());
+    if matches!(
+        connector.as_deref(),
+        Some("to" | "until" | "till" | "through" | "thru" | "-" | "–" | "and")
+    ) {
+        let range = match date_at(tokens, next + 1, Some(start.year())) {
+            Some((end, end_given, after)) => match (start_given, end_given) {
+                // "June 5 to June 9, 2024" is in the year of the end
+                (false, true) => start
+                    .replace_year(end.year())
+                    .ok()
+                    .map(|start| (start, end, after)),
+                // "December 28 to January 3" ends in the next year
+                (_, false) if end < start => end
+                    .replace_year(end.year() + 1)
+                    .ok()
+                    .map(|end| (start, end, after)),
+                _ => Some((start, end, after)),
+            },
+            // "June 5-9"
+            None => tokens
+                .get(next + 1)
+                .copied()
+                .and_then(day)
+                .and_then(|day| start.replace_day(day).ok())
+                .map(|end| (start, end, next + 2)),
+        };
+        if let Some((start, end, after)) = range {
+            let period = Period {
+                start: Some(start),
+                end: Some(end),
+            };
+            return Some((period, Confidence::High, after));
+        }
+    }
+
+    let before = |offset: usize| {
+        index
+            .checked_sub(offset)
+            .map(|at| tokens[at].to_lowercase())
+            .unwrap_or_default()
+    };
+    let (period, confidence) = match before(1).as_str() {
+        "until" | "till" | "through" | "thru" | "before" => (
+            Period {
+                start: None,
+                end: Some(start),
+            },
+            Confidence::Medium,
+        ),
+        "from" | "starting" | "beginning" | "effective" | "commencing" | "after" | "since" => (
+            Period {
+                start: Some(start),
+                end: None,
+            },
+            Confidence::Medium,
+        ),
+        "of" if before(2) == "as" => (
+            Period {
+                start: Some(start),
+                end: None,
+            },
+            Confidence::Medium,
+        ),
+        word => (
+            Period {
+                start: Some(start),
+                end: Some(start),
+            },
+            match word {
+                "on" => Confidence::Medium,
+                _ => Confidence::Low,
+            },
+        ),
+    };
+    Some((period, confidence, next))
+}
+
+time::serde::format_description!(date_formatter, Date, "[year]-[month]-[day]");
+
+#[cfg(test)]
+mod test {
+    use crate::advisories::{AdvisoryImpact, Confidence, Mention, Period};
+    use crate::prelude::*;
+    use time::macros::{date, datetime};
+
+    #[test]
+    fn routes_and_stops() {
+        let impact = AdvisoryImpact::parse(
+            "Routes 11, 14 & 16 will not serve Stop Nos. 10064 and 10065. Use stop #10070 \
+             or 10071 instead. BLUE is not affected. Call 311 for more information.",
+            None,
+        );
+        assert_eq!(
+            impact.routes,
+            vec![
+                Mention {
+                    value: BusType::Regular(11),
+                    confidence: Confidence::High,
+                },
+                Mention {
+                    value: BusType::Regular(14),
+                    confidence: Confidence::High,
+                },
+                Mention {
+                    value: BusType::Regular(16),
+                    confidence: Confidence::High,
+                },
+                Mention {
+                    value: BusType::Blue("BLUE".to_string()),
+                    confidence: Confidence::Medium,
+                },
+            ]
+        );
+        assert_eq!(
+            impact.stops,
+            vec![
+                Mention {
+                    value: 10064,
+                    confidence: Confidence::High,
+                },
+                Mention {
+                    value: 10065,
+                    confidence: Confidence::High,
+                },
+                Mention {
+                    value: 10070,
+                    confidence: Confidence::High,
+                },
+                Mention {
+                    value: 10071,
+                    confidence: Confidence::High,
+                },
+            ]
+        );
+        assert!(impact.affects_route(&BusType::Regular(14), Confidence::High));
+        assert!(!impact.affects_route(&BusType::Regular(311), Confidence::Low));
+    }
+
+    #[test]
+    fn lone_numbers_and_bullets() {
+        let impact = AdvisoryImpact::parse(
+            "The following routes will detour:\n\
+             ** 16 Selkirk-Osborne\n\
+             ** 18 North Main-Corydon\n\
+             Buses will not serve #10064. 10065 will be moved.",
+            None,
+        );
+        assert!(impact.affects_route(&BusType::Regular(16), Confidence::Medium));
+        assert!(impact.affects_route(&BusType::Regular(18), Confidence::Medium));
+        assert!(!impact.affects_route(&BusType::Regular(16), Confidence::High));
+        assert!(impact.affects_stop(10064, Confidence::Medium));
+        assert!(!impact.affects_stop(10065, Confidence::Medium));
+        assert!(impact.affects_stop(10065, Confidence::Low));
+    }
+
+    #[test]
+    fn streets() {
+        let impact = AdvisoryImpact::parse(
+            "Detour on Main St between Pioneer Avenue and Portage Ave. East, due to the \
+             Red River Ex. Buses will use St. Mary's Road.",
+            None,
+        );
+        let streets: Vec<(&str, Confidence)> = impact
+            .streets
+            .iter()
+            .map(|mention| (mention.value.as_str(), mention.confidence))
+            .collect();
+        assert_eq!(
+            streets,
+            vec![
+                ("Main Street", Confidence::Medium),
+                ("Pioneer Avenue", Confidence::High),
+                ("Portage Avenue", Confidence::Medium),
+                ("St. Mary's Road", Confidence::High),
+            ]
+        );
+    }
+
+    #[test]
+    fn periods() {
+        let parse = |text: &str| {
+            AdvisoryImpact::parse(text, Some(2023))
+                .periods
+                .into_iter()
+                .map(|mention| (mention.value.start, mention.value.end, mention.confidence))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            parse("Closed from June 5 to June 9, 2024."),
+            vec![(
+                Some(date!(2024 - 06 - 05)),
+                Some(date!(2024 - 06 - 09)),
+                Confidence::High
+            )]
+        );
+        assert_eq!(
+            parse("Detour Jun. 5th-9th"),
+            vec![(
+                Some(date!(2023 - 06 - 05)),
+                Some(date!(2023 - 06 - 09)),
+                Confidence::High
+            )]
+        );
+        assert_eq!(
+            parse("Holiday service December 28 to January 3"),
+            vec![(
+                Some(date!(2023 - 12 - 28)),
+                Some(date!(2024 - 01 - 03)),
+                Confidence::High
+            )]
+        );
+        assert_eq!(
+            parse("Stop closed until July 3. Detour in effect as of August 1"),
+            vec![
+                (None, Some(date!(2023 - 07 - 03)), Confidence::Medium),
+                (Some(date!(2023 - 08 - 01)), None, Confidence::Medium),
+            ]
+        );
+        assert_eq!(
+            parse("Service on May 22, 2023. Buses may be 5 minutes late."),
+            vec![(
+                Some(date!(2023 - 05 - 22)),
+                Some(date!(2023 - 05 - 22)),
+                Confidence::Medium
+            )]
+        );
+        assert!(AdvisoryImpact::parse("Closed June 5", None)
+            .periods
+            .is_empty());
+
+        let period = Period {
+            start: Some(date!(2023 - 06 - 05)),
+            end: None,
+        };
+        assert!(!period.contains(date!(2023 - 06 - 04)));
+        assert!(period.contains(date!(2023 - 06 - 05)));
+        assert!(period.contains(date!(2024 - 01 - 01)));
+    }
+
+    #[test]
+    fn service_advisory() -> Result<(), serde_json::Error> {
+        let advisory = ServiceAdvisory {
+            key: 1,
+            title: "Route 16 Detour".to_string(),
+            body: "Buses will detour via Osborne Street from June 5 to June 9.".to_string(),
+            updated_at: Some(datetime!(2023-06-01 9:00)),
+            ..Default::default()
+        };
+        let impact = AdvisoryImpact::from(&advisory);
+        assert!(impact.affects_route(&BusType::Regular(16), Confidence::High));
+        assert_eq!(impact.streets[0].value, "Osborne Street");
+        assert!(impact.is_active(date!(2023 - 06 - 07)));
+        assert!(!impact.is_active(date!(2023 - 06 - 10)));
+
+        let json = serde_json::to_string(&impact)?;
+        assert!(json.contains(r#""start":"2023-06-05""#), "{json}");
+        assert_eq!(serde_json::from_str::<AdvisoryImpact>(&json)?, impact);
+        Ok(())
+    }
+}
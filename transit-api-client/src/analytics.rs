@@ -0,0 +1,404 @@
+// TransitTrail - Navigate Winnipeg Transit with a different style
+// Copyright (C) - 2023 Foxx Azalea Pinkerton, Max Fehlinger
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! On-time performance of the buses, computed from recorded departures.
+//!
+//! The departures usually come from [Store::actual_departures](crate::store::Store::actual_departures).
+//! A departure counts as on time, if it is at most [Thresholds::early] early and at most
+//! [Thresholds::late] late.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use time::macros::datetime;
+//! use transit_api_client::analytics::{self, Grouping, Thresholds};
+//! use transit_api_client::store::Store;
+//!
+//! let store = Store::open("adherence.sqlite").unwrap();
+//! let departures = store
+//!     .actual_departures(datetime!(2023-06-01 0:00), datetime!(2023-07-01 0:00))
+//!     .unwrap();
+//! for (route, metrics) in analytics::grouped(&departures, Grouping::Route, &Thresholds::default()) {
+//!     println!("{route}: {:.1}% on time", metrics.on_time_rate() * 100.0);
+//! }
+//! ```
+//!
+
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fmt::{Display, Formatter},
+};
+
+use time::{Duration, Weekday};
+
+pub use crate::structs::stops::Thresholds;
+
+use crate::store::adherence::Observation;
+
+/// How well a set of departures kept to the schedule
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// Number of departures with a scheduled and estimated time, that were not cancelled
+    pub count: usize,
+
+    /// Number of departures within the thresholds
+    pub on_time: usize,
+
+    /// Number of departures before the early threshold
+    pub early: usize,
+
+    /// Number of departures after the late threshold
+    pub late: usize,
+
+    /// Number of cancelled departures. They are not part of any other number.
+    pub cancelled: usize,
+
+    /// The delays of all counted departures, shortest first
+    delays: Vec<Duration>,
+}
+
+impl Metrics {
+    /// The share of departures that were on time, between 0 and 1. 0 if there are no departures.
+    pub fn on_time_rate(&self) -> f64 {
+        rate(self.on_time, self.count)
+    }
+
+    /// The share of departures that were early, between 0 and 1. 0 if there are no departures.
+    pub fn early_rate(&self) -> f64 {
+        rate(self.early, self.count)
+    }
+
+    /// The share of departures that were late, between 0 and 1. 0 if there are no departures.
+    pub fn late_rate(&self) -> f64 {
+        rate(self.late, self.count)
+    }
+
+    /// The mean delay, negative if buses departed early on average
+    pub fn mean_delay(&self) -> Option<Duration> {
+        if self.delays.is_empty() {
+            return None;
+        }
+        let total: Duration = self.delays.iter().sum();
+        Some(total / self.delays.len() as u32)
+    }
+
+    /// The delay that `percentile` percent of the departures did not exceed, using the nearest
+    /// rank method.
+    ///
+    /// # Arguments
+    ///
+    /// * `percentile`: Between 0 and 100, e.g. 50 for the median or 95
+    ///
+    /// returns: Option<Duration>
+    pub fn percentile_delay(&self, percentile: f64) -> Option<Duration> {
+        if self.delays.is_empty() {
+            return None;
+        }
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * self.delays.len() as f64).ceil();
+        let index = (rank as usize).clamp(1, self.delays.len()) - 1;
+        Some(self.delays[index])
+    }
+
+    /// The median delay
+    pub fn median_delay(&self) -> Option<Duration> {
+        self.percentile_delay(50.0)
+    }
+
+    fn add(&mut self, observation: &Observation, thresholds: &Thresholds) {
+        if observation.cancelled {
+            self.cancelled += 1;
+            return;
+        }
+        let delay = match observation.departure_delay() {
+            Some(delay) => delay,
+            None => return,
+        };
+        self.count += 1;
+        if thresholds.is_early(delay) {
+            self.early += 1;
+        } else if thresholds.is_late(delay) {
+            self.late += 1;
+        } else {
+            self.on_time += 1;
+        }
+        let position = self.delays.partition_point(|other| *other <= delay);
+        self.delays.insert(position, delay);
+    }
+}
+
+fn rate(part: usize, count: usize) -> f64 {
+    if count == 0 {
+        0.0
+    } else {
+        part as f64 / count as f64
+    }
+}
+
+/// By what the departures are grouped in [grouped]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Grouping {
+    /// By the key of the route
+    Route,
+
+    /// By the key of the variant
+    Variant,
+
+    /// By the key of the stop
+    Stop,
+
+    /// By the hour of the scheduled departure
+    Hour,
+
+    /// By the day of the week of the scheduled departure
+    Weekday,
+}
+
+/// A group of departures, created by [grouped]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Group {
+    /// Departures of a route
+    Route(String),
+
+    /// Departures of a variant
+    Variant(String),
+
+    /// Departures at a stop
+    Stop(u32),
+
+    /// Departures scheduled in an hour of the day (0 to 23)
+    Hour(u8),
+
+    /// Departures scheduled on a day of the week
+    Weekday(Weekday),
+}
+
+impl Group {
+    fn of(observation: &Observation, grouping: Grouping) -> Option<Self> {
+        Some(match grouping {
+            Grouping::Route => Self::Route(observation.route.clone()),
+            Grouping::Variant => Self::Variant(observation.variant.clone()),
+            Grouping::Stop => Self::Stop(observation.stop),
+            Grouping::Hour => Self::Hour(observation.scheduled_departure?.hour()),
+            Grouping::Weekday => Self::Weekday(observation.scheduled_departure?.weekday()),
+        })
+    }
+
+    fn kind(&self) -> u8 {
+        match self {
+            Self::Route(_) => 0,
+            Self::Variant(_) => 1,
+            Self::Stop(_) => 2,
+            Self::Hour(_) => 3,
+            Self::Weekday(_) => 4,
+        }
+    }
+}
+
+impl Display for Group {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Route(route) => write!(f, "{route}"),
+            Self::Variant(variant) => write!(f, "{variant}"),
+            Self::Stop(stop) => write!(f, "{stop}"),
+            Self::Hour(hour) => write!(f, "{hour:02}:00"),
+            Self::Weekday(weekday) => write!(f, "{weekday}"),
+        }
+    }
+}
+
+impl PartialOrd for Group {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Group {
+    /// Groups of the same kind are ordered by their value, weekdays starting on Monday.
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Route(a), Self::Route(b)) | (Self::Variant(a), Self::Variant(b)) => {
+                // Numeric routes and variants in their numeric order
+                let number = |key: &str| {
+                    key.split('-')
+                        .next()
+                        .and_then(|number| number.parse::<u32>().ok())
+                };
+                match (number(a), number(b)) {
+                    (Some(x), Some(y)) => x.cmp(&y).then_with(|| a.cmp(b)),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => a.cmp(b),
+                }
+            }
+            (Self::Stop(a), Self::Stop(b)) => a.cmp(b),
+            (Self::Hour(a), Self::Hour(b)) => a.cmp(b),
+            (Self::Weekday(a), Self::Weekday(b)) => a
+                .number_days_from_monday()
+                .cmp(&b.number_days_from_monday()),
+            _ => self.kind().cmp(&other.kind()),
+        }
+    }
+}
+
+/// Computes the metrics of all departures.
+///
+/// # Arguments
+///
+/// * `departures`: The actual departures
+/// * `thresholds`: When a departure is early or late
+///
+/// returns: Metrics
+pub fn metrics(departures: &[Observation], thresholds: &Thresholds) -> Metrics {
+    let mut metrics = Metrics::default();
+    for departure in departures {
+        metrics.add(departure, thresholds);
+    }
+    metrics
+}
+
+/// Computes the metrics of the departures for every group.
+///
+/// Departures, that can not be grouped (e.g. by hour without a scheduled departure) are left out.
+///
+/// # Arguments
+///
+/// * `departures`: The actual departures
+/// * `grouping`: By what to group the departures
+/// * `thresholds`: When a departure is early or late
+///
+/// returns: Vec<(Group, Metrics)> Ordered by the group
+pub fn grouped(
+    departures: &[Observation],
+    grouping: Grouping,
+    thresholds: &Thresholds,
+) -> Vec<(Group, Metrics)> {
+    let mut groups: HashMap<Group, Metrics> = HashMap::new();
+    for departure in departures {
+        if let Some(group) = Group::of(departure, grouping) {
+            groups.entry(group).or_default().add(departure, thresholds);
+        }
+    }
+    let mut groups: Vec<(Group, Metrics)> = groups.into_iter().collect();
+    groups.sort_by(|(a, _), (b, _)| a.cmp(b));
+    groups
+}
+
+#[cfg(test)]
+mod test {
+    use crate::analytics::{self, Group, Grouping, Thresholds};
+    use crate::store::adherence::Observation;
+    use time::macros::datetime;
+    use time::{Duration, PrimitiveDateTime, Weekday};
+
+    fn departure(route: &str, scheduled: PrimitiveDateTime, delay: i64) -> Observation {
+        Observation {
+            scheduled_stop: format!("{route}-{scheduled}"),
+            stop: 10064,
+            route: route.to_string(),
+            variant: format!("{route}-1-K"),
+            bus: None,
+            cancelled: false,
+            scheduled_arrival: None,
+            estimated_arrival: None,
+            scheduled_departure: Some(scheduled),
+            estimated_departure: Some(scheduled + Duration::seconds(delay)),
+            observed_at: scheduled,
+        }
+    }
+
+    fn departures() -> Vec<Observation> {
+        vec![
+            // Thursday
+            departure("16", datetime!(2023-06-01 7:10), 0),
+            departure("16", datetime!(2023-06-01 7:40), 240),
+            departure("16", datetime!(2023-06-01 8:10), -90),
+            departure("16", datetime!(2023-06-01 8:40), 60),
+            // Friday
+            departure("BLUE", datetime!(2023-06-02 7:05), 30),
+            departure("BLUE", datetime!(2023-06-02 7:15), 180),
+            departure("11", datetime!(2023-06-02 7:20), 600),
+            Observation {
+                cancelled: true,
+                ..departure("11", datetime!(2023-06-02 7:50), 0)
+            },
+        ]
+    }
+
+    #[test]
+    fn metrics() {
+        let metrics = analytics::metrics(&departures(), &Thresholds::default());
+        assert_eq!(metrics.count, 7);
+        assert_eq!(metrics.cancelled, 1);
+        assert_eq!(metrics.on_time, 4);
+        assert_eq!(metrics.early, 1);
+        assert_eq!(metrics.late, 2);
+        assert!((metrics.on_time_rate() - 4.0 / 7.0).abs() < 1e-9);
+        assert!((metrics.early_rate() - 1.0 / 7.0).abs() < 1e-9);
+        // (0 + 240 - 90 + 60 + 30 + 180 + 600) / 7
+        assert_eq!(metrics.mean_delay(), Some(Duration::seconds(1020) / 7));
+        assert_eq!(metrics.median_delay(), Some(Duration::seconds(60)));
+        assert_eq!(metrics.percentile_delay(90.0), Some(Duration::seconds(600)));
+        assert_eq!(metrics.percentile_delay(0.0), Some(Duration::seconds(-90)));
+
+        let strict = Thresholds {
+            early: Duration::ZERO,
+            late: Duration::ZERO,
+        };
+        let metrics = analytics::metrics(&departures(), &strict);
+        assert_eq!(metrics.on_time, 1);
+        assert_eq!(metrics.late, 5);
+
+        let empty = analytics::metrics(&[], &Thresholds::default());
+        assert_eq!(empty.on_time_rate(), 0.0);
+        assert_eq!(empty.mean_delay(), None);
+        assert_eq!(empty.percentile_delay(50.0), None);
+    }
+
+    #[test]
+    fn grouped() {
+        let thresholds = Thresholds::default();
+        let by_route = analytics::grouped(&departures(), Grouping::Route, &thresholds);
+        let routes: Vec<(String, usize)> = by_route
+            .iter()
+            .map(|(group, metrics)| (group.to_string(), metrics.count))
+            .collect();
+        assert_eq!(
+            routes,
+            vec![
+                ("11".to_string(), 1),
+                ("16".to_string(), 4),
+                ("BLUE".to_string(), 2)
+            ]
+        );
+        assert_eq!(by_route[0].1.cancelled, 1);
+
+        let by_hour = analytics::grouped(&departures(), Grouping::Hour, &thresholds);
+        assert_eq!(by_hour[0].0, Group::Hour(7));
+        assert_eq!(by_hour[0].1.count, 5);
+        assert_eq!(by_hour[1].0, Group::Hour(8));
+        assert_eq!(by_hour[1].1.on_time, 1);
+
+        let by_weekday = analytics::grouped(&departures(), Grouping::Weekday, &thresholds);
+        assert_eq!(by_weekday[0].0, Group::Weekday(Weekday::Thursday));
+        assert_eq!(by_weekday[1].0, Group::Weekday(Weekday::Friday));
+        assert_eq!(by_weekday[1].1.late, 1);
+        assert_eq!(by_weekday[1].1.on_time, 2);
+
+        let by_stop = analytics::grouped(&departures(), Grouping::Stop, &thresholds);
+        assert_eq!(by_stop.len(), 1);
+        assert_eq!(by_stop[0].1.count, 7);
+    }
+}
@@ -18,7 +18,7 @@
 
 use serde::Deserialize;
 
-use crate::structs::{destinations::Destination, Error, UrlParameter, Usage};
+use crate::structs::{destinations::Destination, routes::VariantKey, Error, UrlParameter, Usage};
 
 impl crate::TransitClient {
     /// Returns destinations for the requested variant. These destinations are important landmarks
@@ -26,11 +26,10 @@ impl crate::TransitClient {
     ///
     /// # Arguments
     ///
-    /// * `route`: The route for which to request Destinations. Is a [Variant]'s key
+    /// * `variant`: The key of the variant for which to request destinations, e.g. the
+    ///   [key](crate::structs::routes::Variant::key) of a variant
     /// * `usage`: If the API should yield shorter, longer, or normal names.
     ///
-    /// [Variant]: crate::structs::routes::Variant
-    ///
     /// returns: Result<Vec\<Destination\>, Error>
     ///
     /// # Examples
@@ -40,10 +39,15 @@ impl crate::TransitClient {
     ///
     /// let client = TransitClient::new("<YOUR_API_TOKEN>".to_string());
     /// # tokio_test::block_on(async {
-    /// let destinations = client.destinations("16-1-K", Usage::Normal).await.unwrap();
+    /// let variant: VariantKey = "16-1-K".parse().unwrap();
+    /// let destinations = client.destinations(&variant, Usage::Normal).await.unwrap();
     /// # })
     /// ```
-    pub async fn destinations(&self, route: &str, usage: Usage) -> Result<Vec<Destination>, Error> {
+    pub async fn destinations(
+        &self,
+        variant: &VariantKey,
+        usage: Usage,
+    ) -> Result<Vec<Destination>, Error> {
         #[derive(Debug, Deserialize)]
         struct Response {
             destinations: Vec<Destination>,
@@ -52,7 +56,7 @@ impl crate::TransitClient {
         let response = self
             .client
             .get(format!(
-                "{base}/variants/{route}/destinations.json?api-key={key}{usage}",
+                "{base}/variants/{variant}/destinations.json?api-key={key}{usage}",
                 base = self.base_url,
                 key = self.api_key,
                 usage = UrlParameter::from(usage),
@@ -76,7 +80,8 @@ mod test {
     #[tokio::test]
     async fn destinations() {
         let client = crate::testing_client();
-        let actual = client.destinations("16-1-K", Usage::Normal).await.unwrap();
+        let variant: VariantKey = "16-1-K".parse().unwrap();
+        let actual = client.destinations(&variant, Usage::Normal).await.unwrap();
         let expected = vec![
             Destination {
                 key: 5,
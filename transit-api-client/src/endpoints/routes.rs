@@ -149,11 +149,11 @@ mod test {
             },
             variants: Some(vec![
                 Variant {
-                    key: "25-0-U".to_string(),
+                    key: "25-0-U".parse().unwrap(),
                     name: None,
                 },
                 Variant {
-                    key: "25-1-D".to_string(),
+                    key: "25-1-D".parse().unwrap(),
                     name: None,
                 },
             ]),
@@ -185,31 +185,31 @@ mod test {
                 },
                 variants: Some(vec![
                     Variant {
-                        key: "19-1-A".to_string(),
+                        key: "19-1-A".parse().unwrap(),
                         name: None,
                     },
                     Variant {
-                        key: "19-0-L".to_string(),
+                        key: "19-0-L".parse().unwrap(),
                         name: None,
                     },
                     Variant {
-                        key: "19-0-N".to_string(),
+                        key: "19-0-N".parse().unwrap(),
                         name: None,
                     },
                     Variant {
-                        key: "19-1-D".to_string(),
+                        key: "19-1-D".parse().unwrap(),
                         name: None,
                     },
                     Variant {
-                        key: "19-0-E".to_string(),
+                        key: "19-0-E".parse().unwrap(),
                         name: None,
                     },
                     Variant {
-                        key: "19-1-N".to_string(),
+                        key: "19-1-N".parse().unwrap(),
                         name: None,
                     },
                     Variant {
-                        key: "19-0-#".to_string(),
+                        key: "19-0-#".parse().unwrap(),
                         name: None,
                     },
                 ]),
@@ -231,11 +231,11 @@ mod test {
                 },
                 variants: Some(vec![
                     Variant {
-                        key: "57-0-S".to_string(),
+                        key: "57-0-S".parse().unwrap(),
                         name: None,
                     },
                     Variant {
-                        key: "57-1-D".to_string(),
+                        key: "57-1-D".parse().unwrap(),
                         name: None,
                     },
                 ]),
@@ -266,15 +266,15 @@ mod test {
             },
             variants: Some(vec![
                 Variant {
-                    key: "BLUE-0-S".to_string(),
+                    key: "BLUE-0-S".parse().unwrap(),
                     name: None,
                 },
                 Variant {
-                    key: "BLUE-1-D".to_string(),
+                    key: "BLUE-1-D".parse().unwrap(),
                     name: None,
                 },
                 Variant {
-                    key: "BLUE-0-U".to_string(),
+                    key: "BLUE-0-U".parse().unwrap(),
                     name: None,
                 },
             ]),
@@ -151,7 +151,7 @@ mod test {
             title: "Blue Priority Service".to_string(),
             body: "Winnipeg Transit is operating a Blue Priority Service. Please check the website or call 311 for information on service delays and route cancellations. ".to_string(),
             category: Category::Transit,
-            updated_at: datetime!(2009-02-10 15:41:30),
+            updated_at: Some(datetime!(2009-02-10 15:41:30)),
         };
         log::info!("actual={:?}, expected:{:?}", &actual, &expected);
         assert_eq!(actual, expected);
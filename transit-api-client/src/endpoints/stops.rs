@@ -18,12 +18,14 @@
 
 use crate::filters;
 use serde::Deserialize;
+use time::{Date, Duration};
 
 use crate::structs::common::GeoLocation;
 use crate::structs::{
     stops::{Feature, PartialStop, Schedule, Stop},
     Error, UrlParameter, Usage,
 };
+use crate::timetable::Timetable;
 
 impl crate::TransitClient {
     /// Get information about a specific stop
@@ -197,6 +199,65 @@ impl crate::TransitClient {
         stop: u32,
         filters: Vec<filters::Stop>,
         usage: Usage,
+    ) -> Result<Schedule, Error> {
+        let (query, url_parameters) = filters::StopScheduleQuery::from_filters(filters);
+        let mut schedule = self
+            .fetch_stop_schedule(stop, url_parameters.0, usage)
+            .await?;
+        query.retain(&mut schedule);
+        Ok(schedule)
+    }
+
+    /// Returns the schedule information for the requested stop, with the settings of a
+    /// [StopScheduleQuery](filters::StopScheduleQuery).
+    ///
+    /// # Arguments
+    ///
+    /// * `stop`: They stop number to get information about
+    /// * `query`: The settings of the schedule. An [Error::InvalidQuery] is returned if they are
+    ///   invalid.
+    /// * `usage`: If the API should yield shorter, longer, or normal names.
+    ///
+    /// returns: Result<Schedule, Error>
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use time::macros::datetime;
+    /// use transit_api_client::prelude::*;
+    ///
+    /// # tokio_test::block_on(async {
+    /// let client = TransitClient::new("<YOUR_API_TOKEN>".to_string());
+    /// let query = filters::StopScheduleQuery::new()
+    ///     .start(datetime!(2023-06-01 23:00))
+    ///     .end(datetime!(2023-06-02 1:00))
+    ///     .variant("16-1-K".parse().unwrap());
+    /// let stop_schedule = client
+    ///     .query_stop_schedule(10168, &query, Usage::Normal)
+    ///     .await
+    ///     .unwrap();
+    /// # });
+    /// ```
+    pub async fn query_stop_schedule(
+        &self,
+        stop: u32,
+        query: &filters::StopScheduleQuery,
+        usage: Usage,
+    ) -> Result<Schedule, Error> {
+        query.validate().map_err(Error::InvalidQuery)?;
+        let mut schedule = self
+            .fetch_stop_schedule(stop, UrlParameter::from(query).0, usage)
+            .await?;
+        query.retain(&mut schedule);
+        Ok(schedule)
+    }
+
+    /// Requests the schedule of a stop with already formatted filters
+    async fn fetch_stop_schedule(
+        &self,
+        stop: u32,
+        url_parameters: String,
+        usage: Usage,
     ) -> Result<Schedule, Error> {
         #[derive(Debug, Deserialize)]
         struct Response {
@@ -204,12 +265,6 @@ impl crate::TransitClient {
             stop_schedule: Schedule,
         }
 
-        let mut url_parameters = String::new();
-        for filter in filters {
-            let parameter = UrlParameter::from(filter);
-            url_parameters.push_str(&parameter.0);
-        }
-
         let response = self
             .client
             .get(format!(
@@ -229,6 +284,57 @@ impl crate::TransitClient {
         Ok(out.stop_schedule)
     }
 
+    /// Returns the timetable of the requested stop for a whole day, which may be in the future.
+    ///
+    /// The schedule of the stop is requested in windows of three hours, which are stitched
+    /// together with [Timetable::stitch].
+    ///
+    /// # Arguments
+    ///
+    /// * `stop`: They stop number to get the timetable of
+    /// * `date`: The day of the timetable
+    /// * `usage`: If the API should yield shorter, longer, or normal names.
+    ///
+    /// returns: Result<Timetable, Error>
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use time::macros::date;
+    /// use transit_api_client::prelude::*;
+    ///
+    /// # tokio_test::block_on(async {
+    /// let client = TransitClient::new("<YOUR_API_TOKEN>".to_string());
+    /// let timetable = client
+    ///     .stop_timetable(10168, date!(2023-06-01), Usage::Normal)
+    ///     .await
+    ///     .unwrap();
+    /// # });
+    /// ```
+    pub async fn stop_timetable(
+        &self,
+        stop: u32,
+        date: Date,
+        usage: Usage,
+    ) -> Result<Timetable, Error> {
+        const WINDOW: Duration = Duration::hours(3);
+
+        let midnight = date.midnight();
+        let mut schedules = Vec::new();
+        let mut start = midnight;
+        while start < midnight + Duration::DAY {
+            let end = start + WINDOW;
+            let query = filters::StopScheduleQuery::new().start(start).end(end);
+            schedules.push(
+                self.query_stop_schedule(stop, &query, usage.clone())
+                    .await?,
+            );
+            start = end;
+        }
+
+        Ok(Timetable::stitch(date, schedules).expect("A day has at least one window"))
+    }
+
     /// Returns all stops in Winnipeg, using a non-official API
     ///
     /// The stops are not complete and only include position and an icon style.
@@ -287,7 +393,7 @@ impl PartialStop {
 #[cfg(test)]
 mod test {
     use crate::prelude::*;
-    use time::{macros::offset, OffsetDateTime};
+    use time::{macros::offset, OffsetDateTime, PrimitiveDateTime};
 
     #[tokio::test]
     async fn stop_features() {
@@ -391,6 +497,30 @@ mod test {
         assert_eq!(actual, expected);
     }
 
+    #[tokio::test]
+    async fn stop_info_usage() -> Result<(), crate::structs::Error> {
+        let client = crate::testing_client();
+        let normal = client.stop_info(10064, Usage::Normal).await?;
+        for usage in [Usage::Short, Usage::Long] {
+            let actual = client.stop_info(10064, usage.clone()).await?;
+            log::info!("usage={:?}, actual={:?}", usage, &actual);
+            assert_eq!(actual.key, normal.key);
+            assert_eq!(actual.direction, normal.direction);
+            assert_eq!(actual.street.key, normal.street.key);
+            assert_eq!(actual.centre, normal.centre);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stop_schedule_short() -> Result<(), crate::structs::Error> {
+        let client = crate::testing_client();
+        let actual = client.stop_schedule(10064, vec![], Usage::Short).await?;
+        assert_eq!(actual.stop.key, 10064);
+        assert!(!actual.route_schedules.is_empty());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn stops_nearby() {
         let client = crate::testing_client();
@@ -560,6 +690,48 @@ mod test {
         //assert_eq!(actual.route_schedules[1].scheduled_stops.len(), 3);
     }
 
+    #[tokio::test]
+    async fn query_stop_schedule() {
+        use time::ext::NumericalDuration;
+        let client = crate::testing_client();
+        let now = OffsetDateTime::now_utc().to_offset(offset!(-5));
+        let start = PrimitiveDateTime::new(now.date(), now.time());
+        let query = filters::StopScheduleQuery::new()
+            .start(start)
+            .end(start + 4.hours())
+            .route(BusType::Regular(18))
+            .variant("60-1-U".parse().unwrap())
+            .max_results_per_route(3);
+        let actual = client
+            .query_stop_schedule(10185, &query, Usage::Normal)
+            .await
+            .unwrap();
+
+        log::info!("actual={:?}", &actual);
+        assert_eq!(actual.stop.key, 10185);
+        for route_schedule in &actual.route_schedules {
+            assert!(route_schedule.scheduled_stops.len() <= 3);
+            if route_schedule.route.key != BusType::Regular(18) {
+                for scheduled_stop in &route_schedule.scheduled_stops {
+                    assert_eq!(scheduled_stop.variant.key.to_string(), "60-1-U");
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_stop_schedule_query() {
+        let client = crate::testing_client();
+        let query = filters::StopScheduleQuery::new().max_results_per_route(0);
+        let actual = client
+            .query_stop_schedule(10185, &query, Usage::Normal)
+            .await;
+        assert!(matches!(
+            actual,
+            Err(crate::structs::Error::InvalidQuery(_))
+        ));
+    }
+
     #[tokio::test]
     async fn get_all_stops() {
         let client = crate::testing_client();
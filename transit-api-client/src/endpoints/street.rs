@@ -27,7 +27,8 @@ impl crate::TransitClient {
     ///
     /// # Arguments
     ///
-    /// * `filters`: The filters to apply to the street search.
+    /// * `filters`: The filters to apply to the street search. Borrowed [filters::Street] and
+    ///   owned [filters::OwnedStreet] filters can be used.
     /// * `usage`: If the API should yield shorter, longer, or normal names.
     ///
     /// returns: Result<Vec<Street>, Error>
@@ -54,7 +55,7 @@ impl crate::TransitClient {
     /// ```
     pub async fn street(
         &self,
-        filters: Vec<filters::Street<'_>>,
+        filters: Vec<impl Into<filters::OwnedStreet>>,
         usage: Usage,
     ) -> Result<Vec<Street>, Error> {
         #[derive(Debug, Deserialize)]
@@ -64,7 +65,7 @@ impl crate::TransitClient {
 
         let mut filter_parameters = String::new();
         for filter in filters {
-            filter_parameters.push_str(UrlParameter::from(filter).0.as_str())
+            filter_parameters.push_str(UrlParameter::from(filter.into()).0.as_str())
         }
 
         let response = self
@@ -86,6 +87,44 @@ impl crate::TransitClient {
         Ok(out.streets)
     }
 
+    /// Searches for a street typed by a user, like `Portage Ave E`, and returns the results
+    /// best matching first.
+    ///
+    /// If nothing is found with the type and the leg of the query, the street is searched for
+    /// by its name alone.
+    ///
+    /// # Arguments
+    ///
+    /// * `query`: The parsed street to search for
+    /// * `usage`: If the API should yield shorter, longer, or normal names.
+    ///
+    /// returns: Result<Vec<Street>, Error>
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use transit_api_client::prelude::*;
+    ///
+    /// # tokio_test::block_on(async {
+    /// let client = TransitClient::new("<YOUR_API_TOKEN>".to_string());
+    /// let query: filters::StreetQuery = "Portage Ave E".parse().unwrap();
+    /// let streets = client.street_search(&query, Usage::Normal).await.unwrap();
+    /// # });
+    /// ```
+    pub async fn street_search(
+        &self,
+        query: &filters::StreetQuery,
+        usage: Usage,
+    ) -> Result<Vec<Street>, Error> {
+        let mut streets = self.street(query.filters(), usage.clone()).await?;
+        if streets.is_empty() && (query.street_type.is_some() || query.leg.is_some()) {
+            streets = self
+                .street(vec![filters::Street::Name(&query.name)], usage)
+                .await?;
+        }
+        Ok(query.rank(streets))
+    }
+
     /// Returns a street by the given key.
     ///
     /// # Arguments
@@ -212,6 +251,15 @@ mod test {
         assert_eq!(actual, expected);
     }
 
+    #[tokio::test]
+    async fn portage_east_search() {
+        let client = crate::testing_client();
+        let query: filters::StreetQuery = "Portage Ave E".parse().unwrap();
+        let actual = client.street_search(&query, Usage::Normal).await.unwrap();
+        log::info!("actual={:?}", &actual);
+        assert_eq!(actual[0].key, 2904);
+    }
+
     #[tokio::test]
     async fn street_by_key() {
         let client = crate::testing_client();
@@ -19,7 +19,7 @@
 use serde::Deserialize;
 
 use crate::filters;
-use crate::prelude::PartialLocation;
+use crate::prelude::OwnedPartialLocation;
 use crate::structs::{trip_planner::Plan, Error, UrlParameter, Usage};
 
 impl crate::TransitClient {
@@ -27,9 +27,13 @@ impl crate::TransitClient {
     ///
     /// # Arguments
     ///
-    /// * `origin`: A location, where the trip should start
+    /// * `origin`: A location, where the trip should start. Anything that converts into an
+    ///   [OwnedPartialLocation] can be used, like a
+    ///   [PartialLocation](crate::prelude::PartialLocation), a
+    ///   [Location](crate::prelude::Location) or a [Stop](crate::prelude::Stop).
     /// * `destination`: A location, where the trip should end
-    /// * `filters`: Any potential filters, to personalize the trip
+    /// * `filters`: Any potential filters, to personalize the trip. Every kind of filter can
+    ///   only be given once, otherwise an [Error::InvalidQuery] is returned.
     /// * `usage`: If the API should yield shorter, longer, or normal names.
     ///
     /// returns: Result<Vec<Plan, Global>, Error>
@@ -66,28 +70,77 @@ impl crate::TransitClient {
     /// ```
     pub async fn trip_planner(
         &self,
-        origin: PartialLocation<'_>,
-        destination: PartialLocation<'_>,
+        origin: impl Into<OwnedPartialLocation>,
+        destination: impl Into<OwnedPartialLocation>,
         filters: Vec<filters::TripPlan>,
         usage: Usage,
+    ) -> Result<Vec<Plan>, Error> {
+        let query = filters::TripPlanQuery::try_from(filters).map_err(Error::InvalidQuery)?;
+        self.plan_trip(origin, destination, &query, usage).await
+    }
+
+    /// Uses the Navigo engine to plan optimal trips from an origin to a destination, with the
+    /// settings of a [TripPlanQuery](filters::TripPlanQuery).
+    ///
+    /// # Arguments
+    ///
+    /// * `origin`: A location, where the trip should start
+    /// * `destination`: A location, where the trip should end
+    /// * `query`: The settings of the trip. An [Error::InvalidQuery] is returned if they are
+    ///   invalid.
+    /// * `usage`: If the API should yield shorter, longer, or normal names.
+    ///
+    /// returns: Result<Vec<Plan, Global>, Error>
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use time::{OffsetDateTime, macros::offset};
+    /// use transit_api_client::prelude::*;
+    ///
+    /// # tokio_test::block_on(async {
+    /// let client = TransitClient::new("<YOUR_API_TOKEN>".to_string());
+    /// let now = OffsetDateTime::now_utc().to_offset(offset!(-5));
+    /// let query = filters::TripPlanQuery::new()
+    ///     .date(now.date())
+    ///     .time(now.time())
+    ///     .mode(filters::Mode::DepartAfter)
+    ///     .max_transfers(2);
+    /// let trip_plan = client
+    ///     .plan_trip(
+    ///         PartialLocation::Point(49.86917, -97.1391),
+    ///         PartialLocation::Point(49.8327, -97.10887),
+    ///         &query,
+    ///         Usage::Normal,
+    ///     )
+    ///     .await
+    ///     .unwrap();
+    /// # });
+    /// ```
+    pub async fn plan_trip(
+        &self,
+        origin: impl Into<OwnedPartialLocation>,
+        destination: impl Into<OwnedPartialLocation>,
+        query: &filters::TripPlanQuery,
+        usage: Usage,
     ) -> Result<Vec<Plan>, Error> {
         #[derive(Debug, Deserialize)]
         struct Response {
             plans: Vec<Plan>,
         }
 
-        let mut filter_parameters = String::new();
-        for filter in filters {
-            filter_parameters.push_str(&UrlParameter::from(filter).to_string())
-        }
+        query.validate().map_err(Error::InvalidQuery)?;
+        let origin = origin.into();
+        let destination = destination.into();
 
         let response = self
             .client
             .get(format!(
-                "{base}/trip-planner.json?api-key={api_key}{usage}&origin={origin}&destination={destination}{filter_parameters}",
+                "{base}/trip-planner.json?api-key={api_key}{usage}&origin={origin}&destination={destination}{filters}",
                 base = self.base_url,
                 api_key = self.api_key,
                 usage = UrlParameter::from(usage),
+                filters = UrlParameter::from(query),
             ))
             .send()
             .await?;
@@ -104,6 +157,7 @@ impl crate::TransitClient {
 #[cfg(test)]
 mod test {
     use crate::prelude::*;
+    use crate::structs::Error;
     use time::{macros::offset, OffsetDateTime};
 
     #[tokio::test]
@@ -121,6 +175,45 @@ mod test {
         log::info!("actual={:?}", &actual);
     }
 
+    #[tokio::test]
+    async fn invalid_filters() {
+        let client = crate::testing_client();
+        let actual = client
+            .trip_planner(
+                PartialLocation::Point(49.86917, -97.1391),
+                PartialLocation::Point(49.8327, -97.10887),
+                vec![filters::TripPlan::Time(25, 99)],
+                Usage::Normal,
+            )
+            .await;
+        assert!(matches!(actual, Err(Error::InvalidQuery(_))));
+    }
+
+    #[tokio::test]
+    async fn query() {
+        let client = crate::testing_client();
+        let now = OffsetDateTime::now_utc().to_offset(offset!(-5));
+        let query = filters::TripPlanQuery::new()
+            .date(now.date())
+            .time(now.time())
+            .mode(filters::Mode::ArriveAfter)
+            .walk_speed(4.5)
+            .max_walk_time(10)
+            .min_transfer_wait(2)
+            .max_transfer_wait(15)
+            .max_transfers(2);
+        let actual = client
+            .plan_trip(
+                PartialLocation::Point(49.86917, -97.1391),
+                PartialLocation::Point(49.8327, -97.10887),
+                &query,
+                Usage::Normal,
+            )
+            .await
+            .unwrap();
+        log::info!("actual={:?}", &actual);
+    }
+
     #[tokio::test]
     async fn filters() {
         let client = crate::testing_client();
@@ -182,7 +182,7 @@ mod test {
             .await
             .unwrap();
         let expected = Variant {
-            key: "17-1-G".to_string(),
+            key: "17-1-G".parse().unwrap(),
             name: Some("McGregor to Garden City Centre".to_string()),
         };
 
@@ -0,0 +1,412 @@
+// TransitTrail - Navigate Winnipeg Transit with a different style
+// Copyright (C) - 2023 Foxx Azalea Pinkerton, Max Fehlinger
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Exports trip plans as iCalendar (`.ics`) files, which can be imported into most calendars.
+//!
+//! The calendar has one event for the whole trip, with every step of the trip in its description,
+//! and optionally one event for every ride on a bus. All times are in the `America/Winnipeg`
+//! time zone, which is included in the calendar.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use time::OffsetDateTime;
+//! use transit_api_client::export::calendar::{self, Options};
+//! use transit_api_client::prelude::*;
+//!
+//! # tokio_test::block_on(async {
+//! let client = TransitClient::new("<YOUR_API_TOKEN>".to_string());
+//! let plans = client.trip_planner(
+//!     PartialLocation::Point(49.86917, -97.1391),
+//!     PartialLocation::Point(49.8327, -97.10887),
+//!     Vec::new(),
+//!     Usage::Normal
+//! ).await.unwrap();
+//! let ics = calendar::to_ics(&plans[0], &Options::default(), OffsetDateTime::now_utc());
+//! std::fs::write("trip.ics", ics).unwrap();
+//! # });
+//! ```
+//!
+
+use time::{macros::format_description, Duration, OffsetDateTime, PrimitiveDateTime, UtcOffset};
+
+use crate::export::ride_name;
+use crate::structs::trip_planner::{Plan, Segment};
+
+/// The time zone of all times of the API
+const TIME_ZONE: &str = "America/Winnipeg";
+
+/// The definition of the [TIME_ZONE]: Central Standard Time, with daylight saving time from the
+/// second Sunday in March until the first Sunday in November.
+const VTIMEZONE: &[&str] = &[
+    "BEGIN:VTIMEZONE",
+    "TZID:America/Winnipeg",
+    "BEGIN:DAYLIGHT",
+    "TZOFFSETFROM:-0600",
+    "TZOFFSETTO:-0500",
+    "TZNAME:CDT",
+    "DTSTART:19700308T020000",
+    "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU",
+    "END:DAYLIGHT",
+    "BEGIN:STANDARD",
+    "TZOFFSETFROM:-0500",
+    "TZOFFSETTO:-0600",
+    "TZNAME:CST",
+    "DTSTART:19701101T020000",
+    "RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU",
+    "END:STANDARD",
+    "END:VTIMEZONE",
+];
+
+/// What goes into the calendar
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    /// Whether every ride on a bus gets its own event, besides the event of the whole trip
+    pub rides: bool,
+
+    /// How long before the trip starts a reminder goes off. No reminder, if [None].
+    pub reminder: Option<Duration>,
+}
+
+impl Default for Options {
+    /// Events for every ride, and a reminder 10 minutes before the trip
+    fn default() -> Self {
+        Self {
+            rides: true,
+            reminder: Some(Duration::minutes(10)),
+        }
+    }
+}
+
+/// Creates an iCalendar file of a trip plan.
+///
+/// # Arguments
+///
+/// * `plan`: The trip plan
+/// * `options`: What goes into the calendar
+/// * `now`: When the calendar is created
+///
+/// returns: String The contents of the `.ics` file
+pub fn to_ics(plan: &Plan, options: &Options, now: OffsetDateTime) -> String {
+    let mut lines: Vec<String> = [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//TransitTrail//Trip Planner//EN",
+        "CALSCALE:GREGORIAN",
+        "METHOD:PUBLISH",
+    ]
+    .iter()
+    .chain(VTIMEZONE)
+    .map(|line| line.to_string())
+    .collect();
+
+    let stamp = now
+        .to_offset(UtcOffset::UTC)
+        .format(format_description!(
+            "[year][month][day]T[hour][minute][second]Z"
+        ))
+        .unwrap();
+    let id = format!(
+        "{}-{}",
+        plan.times.start.map(local).unwrap_or_default(),
+        plan.times.end.map(local).unwrap_or_default()
+    );
+
+    // An event needs both ends, a plan without them only gets events for its rides
+    if let (Some(start), Some(end)) = (plan.times.start, plan.times.end) {
+        let origin = plan.segments.first().and_then(segment_from);
+        let destination = plan.segments.last().and_then(segment_to);
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:trip-{id}@transittrail"),
+            format!("DTSTAMP:{stamp}"),
+            format!("DTSTART;TZID={TIME_ZONE}:{}", local(start)),
+            format!("DTEND;TZID={TIME_ZONE}:{}", local(end)),
+            format!(
+                "SUMMARY:{}",
+                escape(&match &destination {
+                    Some(destination) => format!("Trip to {destination}"),
+                    None => "Trip".to_string(),
+                })
+            ),
+            format!("DESCRIPTION:{}", escape(&description(plan))),
+        ]);
+        if let Some(origin) = &origin {
+            lines.push(format!("LOCATION:{}", escape(origin)));
+        }
+        if let Some(reminder) = options.reminder {
+            lines.extend([
+                "BEGIN:VALARM".to_string(),
+                "ACTION:DISPLAY".to_string(),
+                format!("TRIGGER:-PT{}M", reminder.whole_minutes()),
+                format!(
+                    "DESCRIPTION:{}",
+                    escape(&match &origin {
+                        Some(origin) => format!("Leave {origin}"),
+                        None => "Leave for your trip".to_string(),
+                    })
+                ),
+                "END:VALARM".to_string(),
+            ]);
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+
+    if options.rides {
+        for (index, segment) in plan.segments.iter().enumerate() {
+            let ride = match segment {
+                Segment::Ride(ride) => ride,
+                _ => continue,
+            };
+            let (start, end) = match (ride.times.start, ride.times.end) {
+                (Some(start), Some(end)) => (start, end),
+                _ => continue,
+            };
+            let (from, to) = ride_stops(plan, index);
+            lines.extend([
+                "BEGIN:VEVENT".to_string(),
+                format!("UID:ride-{index}-{id}@transittrail"),
+                format!("DTSTAMP:{stamp}"),
+                format!("DTSTART;TZID={TIME_ZONE}:{}", local(start)),
+                format!("DTEND;TZID={TIME_ZONE}:{}", local(end)),
+                format!("SUMMARY:{}", escape(&ride_name(ride))),
+                format!(
+                    "DESCRIPTION:{}",
+                    escape(&format!(
+                        "From {} to {}",
+                        from.as_deref().unwrap_or("?"),
+                        to.as_deref().unwrap_or("?")
+                    ))
+                ),
+            ]);
+            if let Some(from) = from {
+                lines.push(format!("LOCATION:{}", escape(&from)));
+            }
+            lines.push("END:VEVENT".to_string());
+        }
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+    let mut ics: String = lines
+        .iter()
+        .map(|line| fold(line))
+        .collect::<Vec<_>>()
+        .join("\r\n");
+    ics.push_str("\r\n");
+    ics
+}
+
+/// Every step of the trip, one per line
+fn description(plan: &Plan) -> String {
+    let mut steps = Vec::new();
+    for (index, segment) in plan.segments.iter().enumerate() {
+        let start = match segment.times().start {
+            Some(start) => start
+                .format(format_description!("[hour]:[minute]"))
+                .unwrap(),
+            None => "?".to_string(),
+        };
+        let minutes = segment.times().durations.total;
+        let step = match segment {
+            Segment::Walk(walk) => {
+                let mut step = format!("{start} Walk for {minutes} min");
+                if let Some(from) = &walk.from {
+                    step.push_str(&format!(" from {}", from.name()));
+                }
+                if let Some(to) = &walk.to {
+                    step.push_str(&format!(" to {}", to.name()));
+                }
+                if let Some(instructions) = &walk.instructions {
+                    step.push_str(&format!(": {}", strip_html(instructions)));
+                }
+                step
+            }
+            Segment::Ride(ride) => {
+                let (from, to) = ride_stops(plan, index);
+                let mut step = format!("{start} Ride {} for {minutes} min", ride_name(ride));
+                if let Some(from) = from {
+                    step.push_str(&format!(" from {from}"));
+                }
+                if let Some(to) = to {
+                    step.push_str(&format!(" to {to}"));
+                }
+                step
+            }
+            Segment::Transfer(transfer) => format!(
+                "{start} Transfer from {} to {}",
+                transfer.from.name(),
+                transfer.to.name()
+            ),
+        };
+        steps.push(step);
+    }
+    steps.join("\n")
+}
+
+/// Where a ride starts and ends. Rides only have names of their stops, if the plan came from
+/// Google Maps; otherwise the stops are taken from the segments before and after the ride.
+fn ride_stops(plan: &Plan, index: usize) -> (Option<String>, Option<String>) {
+    let ride = match &plan.segments[index] {
+        Segment::Ride(ride) => ride,
+        _ => return (None, None),
+    };
+    let from = ride.from.clone().or_else(|| {
+        index
+            .checked_sub(1)
+            .and_then(|previous| plan.segments.get(previous))
+            .and_then(segment_to)
+    });
+    let to = ride
+        .to
+        .clone()
+        .or_else(|| plan.segments.get(index + 1).and_then(segment_from));
+    (from, to)
+}
+
+/// Where a segment starts
+fn segment_from(segment: &Segment) -> Option<String> {
+    match segment {
+        Segment::Walk(walk) => walk.from.as_ref().map(|from| from.name()),
+        Segment::Ride(ride) => ride.from.clone(),
+        Segment::Transfer(transfer) => Some(transfer.from.name()),
+    }
+}
+
+/// Where a segment ends
+fn segment_to(segment: &Segment) -> Option<String> {
+    match segment {
+        Segment::Walk(walk) => walk.to.as_ref().map(|to| to.name()),
+        Segment::Ride(ride) => ride.to.clone(),
+        Segment::Transfer(transfer) => Some(transfer.to.name()),
+    }
+}
+
+/// A local date and time, e.g. "20230601T070000"
+fn local(time: PrimitiveDateTime) -> String {
+    time.format(format_description!(
+        "[year][month][day]T[hour][minute][second]"
+    ))
+    .unwrap()
+}
+
+/// Removes the tags from the HTML instructions of Google Maps
+fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+                text.push(' ');
+            }
+            '>' => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Escapes text of a property
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            ';' => out.push_str("\\;"),
+            ',' => out.push_str("\\,"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Folds a line, so no line is longer than 75 bytes
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod test {
+    use crate::export::calendar::{self, fold, strip_html, Options};
+    use crate::export::test::plan;
+    use time::macros::datetime;
+    use time::Duration;
+
+    #[test]
+    fn to_ics() {
+        let now = datetime!(2023-06-01 6:00 -5);
+        let ics = calendar::to_ics(&plan(), &Options::default(), now);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.lines().all(|line| line.len() <= 76));
+        assert!(ics.contains("TZID:America/Winnipeg\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2);
+        assert!(ics.contains("DTSTAMP:20230601T110000Z\r\n"));
+        assert!(ics.contains("DTSTART;TZID=America/Winnipeg:20230601T070000\r\n"));
+        assert!(ics.contains("DTEND;TZID=America/Winnipeg:20230601T073000\r\n"));
+        assert!(ics.contains("SUMMARY:Trip to 49.8327\\, -97.10887\r\n"));
+        assert!(ics.contains("TRIGGER:-PT10M\r\n"));
+        assert!(ics.contains("SUMMARY:16 Selkirk-Osborne to Kingston Row\r\n"));
+        assert!(ics.contains("DTSTART;TZID=America/Winnipeg:20230601T070800\r\n"));
+        assert!(ics.contains("LOCATION:Northbound Osborne at Glasgow\r\n"));
+
+        let unfolded = ics.replace("\r\n ", "");
+        assert!(unfolded.contains(
+            "DESCRIPTION:From Northbound Osborne at Glasgow to Southbound Osborne at Kingston Row\r\n"
+        ));
+        assert!(unfolded.contains(
+            "\\n07:08 Ride 16 Selkirk-Osborne to Kingston Row for 17 min from Northbound Osborne \
+             at Glasgow to Southbound Osborne at Kingston Row\\n"
+        ));
+
+        let options = Options {
+            rides: false,
+            reminder: None,
+        };
+        let ics = calendar::to_ics(&plan(), &options, now);
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+        assert!(!ics.contains("BEGIN:VALARM"));
+    }
+
+    #[test]
+    fn helpers() {
+        assert_eq!(
+            strip_html("Head <b>north</b> on <b>Osborne&nbsp;St</b>"),
+            "Head north on Osborne St"
+        );
+        let line = "DESCRIPTION:".to_string() + &"é".repeat(40);
+        let folded = fold(&line);
+        assert!(folded.split("\r\n").all(|line| line.len() <= 75));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+        assert_eq!(Options::default().reminder, Some(Duration::minutes(10)));
+    }
+}
@@ -0,0 +1,691 @@
+// TransitTrail - Navigate Winnipeg Transit with a different style
+// Copyright (C) - 2023 Foxx Azalea Pinkerton, Max Fehlinger
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Exports stops and trip plans as GeoJSON, GPX and KML, to use them in GIS software like QGIS.
+//!
+//! Stops exported as GeoJSON keep all of their fields as properties, so they can be edited and
+//! imported again with [stops_from_geojson].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use transit_api_client::export::geo::{self, StopDetails};
+//! use transit_api_client::prelude::*;
+//!
+//! # tokio_test::block_on(async {
+//! let client = TransitClient::new("<YOUR_API_TOKEN>".to_string());
+//! let mut stops = Vec::new();
+//! for stop in [10064, 10642] {
+//!     let mut details = StopDetails::from(client.stop_info(stop, Usage::Normal).await.unwrap());
+//!     details.features = client.stop_features(stop, Usage::Normal).await.unwrap();
+//!     details.routes = client.routes_by_stop(stop, Usage::Normal).await.unwrap();
+//!     stops.push(details);
+//! }
+//! std::fs::write("stops.geojson", geo::stops_to_geojson(&stops)).unwrap();
+//! # });
+//! ```
+//!
+
+use std::fmt::Write;
+
+use serde::de::Error as _;
+use serde_json::{json, Map, Value};
+use time::{macros::format_description, PrimitiveDateTime};
+
+use crate::export::{escape_xml, ride_name};
+use crate::structs::{
+    common::GeoLocation,
+    routes::Route,
+    stops::{Feature, Stop},
+    trip_planner::{Bounds, Plan, Segment, TripStop},
+    Error,
+};
+
+/// A stop, with its features and the routes stopping there
+#[derive(Clone, Debug, PartialEq)]
+pub struct StopDetails {
+    /// The stop itself
+    pub stop: Stop,
+
+    /// The features of the stop, like benches or shelters
+    pub features: Vec<Feature>,
+
+    /// The routes stopping at the stop
+    pub routes: Vec<Route>,
+}
+
+impl From<Stop> for StopDetails {
+    fn from(stop: Stop) -> Self {
+        Self {
+            stop,
+            features: Vec::new(),
+            routes: Vec::new(),
+        }
+    }
+}
+
+impl StopDetails {
+    /// A short description, e.g. "Northbound, Nearside. Routes: 16, 18. Features: Bench"
+    fn description(&self) -> String {
+        let mut description = format!("{}, {}.", self.stop.direction, name(&self.stop.side));
+        if !self.routes.is_empty() {
+            let badges: Vec<String> = self.routes.iter().map(Route::badge_label).collect();
+            write!(description, " Routes: {}.", badges.join(", ")).unwrap();
+        }
+        if !self.features.is_empty() {
+            let features: Vec<&str> = self.features.iter().map(|f| f.name.as_str()).collect();
+            write!(description, " Features: {}.", features.join(", ")).unwrap();
+        }
+        description
+    }
+
+    /// The title of the stop, e.g. "#10064 Northbound Osborne at Glasgow"
+    fn title(&self) -> String {
+        format!("#{} {}", self.stop.number, self.stop.name)
+    }
+}
+
+/// The name of a value, as it is serialized, e.g. "Farside Opposite" for a side
+fn name(value: &impl serde::Serialize) -> String {
+    match serde_json::to_value(value) {
+        Ok(Value::String(name)) => name,
+        Ok(other) => other.to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+/// GeoJSON coordinates of a point: longitude first, then latitude
+fn coordinates(point: &GeoLocation) -> Value {
+    json!([point.longitude, point.latitude])
+}
+
+/// A GeoJSON polygon of the bounds
+fn polygon(bounds: &Bounds) -> Value {
+    let corners = bounds.corners();
+    let mut ring: Vec<Value> = corners.iter().map(coordinates).collect();
+    ring.push(coordinates(&corners[0]));
+    json!({"type": "Polygon", "coordinates": [ring]})
+}
+
+/// Exports stops as a GeoJSON feature collection of points.
+///
+/// Every field of the stop is a property, as well as its `features`, its `routes` and the
+/// `badges` of the routes.
+///
+/// # Arguments
+///
+/// * `stops`: The stops to export
+///
+/// returns: String
+pub fn stops_to_geojson(stops: &[StopDetails]) -> String {
+    let features: Vec<Value> = stops
+        .iter()
+        .map(|details| {
+            let mut properties = match serde_json::to_value(&details.stop) {
+                Ok(Value::Object(properties)) => properties,
+                _ => Map::new(),
+            };
+            properties.remove("centre");
+            properties.insert("features".to_string(), json!(details.features));
+            properties.insert("routes".to_string(), json!(details.routes));
+            let badges: Vec<String> = details.routes.iter().map(Route::badge_label).collect();
+            properties.insert("badges".to_string(), json!(badges));
+            json!({
+                "type": "Feature",
+                "id": details.stop.key,
+                "geometry": {"type": "Point", "coordinates": coordinates(&details.stop.centre)},
+                "properties": properties,
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&json!({"type": "FeatureCollection", "features": features}))
+        .unwrap()
+}
+
+/// Imports stops from a GeoJSON feature collection, as exported by [stops_to_geojson].
+///
+/// The position of a stop is taken from its geometry, so stops moved in a GIS program keep
+/// their new position.
+///
+/// # Arguments
+///
+/// * `geojson`: The GeoJSON document
+///
+/// returns: Result<Vec<StopDetails>, Error> [Error::Json], if the document is not a feature
+/// collection of stops
+pub fn stops_from_geojson(geojson: &str) -> Result<Vec<StopDetails>, Error> {
+    let document: Value = serde_json::from_str(geojson)?;
+    if document["type"] != "FeatureCollection" {
+        return Err(serde_json::Error::custom("Expected a GeoJSON FeatureCollection").into());
+    }
+    let features = document["features"]
+        .as_array()
+        .ok_or_else(|| serde_json::Error::custom("Expected features in the collection"))?;
+
+    features
+        .iter()
+        .map(|feature| -> Result<StopDetails, Error> {
+            let geometry = &feature["geometry"];
+            let position = match (&geometry["type"], geometry["coordinates"].as_array()) {
+                (Value::String(kind), Some(position)) if kind == "Point" => position,
+                _ => return Err(serde_json::Error::custom("Expected a stop to be a Point").into()),
+            };
+            let (longitude, latitude) = match (
+                position.first().and_then(Value::as_f64),
+                position.get(1).and_then(Value::as_f64),
+            ) {
+                (Some(longitude), Some(latitude)) => (longitude, latitude),
+                _ => return Err(serde_json::Error::custom("Invalid coordinates of a stop").into()),
+            };
+
+            let mut properties = match &feature["properties"] {
+                Value::Object(properties) => properties.clone(),
+                _ => return Err(serde_json::Error::custom("A stop has no properties").into()),
+            };
+            let features = match properties.remove("features") {
+                Some(features) => serde_json::from_value(features)?,
+                None => Vec::new(),
+            };
+            let routes = match properties.remove("routes") {
+                Some(routes) => serde_json::from_value(routes)?,
+                None => Vec::new(),
+            };
+            properties.remove("badges");
+            properties.insert(
+                "centre".to_string(),
+                serde_json::to_value(GeoLocation::new(latitude, longitude))?,
+            );
+
+            Ok(StopDetails {
+                stop: serde_json::from_value(Value::Object(properties))?,
+                features,
+                routes,
+            })
+        })
+        .collect()
+}
+
+/// The start of a GPX document
+const GPX_HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="TransitTrail" xmlns="http://www.topografix.com/GPX/1/1">
+"#;
+
+/// The start of a KML document
+const KML_HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<kml xmlns="http://www.opengis.net/kml/2.2">
+<Document>
+"#;
+
+/// Exports stops as waypoints of a GPX document.
+///
+/// # Arguments
+///
+/// * `stops`: The stops to export
+///
+/// returns: String
+pub fn stops_to_gpx(stops: &[StopDetails]) -> String {
+    let mut gpx = GPX_HEADER.to_string();
+    for details in stops {
+        let centre = &details.stop.centre;
+        writeln!(
+            gpx,
+            "<wpt lat=\"{}\" lon=\"{}\"><name>{}</name><desc>{}</desc><type>stop</type></wpt>",
+            centre.latitude,
+            centre.longitude,
+            escape_xml(&details.title()),
+            escape_xml(&details.description()),
+        )
+        .unwrap();
+    }
+    gpx.push_str("</gpx>\n");
+    gpx
+}
+
+/// Exports stops as placemarks of a KML document. The direction, side, features and badges of
+/// the routes of every stop are included as extended data.
+///
+/// # Arguments
+///
+/// * `stops`: The stops to export
+///
+/// returns: String
+pub fn stops_to_kml(stops: &[StopDetails]) -> String {
+    let mut kml = KML_HEADER.to_string();
+    kml.push_str("<name>Stops</name>\n");
+    for details in stops {
+        let badges: Vec<String> = details.routes.iter().map(Route::badge_label).collect();
+        let features: Vec<&str> = details.features.iter().map(|f| f.name.as_str()).collect();
+        let data = [
+            ("key", details.stop.key.to_string()),
+            ("direction", details.stop.direction.to_string()),
+            ("side", name(&details.stop.side)),
+            ("routes", badges.join(", ")),
+            ("features", features.join(", ")),
+        ];
+        writeln!(
+            kml,
+            "<Placemark><name>{}</name><description>{}</description><ExtendedData>{}</ExtendedData>{}</Placemark>",
+            escape_xml(&details.title()),
+            escape_xml(&details.description()),
+            kml_data(&data),
+            kml_point(&details.stop.centre),
+        )
+        .unwrap();
+    }
+    kml.push_str("</Document>\n</kml>\n");
+    kml
+}
+
+/// A stop or location of a plan, and where it is in the plan
+struct PlanPoint<'a> {
+    /// The index of the segment the point belongs to
+    segment: usize,
+
+    /// If the segment starts ("from") or ends ("to") at the point
+    role: &'static str,
+
+    /// The stop or location
+    stop: &'a TripStop,
+}
+
+/// All stops and locations of a plan, in order
+fn plan_points(plan: &Plan) -> Vec<PlanPoint<'_>> {
+    let mut points = Vec::new();
+    for (segment, part) in plan.segments.iter().enumerate() {
+        let (from, to) = match part {
+            Segment::Walk(walk) => (walk.from.as_ref(), walk.to.as_ref()),
+            Segment::Transfer(transfer) => (Some(&transfer.from), Some(&transfer.to)),
+            Segment::Ride(_) => (None, None),
+        };
+        for (role, stop) in [("from", from), ("to", to)] {
+            if let Some(stop) = stop {
+                points.push(PlanPoint {
+                    segment,
+                    role,
+                    stop,
+                });
+            }
+        }
+    }
+    points
+}
+
+/// The path along all points of a plan, without points that repeat the one before them
+fn plan_path<'a>(points: &[PlanPoint<'a>]) -> Vec<&'a GeoLocation> {
+    let mut path: Vec<&GeoLocation> = Vec::new();
+    for point in points {
+        let centre = point.stop.centre();
+        if path.last() != Some(&centre) {
+            path.push(centre);
+        }
+    }
+    path
+}
+
+/// What kind of segment it is, e.g. "walk"
+fn segment_type(segment: &Segment) -> &'static str {
+    match segment {
+        Segment::Walk(_) => "walk",
+        Segment::Ride(_) => "ride",
+        Segment::Transfer(_) => "transfer",
+    }
+}
+
+/// A name of the segment, e.g. "Walk 07:00-07:05"
+fn segment_name(segment: &Segment) -> String {
+    let format = format_description!("[hour]:[minute]");
+    let times = segment.times();
+    let kind = match segment {
+        Segment::Walk(_) => "Walk".to_string(),
+        Segment::Ride(ride) => format!("Ride {}", ride_name(ride)),
+        Segment::Transfer(_) => "Transfer".to_string(),
+    };
+    let hours = |time: Option<PrimitiveDateTime>| match time {
+        Some(time) => time.format(format).unwrap(),
+        None => "?".to_string(),
+    };
+    format!("{kind} {}-{}", hours(times.start), hours(times.end))
+}
+
+/// Exports a trip plan as a GeoJSON feature collection.
+///
+/// Every segment with known bounds becomes a polygon, every stop and location of a segment a
+/// point, and the path along the points a line string.
+///
+/// # Arguments
+///
+/// * `plan`: The trip plan to export
+///
+/// returns: String
+pub fn plan_to_geojson(plan: &Plan) -> String {
+    let datetime = format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]");
+    let mut features = Vec::new();
+
+    for (index, segment) in plan.segments.iter().enumerate() {
+        let bounds = match segment.bounds() {
+            Some(bounds) => bounds,
+            None => continue,
+        };
+        let times = segment.times();
+        let mut properties = json!({
+            "segment": index,
+            "type": segment_type(segment),
+            "name": segment_name(segment),
+            "start": times.start.map(|start| start.format(datetime).unwrap()),
+            "end": times.end.map(|end| end.format(datetime).unwrap()),
+            "minutes": times.durations.total,
+        });
+        if let Segment::Ride(ride) = segment {
+            properties["route"] = json!(ride.route.badge_label());
+            properties["variant"] = json!(ride.variant.key);
+        }
+        features.push(json!({
+            "type": "Feature",
+            "geometry": polygon(bounds),
+            "properties": properties,
+        }));
+    }
+
+    let points = plan_points(plan);
+    for point in &points {
+        let mut properties = json!({
+            "segment": point.segment,
+            "role": point.role,
+            "name": point.stop.name(),
+        });
+        if let TripStop::Stop(stop) = point.stop {
+            properties["stop"] = json!(stop.key);
+        }
+        features.push(json!({
+            "type": "Feature",
+            "geometry": {"type": "Point", "coordinates": coordinates(point.stop.centre())},
+            "properties": properties,
+        }));
+    }
+
+    let path = plan_path(&points);
+    if path.len() > 1 {
+        let line: Vec<Value> = path.into_iter().map(coordinates).collect();
+        features.push(json!({
+            "type": "Feature",
+            "geometry": {"type": "LineString", "coordinates": line},
+            "properties": {"type": "path"},
+        }));
+    }
+
+    serde_json::to_string_pretty(&json!({"type": "FeatureCollection", "features": features}))
+        .unwrap()
+}
+
+/// Exports a trip plan as a GPX document, with the bounds of the whole plan, every stop and
+/// location as a waypoint, and a route along them.
+///
+/// # Arguments
+///
+/// * `plan`: The trip plan to export
+///
+/// returns: String
+pub fn plan_to_gpx(plan: &Plan) -> String {
+    let mut gpx = GPX_HEADER.to_string();
+
+    let mut segments = plan.segments.iter().filter_map(Segment::bounds);
+    if let Some(first) = segments.next() {
+        let mut bounds = first.clone();
+        for segment in segments {
+            bounds.extend(&segment.minimum);
+            bounds.extend(&segment.maximum);
+        }
+        writeln!(
+            gpx,
+            "<metadata><bounds minlat=\"{}\" minlon=\"{}\" maxlat=\"{}\" maxlon=\"{}\"/></metadata>",
+            bounds.minimum.latitude,
+            bounds.minimum.longitude,
+            bounds.maximum.latitude,
+            bounds.maximum.longitude,
+        )
+        .unwrap();
+    }
+
+    let points = plan_points(plan);
+    for point in &points {
+        let centre = point.stop.centre();
+        writeln!(
+            gpx,
+            "<wpt lat=\"{}\" lon=\"{}\"><name>{}</name><desc>{}</desc></wpt>",
+            centre.latitude,
+            centre.longitude,
+            escape_xml(&point.stop.name()),
+            escape_xml(&segment_name(&plan.segments[point.segment])),
+        )
+        .unwrap();
+    }
+
+    gpx.push_str("<rte><name>Trip</name>\n");
+    for centre in plan_path(&points) {
+        writeln!(
+            gpx,
+            "<rtept lat=\"{}\" lon=\"{}\"/>",
+            centre.latitude, centre.longitude
+        )
+        .unwrap();
+    }
+    gpx.push_str("</rte>\n</gpx>\n");
+    gpx
+}
+
+/// Exports a trip plan as a KML document, with the bounds of every segment as a polygon, every
+/// stop and location as a point, and the path along them.
+///
+/// # Arguments
+///
+/// * `plan`: The trip plan to export
+///
+/// returns: String
+pub fn plan_to_kml(plan: &Plan) -> String {
+    let mut kml = KML_HEADER.to_string();
+    kml.push_str("<name>Trip</name>\n");
+
+    for (index, segment) in plan.segments.iter().enumerate() {
+        let bounds = match segment.bounds() {
+            Some(bounds) => bounds,
+            None => continue,
+        };
+        let corners = bounds.corners();
+        let ring: Vec<String> = corners
+            .iter()
+            .chain([&corners[0]])
+            .map(|corner| format!("{},{}", corner.longitude, corner.latitude))
+            .collect();
+        let data = [
+            ("segment", index.to_string()),
+            ("type", segment_type(segment).to_string()),
+        ];
+        writeln!(
+            kml,
+            "<Placemark><name>{}</name><ExtendedData>{}</ExtendedData><Polygon><outerBoundaryIs><LinearRing><coordinates>{}</coordinates></LinearRing></outerBoundaryIs></Polygon></Placemark>",
+            escape_xml(&segment_name(segment)),
+            kml_data(&data),
+            ring.join(" "),
+        )
+        .unwrap();
+    }
+
+    let points = plan_points(plan);
+    for point in &points {
+        let data = [
+            ("segment", point.segment.to_string()),
+            ("role", point.role.to_string()),
+        ];
+        writeln!(
+            kml,
+            "<Placemark><name>{}</name><ExtendedData>{}</ExtendedData>{}</Placemark>",
+            escape_xml(&point.stop.name()),
+            kml_data(&data),
+            kml_point(point.stop.centre()),
+        )
+        .unwrap();
+    }
+
+    let path: Vec<String> = plan_path(&points)
+        .into_iter()
+        .map(|point| format!("{},{}", point.longitude, point.latitude))
+        .collect();
+    if path.len() > 1 {
+        writeln!(
+            kml,
+            "<Placemark><name>Path</name><LineString><coordinates>{}</coordinates></LineString></Placemark>",
+            path.join(" ")
+        )
+        .unwrap();
+    }
+
+    kml.push_str("</Document>\n</kml>\n");
+    kml
+}
+
+/// The extended data of a KML placemark
+fn kml_data(data: &[(&str, String)]) -> String {
+    data.iter()
+        .map(|(name, value)| {
+            format!(
+                "<Data name=\"{name}\"><value>{}</value></Data>",
+                escape_xml(value)
+            )
+        })
+        .collect()
+}
+
+/// A KML point
+fn kml_point(point: &GeoLocation) -> String {
+    format!(
+        "<Point><coordinates>{},{}</coordinates></Point>",
+        point.longitude, point.latitude
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::export::geo::{self, StopDetails};
+    use crate::export::test::plan;
+    use crate::prelude::*;
+    use crate::structs::stops;
+    use serde_json::Value;
+
+    fn stop_details() -> StopDetails {
+        let stop = Stop {
+            side: Side::FarsideOpposite,
+            ..serde_json::from_str(stops::test::STOP).unwrap()
+        };
+        let route: Route = serde_json::from_str(stops::test::ROUTE).unwrap();
+        StopDetails {
+            stop,
+            features: vec![Feature {
+                name: "Bench".to_string(),
+                count: 1,
+            }],
+            routes: vec![route],
+        }
+    }
+
+    #[test]
+    fn stops_geojson() -> Result<(), crate::structs::Error> {
+        let stops = vec![stop_details()];
+        let geojson = geo::stops_to_geojson(&stops);
+
+        let document: Value = serde_json::from_str(&geojson)?;
+        let feature = &document["features"][0];
+        assert_eq!(feature["geometry"]["coordinates"][0], -97.1375);
+        assert_eq!(feature["geometry"]["coordinates"][1], 49.86912);
+        assert_eq!(feature["properties"]["direction"], "Northbound");
+        assert_eq!(feature["properties"]["side"], "Farside Opposite");
+        assert_eq!(feature["properties"]["badges"][0], "16");
+        assert_eq!(feature["properties"]["features"][0]["name"], "Bench");
+
+        assert_eq!(geo::stops_from_geojson(&geojson)?, stops);
+
+        // Moved in a GIS program
+        let moved = geojson.replace("-97.1375", "-97.1376");
+        let imported = geo::stops_from_geojson(&moved)?;
+        assert_eq!(imported[0].stop.centre.longitude, -97.1376);
+
+        assert!(geo::stops_from_geojson(r#"{"type": "Feature"}"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn stops_gpx_kml() {
+        let stops = vec![stop_details()];
+        let gpx = geo::stops_to_gpx(&stops);
+        assert!(gpx.contains(
+            "<wpt lat=\"49.86912\" lon=\"-97.1375\"><name>#10064 Northbound Osborne at Glasgow</name>\
+             <desc>Northbound, Farside Opposite. Routes: 16. Features: Bench.</desc><type>stop</type></wpt>"
+        ));
+        assert!(gpx.ends_with("</gpx>\n"));
+
+        let kml = geo::stops_to_kml(&stops);
+        assert!(kml.contains("<Data name=\"routes\"><value>16</value></Data>"));
+        assert!(kml.contains("<Point><coordinates>-97.1375,49.86912</coordinates></Point>"));
+        assert!(kml.ends_with("</Document>\n</kml>\n"));
+    }
+
+    #[test]
+    fn plan_exports() -> Result<(), crate::structs::Error> {
+        let plan = plan();
+
+        let document: Value = serde_json::from_str(&geo::plan_to_geojson(&plan))?;
+        let features = document["features"].as_array().unwrap();
+        let kinds: Vec<&str> = features
+            .iter()
+            .map(|feature| feature["geometry"]["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "Polygon",
+                "Polygon",
+                "Polygon",
+                "Point",
+                "Point",
+                "Point",
+                "Point",
+                "LineString"
+            ]
+        );
+        assert_eq!(features[1]["properties"]["route"], "16");
+        assert_eq!(features[1]["properties"]["variant"], "16-1-K");
+        assert_eq!(features[4]["properties"]["stop"], 10064);
+        // The ride has no stops of its own, so the path goes from the origin over both stops
+        assert_eq!(
+            features[7]["geometry"]["coordinates"]
+                .as_array()
+                .unwrap()
+                .len(),
+            4
+        );
+
+        let gpx = geo::plan_to_gpx(&plan);
+        assert!(gpx.contains(
+            "<bounds minlat=\"49.8327\" minlon=\"-97.1391\" maxlat=\"49.86917\" maxlon=\"-97.10887\"/>"
+        ));
+        assert_eq!(gpx.matches("<wpt ").count(), 4);
+        assert_eq!(gpx.matches("<rtept ").count(), 4);
+
+        let kml = geo::plan_to_kml(&plan);
+        assert_eq!(kml.matches("<Polygon>").count(), 3);
+        assert!(kml.contains("<name>Ride 16 Selkirk-Osborne to Kingston Row 07:08-07:25</name>"));
+        assert!(kml.contains("<LineString>"));
+        Ok(())
+    }
+}
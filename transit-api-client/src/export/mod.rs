@@ -0,0 +1,151 @@
+// TransitTrail - Navigate Winnipeg Transit with a different style
+// Copyright (C) - 2023 Foxx Azalea Pinkerton, Max Fehlinger
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Exports data of the API to formats other programs understand.
+//!
+
+use crate::structs::trip_planner::Ride;
+
+pub mod calendar;
+pub mod geo;
+pub mod timetable;
+
+/// The badge and the variant of a ride, e.g. "16 Selkirk-Osborne to Kingston Row"
+pub(crate) fn ride_name(ride: &Ride) -> String {
+    let label = ride.route.badge_label();
+    match ride.variant.name.as_deref().or_else(|| ride.route.name()) {
+        Some(name) => format!("{label} {name}"),
+        None => label,
+    }
+}
+
+/// Escapes text for HTML and XML documents
+pub(crate) fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escapes a field of a CSV file, quoting it if needed
+pub(crate) fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::export::{escape_csv, escape_xml};
+    use crate::prelude::*;
+    use crate::structs::trip_planner::{Bounds, Durations, Location, Stop, Times, TripStop};
+    use time::macros::datetime;
+    use time::PrimitiveDateTime;
+
+    fn times(start: PrimitiveDateTime, end: PrimitiveDateTime) -> Times {
+        let total = (end - start).whole_minutes() as u32;
+        Times {
+            start: Some(start),
+            end: Some(end),
+            durations: Durations {
+                total,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn bounds(from: &TripStop, to: &TripStop) -> Option<Bounds> {
+        Bounds::from_points([from.centre(), to.centre()])
+    }
+
+    /// A plan from near Osborne Village to near Kingston Row, walking to route 16
+    pub(crate) fn plan() -> trip::Plan {
+        let stop = |key: u32, name: &str, latitude: f64, longitude: f64| {
+            TripStop::Stop(Stop {
+                key,
+                name: name.to_string(),
+                centre: GeoLocation::new(latitude, longitude),
+            })
+        };
+        let origin = TripStop::Origin(Location::Point(GeoLocation::new(49.86917, -97.1391)));
+        let first = stop(10064, "Northbound Osborne at Glasgow", 49.86912, -97.1375);
+        let second = stop(
+            10642,
+            "Southbound Osborne at Kingston Row",
+            49.83441,
+            -97.11219,
+        );
+        let destination =
+            TripStop::Destination(Location::Point(GeoLocation::new(49.8327, -97.10887)));
+        trip::Plan {
+            times: times(datetime!(2023-06-01 7:00), datetime!(2023-06-01 7:30)),
+            segments: vec![
+                trip::Segment::Walk(trip::Walk {
+                    bounds: bounds(&origin, &first),
+                    from: Some(origin),
+                    to: Some(first.clone()),
+                    times: times(datetime!(2023-06-01 7:00), datetime!(2023-06-01 7:05)),
+                    ..Default::default()
+                }),
+                trip::Segment::Ride(trip::Ride {
+                    bounds: bounds(&first, &second),
+                    route: Route::Regular(Regular {
+                        key: 16,
+                        number: 16,
+                        badge_label: 16,
+                        name: "Route 16 Selkirk-Osborne".to_string(),
+                        ..Default::default()
+                    }),
+                    variant: Variant {
+                        key: "16-1-K".parse().unwrap(),
+                        name: Some("Selkirk-Osborne to Kingston Row".to_string()),
+                    },
+                    times: times(datetime!(2023-06-01 7:08), datetime!(2023-06-01 7:25)),
+                    ..Default::default()
+                }),
+                trip::Segment::Walk(trip::Walk {
+                    bounds: bounds(&second, &destination),
+                    from: Some(second),
+                    to: Some(destination),
+                    times: times(datetime!(2023-06-01 7:25), datetime!(2023-06-01 7:30)),
+                    ..Default::default()
+                }),
+            ],
+        }
+    }
+
+    #[test]
+    fn escape() {
+        assert_eq!(
+            escape_xml("Portage & Main <\"'>"),
+            "Portage &amp; Main &lt;&quot;&#39;&gt;"
+        );
+        assert_eq!(escape_csv("Osborne"), "Osborne");
+        assert_eq!(
+            escape_csv("Osborne, \"Village\""),
+            "\"Osborne, \"\"Village\"\"\""
+        );
+    }
+}
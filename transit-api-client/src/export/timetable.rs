@@ -0,0 +1,538 @@
+// TransitTrail - Navigate Winnipeg Transit with a different style
+// Copyright (C) - 2023 Foxx Azalea Pinkerton, Max Fehlinger
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Printable timetables of a stop, as HTML, CSV or PDF.
+//!
+//! Every route gets a header with its badge, then a row for every hour with the minutes buses
+//! depart. Departures of variants other than the most common one are marked with a letter, which
+//! is explained in the footnotes of the route.
+//!
+//! The PDF is written by hand, using only the fonts every PDF reader has, so no other crates or
+//! programs are needed.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use time::macros::date;
+//! use transit_api_client::export::timetable;
+//! use transit_api_client::prelude::*;
+//!
+//! # tokio_test::block_on(async {
+//! let client = TransitClient::new("<YOUR_API_TOKEN>".to_string());
+//! let stop_timetable = client.stop_timetable(10064, date!(2023-06-01), Usage::Normal).await.unwrap();
+//! std::fs::write("10064.pdf", timetable::to_pdf(&stop_timetable)).unwrap();
+//! # });
+//! ```
+//!
+
+use std::fmt::Write;
+
+use time::macros::format_description;
+
+use crate::export::{escape_csv, escape_xml};
+use crate::structs::{routes::Variant, stops::FoxxRoute};
+use crate::timetable::{RouteTimetable, Timetable};
+
+/// The mark of a variant in a timetable. The most common variant, the first one, is unmarked;
+/// the others are marked with "a", "b", ... "z", "aa", ...
+fn mark(variant: usize) -> String {
+    if variant == 0 {
+        return String::new();
+    }
+    let letter = char::from(b'a' + ((variant - 1) % 26) as u8);
+    letter.to_string().repeat((variant - 1) / 26 + 1)
+}
+
+/// Explains a variant in the footnotes of a route
+fn footnote(variant: usize, details: &Variant) -> String {
+    let mark = match variant {
+        0 => "Unmarked".to_string(),
+        _ => mark(variant),
+    };
+    match &details.name {
+        Some(name) => format!("{mark}: {} {name}", details.key),
+        None => format!("{mark}: {}", details.key),
+    }
+}
+
+/// The title of a timetable, e.g. "#10064 Northbound Osborne at Glasgow"
+fn title(timetable: &Timetable) -> String {
+    let stop = &timetable.schedule.stop;
+    format!("#{} {}", stop.number, stop.name)
+}
+
+/// The day of a timetable, e.g. "Thursday, June 1, 2023"
+fn day(timetable: &Timetable) -> String {
+    timetable
+        .date
+        .format(format_description!(
+            "[weekday], [month repr:long] [day padding:none], [year]"
+        ))
+        .unwrap()
+}
+
+/// The name of a route, without its badge
+fn route_name(route: &FoxxRoute) -> String {
+    route
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("Route {}", route.badge_label))
+}
+
+/// Renders a timetable as a standalone HTML page.
+///
+/// # Arguments
+///
+/// * `timetable`: The timetable of a stop
+///
+/// returns: String
+pub fn to_html(timetable: &Timetable) -> String {
+    let mut html = String::new();
+    let title = escape_xml(&title(timetable));
+    writeln!(
+        html,
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ font-family: sans-serif; }}
+.badge {{ display: inline-block; padding: 0 0.4em; border: 2px solid; border-radius: 0.3em; font-weight: bold; }}
+table {{ border-collapse: collapse; }}
+th, td {{ padding: 0.1em 0.6em; text-align: left; border-bottom: 1px solid #d9d9d9; }}
+.footnotes {{ font-size: smaller; list-style: none; padding: 0; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<p>{}</p>"#,
+        escape_xml(&day(timetable))
+    )
+    .unwrap();
+
+    for route in timetable.routes() {
+        let style = &route.route.badge_style;
+        writeln!(
+            html,
+            r#"<section class="route">
+<h2><span class="badge" style="color: {}; background-color: {}; border-color: {};">{}</span> {}</h2>
+<table>"#,
+            escape_xml(&style.color),
+            escape_xml(&style.background_color),
+            escape_xml(&style.border_color),
+            escape_xml(&route.route.badge_label.to_string()),
+            escape_xml(&route_name(route.route)),
+        )
+        .unwrap();
+        for hour in &route.hours {
+            let minutes: Vec<String> = hour
+                .departures
+                .iter()
+                .map(|entry| match mark(entry.variant).as_str() {
+                    "" => format!("{:02}", entry.minute()),
+                    mark => format!("{:02}<sup>{mark}</sup>", entry.minute()),
+                })
+                .collect();
+            writeln!(
+                html,
+                "<tr><th>{:02}</th><td>{}</td></tr>",
+                hour.hour,
+                minutes.join(" ")
+            )
+            .unwrap();
+        }
+        html.push_str("</table>\n<ul class=\"footnotes\">\n");
+        for (index, variant) in route.variants.iter().enumerate() {
+            writeln!(html, "<li>{}</li>", escape_xml(&footnote(index, variant))).unwrap();
+        }
+        html.push_str("</ul>\n</section>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Renders a timetable as CSV, with one departure per line.
+///
+/// The columns are `route`, `variant`, `variant name`, `mark`, `date` and `time`.
+///
+/// # Arguments
+///
+/// * `timetable`: The timetable of a stop
+///
+/// returns: String
+pub fn to_csv(timetable: &Timetable) -> String {
+    let mut csv = String::from("route,variant,variant name,mark,date,time\n");
+    for route in timetable.routes() {
+        for entry in route.hours.iter().flat_map(|hour| &hour.departures) {
+            let variant = route.variants[entry.variant];
+            writeln!(
+                csv,
+                "{},{},{},{},{},{}",
+                escape_csv(&route.route.badge_label.to_string()),
+                escape_csv(&variant.key.to_string()),
+                escape_csv(variant.name.as_deref().unwrap_or_default()),
+                mark(entry.variant),
+                entry
+                    .time
+                    .format(format_description!("[year]-[month]-[day]"))
+                    .unwrap(),
+                entry
+                    .time
+                    .format(format_description!("[hour]:[minute]"))
+                    .unwrap(),
+            )
+            .unwrap();
+        }
+    }
+    csv
+}
+
+/// Renders a timetable as a PDF document, on as many letter sized pages as needed.
+///
+/// # Arguments
+///
+/// * `timetable`: The timetable of a stop
+///
+/// returns: Vec<u8> The bytes of the PDF file
+pub fn to_pdf(timetable: &Timetable) -> Vec<u8> {
+    /// How many departures fit on one line
+    const PER_LINE: usize = 16;
+
+    let mut pdf = Pdf::new();
+    pdf.advance(20.0);
+    pdf.text(MARGIN, 16.0, true, BLACK, &title(timetable));
+    pdf.advance(18.0);
+    pdf.text(MARGIN, 11.0, false, BLACK, &day(timetable));
+    pdf.advance(10.0);
+
+    for route in timetable.routes() {
+        pdf_route_header(&mut pdf, &route);
+        for hour in &route.hours {
+            for (line, departures) in hour.departures.chunks(PER_LINE).enumerate() {
+                pdf.advance(14.0);
+                if line == 0 {
+                    pdf.text(MARGIN, 10.0, true, BLACK, &format!("{:02}", hour.hour));
+                }
+                for (column, entry) in departures.iter().enumerate() {
+                    let minute = format!("{:02}{}", entry.minute(), mark(entry.variant));
+                    let x = MARGIN + 30.0 + column as f32 * 30.0;
+                    pdf.text(x, 10.0, false, BLACK, &minute);
+                }
+            }
+        }
+        pdf.advance(4.0);
+        for (index, variant) in route.variants.iter().enumerate() {
+            pdf.advance(11.0);
+            pdf.text(MARGIN, 8.0, false, BLACK, &footnote(index, variant));
+        }
+        pdf.advance(14.0);
+    }
+
+    pdf.finish()
+}
+
+/// Draws the badge and the name of a route
+fn pdf_route_header(pdf: &mut Pdf, route: &RouteTimetable) {
+    let style = &route.route.badge_style;
+    let label = route.route.badge_label.to_string();
+    let width = label.chars().count() as f32 * 7.0 + 8.0;
+
+    pdf.advance(26.0);
+    let y = pdf.y;
+    pdf.rect(
+        MARGIN,
+        y - 4.0,
+        width,
+        16.0,
+        rgb(&style.background_color).unwrap_or(WHITE),
+        rgb(&style.border_color).unwrap_or(BLACK),
+    );
+    let colour = rgb(&style.color).unwrap_or(BLACK);
+    pdf.text(MARGIN + 4.0, 11.0, true, colour, &label);
+    pdf.text(
+        MARGIN + width + 8.0,
+        12.0,
+        true,
+        BLACK,
+        &route_name(route.route),
+    );
+    pdf.advance(4.0);
+}
+
+/// A colour as red, green and blue between 0 and 1
+type Rgb = (f32, f32, f32);
+
+const BLACK: Rgb = (0.0, 0.0, 0.0);
+const WHITE: Rgb = (1.0, 1.0, 1.0);
+
+/// Parses a CSS colour like "#ffcc00" or "#fc0"
+fn rgb(colour: &str) -> Option<Rgb> {
+    let hex = colour.trim().strip_prefix('#')?;
+    let channel = |hex: &str| u8::from_str_radix(hex, 16).ok().map(|c| c as f32 / 255.0);
+    match hex.len() {
+        6 => Some((
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        )),
+        3 => Some((
+            channel(&hex[0..1].repeat(2))?,
+            channel(&hex[1..2].repeat(2))?,
+            channel(&hex[2..3].repeat(2))?,
+        )),
+        _ => None,
+    }
+}
+
+/// The width of a letter sized page, in points
+const PAGE_WIDTH: f32 = 612.0;
+
+/// The height of a letter sized page, in points
+const PAGE_HEIGHT: f32 = 792.0;
+
+/// The space around the content of a page, in points
+const MARGIN: f32 = 54.0;
+
+/// A minimal PDF writer, which only knows text in Helvetica and rectangles
+struct Pdf {
+    /// The content streams of the finished pages
+    pages: Vec<String>,
+
+    /// The content stream of the current page
+    content: String,
+
+    /// Where the next line goes on the current page, from the bottom
+    y: f32,
+}
+
+impl Pdf {
+    fn new() -> Self {
+        Self {
+            pages: Vec::new(),
+            content: String::new(),
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    /// Moves down by `height`, on a new page if the current one is full
+    fn advance(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            self.pages.push(std::mem::take(&mut self.content));
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+        self.y -= height;
+    }
+
+    /// Writes text on the current line
+    fn text(&mut self, x: f32, size: f32, bold: bool, colour: Rgb, text: &str) {
+        writeln!(
+            self.content,
+            "BT /F{} {size} Tf {:.3} {:.3} {:.3} rg {x:.2} {:.2} Td ({}) Tj ET",
+            if bold { 2 } else { 1 },
+            colour.0,
+            colour.1,
+            colour.2,
+            self.y,
+            escape_pdf(text)
+        )
+        .unwrap();
+    }
+
+    /// Draws a filled rectangle with a border
+    fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, fill: Rgb, stroke: Rgb) {
+        writeln!(
+            self.content,
+            "{:.3} {:.3} {:.3} rg {:.3} {:.3} {:.3} RG 1 w {x:.2} {y:.2} {width:.2} {height:.2} re B",
+            fill.0, fill.1, fill.2, stroke.0, stroke.1, stroke.2
+        )
+        .unwrap();
+    }
+
+    /// Writes the document
+    fn finish(mut self) -> Vec<u8> {
+        if !self.content.is_empty() || self.pages.is_empty() {
+            self.pages.push(self.content);
+        }
+
+        // 1: catalog, 2: pages, 3 and 4: fonts, then a page and its content for every page
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                (0..self.pages.len())
+                    .map(|page| format!("{} 0 R", 5 + 2 * page))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                self.pages.len()
+            ),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_string(),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+                .to_string(),
+        ];
+        for (page, content) in self.pages.iter().enumerate() {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                6 + 2 * page
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{content}endstream",
+                content.len()
+            ));
+        }
+
+        let mut out = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n{object}\nendobj\n", index + 1).as_bytes());
+        }
+        let xref = out.len();
+        let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            writeln!(trailer, "{offset:010} 00000 n ").unwrap();
+        }
+        write!(
+            trailer,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .unwrap();
+        out.extend_from_slice(trailer.as_bytes());
+        out
+    }
+}
+
+/// Escapes text for a PDF string in WinAnsi encoding. Letters outside of Latin-1 are replaced by
+/// a question mark.
+fn escape_pdf(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            ' '..='~' => out.push(c),
+            '\u{a0}'..='\u{ff}' => write!(out, "\\{:03o}", c as u32).unwrap(),
+            _ => out.push('?'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use crate::export::timetable::{self, escape_pdf, mark, rgb};
+    use crate::structs::stops::{self, test::departure};
+    use crate::timetable::Timetable;
+    use time::macros::{date, datetime};
+
+    fn stop_timetable() -> Timetable {
+        let schedule = stops::test::schedule(vec![
+            departure(
+                "1",
+                "16-1-K",
+                datetime!(2023-06-01 6:50),
+                datetime!(2023-06-01 6:50),
+            ),
+            departure(
+                "2",
+                "16-0-D",
+                datetime!(2023-06-01 7:05),
+                datetime!(2023-06-01 7:05),
+            ),
+            departure(
+                "3",
+                "16-1-K",
+                datetime!(2023-06-01 7:20),
+                datetime!(2023-06-01 7:20),
+            ),
+        ]);
+        Timetable::stitch(date!(2023 - 06 - 01), vec![schedule]).unwrap()
+    }
+
+    #[test]
+    fn html() {
+        let html = timetable::to_html(&stop_timetable());
+        assert!(html.contains("<h1>#10064 Northbound Osborne at Glasgow</h1>"));
+        assert!(html.contains("<p>Thursday, June 1, 2023</p>"));
+        assert!(html.contains(
+            r#"style="color: #000000; background-color: #ffffff; border-color: #d9d9d9;">16</span>"#
+        ));
+        assert!(html.contains("<tr><th>06</th><td>50</td></tr>"));
+        assert!(html.contains("<tr><th>07</th><td>05<sup>a</sup> 20</td></tr>"));
+        assert!(html.contains("<li>Unmarked: 16-1-K Selkirk-Osborne to Kingston Row</li>"));
+        assert!(
+            html.contains("<li>a: 16-0-D Selkirk-Osborne to Downtown, via Portage &amp; Main</li>")
+        );
+    }
+
+    #[test]
+    fn csv() {
+        let csv = timetable::to_csv(&stop_timetable());
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "route,variant,variant name,mark,date,time",
+                "16,16-1-K,Selkirk-Osborne to Kingston Row,,2023-06-01,06:50",
+                "16,16-0-D,\"Selkirk-Osborne to Downtown, via Portage & Main\",a,2023-06-01,07:05",
+                "16,16-1-K,Selkirk-Osborne to Kingston Row,,2023-06-01,07:20",
+            ]
+        );
+    }
+
+    #[test]
+    fn pdf() {
+        let pdf = timetable::to_pdf(&stop_timetable());
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.4\n"));
+        assert!(text.ends_with("%%EOF\n"));
+        assert!(text.contains("/Count 1"));
+        assert!(text.contains("(#10064 Northbound Osborne at Glasgow) Tj"));
+        assert!(text.contains("(05a) Tj"));
+
+        // The cross-reference table points at the objects
+        let xref: usize = text
+            .lines()
+            .skip_while(|line| *line != "startxref")
+            .nth(1)
+            .unwrap()
+            .parse()
+            .unwrap();
+        let table = std::str::from_utf8(&pdf[xref..]).unwrap();
+        assert!(table.starts_with("xref\n0 7\n"));
+        let first: usize = table.lines().nth(3).unwrap()[..10].parse().unwrap();
+        assert!(pdf[first..].starts_with(b"1 0 obj"));
+    }
+
+    #[test]
+    fn helpers() {
+        assert_eq!(mark(0), "");
+        assert_eq!(mark(1), "a");
+        assert_eq!(mark(27), "aa");
+        assert_eq!(rgb("#ffffff"), Some((1.0, 1.0, 1.0)));
+        assert_eq!(rgb("#f00"), Some((1.0, 0.0, 0.0)));
+        assert_eq!(rgb("red"), None);
+        assert_eq!(
+            escape_pdf("Portage (Main) \\ Café ☺"),
+            "Portage \\(Main\\) \\\\ Caf\\351 ?"
+        );
+    }
+}
@@ -17,11 +17,15 @@
 //!
 
 use std::fmt::Display;
+use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
-use time::{macros::format_description, Date};
+use time::{macros::format_description, Date, PrimitiveDateTime, Time};
 
-use crate::structs::common::StreetLeg;
+use crate::search::words;
+use crate::structs::common::{Street as FoundStreet, StreetLeg};
+use crate::structs::routes::VariantKey;
+use crate::structs::stops::{BusType, Schedule};
 use crate::structs::{
     service_advisories::{Category, Priority},
     UrlParameter,
@@ -142,11 +146,183 @@ impl Display for Mode {
             Self::DepartBefore => write!(f, "depart-before"),
             Self::DepartAfter => write!(f, "depart-after"),
             Self::ArriveBefore => write!(f, "arrive-before"),
-            Self::ArriveAfter => write!(f, "depart-after"),
+            Self::ArriveAfter => write!(f, "arrive-after"),
         }
     }
 }
 
+/// The filters of a trip plan, checked for invalid or conflicting values. Every setting is
+/// optional, the API uses its defaults for missing ones.
+///
+/// # Examples
+///
+/// ```
+/// use time::macros::{date, time};
+/// use transit_api_client::prelude::*;
+///
+/// let query = filters::TripPlanQuery::new()
+///     .date(date!(2023 - 06 - 01))
+///     .time(time!(7:00))
+///     .mode(filters::Mode::ArriveBefore)
+///     .walk_speed(4.5)
+///     .min_transfer_wait(2)
+///     .max_transfer_wait(15);
+/// assert!(query.validate().is_ok());
+///
+/// let invalid = query.min_transfer_wait(20);
+/// assert!(invalid.validate().is_err());
+/// ```
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct TripPlanQuery {
+    date: Option<Date>,
+    time: Option<Time>,
+    mode: Option<Mode>,
+    walk_speed: Option<f32>,
+    max_walk_time: Option<u32>,
+    min_transfer_wait: Option<u32>,
+    max_transfer_wait: Option<u32>,
+    max_transfers: Option<u32>,
+}
+
+impl TripPlanQuery {
+    /// Creates a query, that uses the defaults of the API for everything
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The date of the trip. Defaults to today.
+    pub fn date(mut self, date: Date) -> Self {
+        self.date = Some(date);
+        self
+    }
+
+    /// The time of the trip. Defaults to now. Seconds are ignored.
+    ///
+    /// What the time means can be customized with a [Mode]
+    pub fn time(mut self, time: Time) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// What the time applies to. Defaults to [Mode::DepartAfter].
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Walking speed in km/h
+    pub fn walk_speed(mut self, speed: f32) -> Self {
+        self.walk_speed = Some(speed);
+        self
+    }
+
+    /// The maximum number of minutes to spend walking
+    pub fn max_walk_time(mut self, minutes: u32) -> Self {
+        self.max_walk_time = Some(minutes);
+        self
+    }
+
+    /// The minimum number of minutes to spend waiting for a transfer
+    pub fn min_transfer_wait(mut self, minutes: u32) -> Self {
+        self.min_transfer_wait = Some(minutes);
+        self
+    }
+
+    /// The maximum number of minutes to spend waiting for a transfer
+    pub fn max_transfer_wait(mut self, minutes: u32) -> Self {
+        self.max_transfer_wait = Some(minutes);
+        self
+    }
+
+    /// The maximum number of total transfers
+    pub fn max_transfers(mut self, transfers: u32) -> Self {
+        self.max_transfers = Some(transfers);
+        self
+    }
+
+    /// Checks that the settings make sense on their own and together.
+    ///
+    /// returns: Result<(), &str>
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if let Some(speed) = self.walk_speed {
+            if !speed.is_finite() || speed <= 0.0 {
+                return Err("The walking speed has to be a positive number of km/h");
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_transfer_wait, self.max_transfer_wait) {
+            if min > max {
+                return Err(
+                    "The minimum transfer wait can not be longer than the maximum transfer wait",
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// The settings as filters, in the order they are sent to the API
+    pub fn filters(&self) -> Vec<TripPlan> {
+        let mut filters = Vec::new();
+        filters.extend(self.date.map(TripPlan::Date));
+        filters.extend(
+            self.time
+                .map(|time| TripPlan::Time(time.hour(), time.minute())),
+        );
+        filters.extend(self.mode.clone().map(TripPlan::Mode));
+        filters.extend(self.walk_speed.map(TripPlan::WalkSpeed));
+        filters.extend(self.max_walk_time.map(TripPlan::MaxWalkTime));
+        filters.extend(self.min_transfer_wait.map(TripPlan::MinTransferWait));
+        filters.extend(self.max_transfer_wait.map(TripPlan::MaxTransferWait));
+        filters.extend(self.max_transfers.map(TripPlan::MaxTransfers));
+        filters
+    }
+}
+
+impl TryFrom<Vec<TripPlan>> for TripPlanQuery {
+    type Error = &'static str;
+
+    /// Collects filters into a query. Every kind of filter can only be given once.
+    fn try_from(filters: Vec<TripPlan>) -> Result<Self, Self::Error> {
+        fn set<T>(setting: &mut Option<T>, value: T) -> Result<(), &'static str> {
+            match setting.replace(value) {
+                Some(_) => Err("Every kind of trip plan filter can only be given once"),
+                None => Ok(()),
+            }
+        }
+
+        let mut query = Self::new();
+        for filter in filters {
+            match filter {
+                TripPlan::Date(date) => set(&mut query.date, date)?,
+                TripPlan::Time(hours, minutes) => {
+                    let time = Time::from_hms(hours, minutes, 0)
+                        .map_err(|_| "The time of a trip has to be between 00:00 and 23:59")?;
+                    set(&mut query.time, time)?
+                }
+                TripPlan::Mode(mode) => set(&mut query.mode, mode)?,
+                TripPlan::WalkSpeed(speed) => set(&mut query.walk_speed, speed)?,
+                TripPlan::MaxWalkTime(minutes) => set(&mut query.max_walk_time, minutes)?,
+                TripPlan::MinTransferWait(minutes) => set(&mut query.min_transfer_wait, minutes)?,
+                TripPlan::MaxTransferWait(minutes) => set(&mut query.max_transfer_wait, minutes)?,
+                TripPlan::MaxTransfers(transfers) => set(&mut query.max_transfers, transfers)?,
+            }
+        }
+        query.validate()?;
+        Ok(query)
+    }
+}
+
+impl From<&TripPlanQuery> for UrlParameter {
+    fn from(value: &TripPlanQuery) -> Self {
+        Self(
+            value
+                .filters()
+                .into_iter()
+                .map(|filter| UrlParameter::from(filter).0)
+                .collect(),
+        )
+    }
+}
+
 /// A filter when searching for streets
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Street<'a> {
@@ -179,6 +355,234 @@ impl From<Street<'_>> for UrlParameter {
     }
 }
 
+/// An owned [Street] filter, that does not borrow the name or type of the street
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub enum OwnedStreet {
+    /// Filter for the name of the street
+    Name(String),
+
+    /// Filter for the type of the street
+    Type(String),
+
+    /// Filter for the leg of the street
+    Leg(StreetLeg),
+}
+
+impl OwnedStreet {
+    /// Borrows the filter as a [Street] filter.
+    pub fn as_borrowed(&self) -> Street<'_> {
+        match self {
+            Self::Name(n) => Street::Name(n),
+            Self::Type(t) => Street::Type(t),
+            Self::Leg(l) => Street::Leg(l.clone()),
+        }
+    }
+}
+
+impl From<Street<'_>> for OwnedStreet {
+    fn from(value: Street<'_>) -> Self {
+        match value {
+            Street::Name(n) => Self::Name(n.to_string()),
+            Street::Type(t) => Self::Type(t.to_string()),
+            Street::Leg(l) => Self::Leg(l),
+        }
+    }
+}
+
+impl From<OwnedStreet> for UrlParameter {
+    fn from(value: OwnedStreet) -> Self {
+        value.as_borrowed().into()
+    }
+}
+
+/// A street typed by a user, like `Portage Ave E` or `Boul Provencher`, split into the parts
+/// the API can filter for.
+///
+/// Abbreviated street types (`Ave`, `St`, `Blvd`, `Cres`, ...) are expanded, a trailing
+/// direction (`E`, `North`, `Ouest`, ...) becomes the [StreetLeg]. French names, which start
+/// with their type (`Rue Des Meurons`), keep the type as part of their name.
+///
+/// # Examples
+///
+/// ```
+/// use transit_api_client::prelude::*;
+///
+/// let query: filters::StreetQuery = "Portage Ave E".parse().unwrap();
+/// assert_eq!(query.name, "Portage");
+/// assert_eq!(query.street_type.as_deref(), Some("Avenue"));
+/// assert_eq!(query.leg, Some(StreetLeg::East));
+/// ```
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub struct StreetQuery {
+    /// The name of the street, without its type for English names
+    pub name: String,
+
+    /// The type of the street, e.g. `Avenue`
+    pub street_type: Option<String>,
+
+    /// The leg of the street
+    pub leg: Option<StreetLeg>,
+}
+
+/// Street types written after the name, with their abbreviations
+pub(crate) const STREET_TYPES: [(&str, &[&str]); 16] = [
+    ("Avenue", &["avenue", "ave", "av"]),
+    ("Bay", &["bay"]),
+    ("Boulevard", &["boulevard", "blvd", "boul"]),
+    ("Close", &["close", "cl"]),
+    ("Court", &["court", "crt", "ct"]),
+    ("Crescent", &["crescent", "cres", "cr"]),
+    ("Drive", &["drive", "dr"]),
+    ("Gate", &["gate", "gt"]),
+    ("Highway", &["highway", "hwy"]),
+    ("Lane", &["lane", "ln"]),
+    ("Parkway", &["parkway", "pkwy"]),
+    ("Place", &["place", "pl"]),
+    ("Road", &["road", "rd"]),
+    ("Street", &["street", "st"]),
+    ("Terrace", &["terrace", "terr"]),
+    ("Way", &["way"]),
+];
+
+/// French street types, that are written before the name, with their abbreviations
+const FRENCH_STREET_TYPES: [(&str, &[&str]); 6] = [
+    ("Avenue", &["avenue", "av"]),
+    ("Boulevard", &["boulevard", "boul", "blvd"]),
+    ("Chemin", &["chemin", "ch"]),
+    ("Place", &["place", "pl"]),
+    ("Promenade", &["promenade", "prom"]),
+    ("Rue", &["rue"]),
+];
+
+impl StreetQuery {
+    /// The filters to search for the street with
+    /// [TransitClient::street](crate::TransitClient::street).
+    pub fn filters(&self) -> Vec<OwnedStreet> {
+        let mut filters = vec![OwnedStreet::Name(self.name.clone())];
+        filters.extend(self.street_type.clone().map(OwnedStreet::Type));
+        filters.extend(self.leg.clone().map(OwnedStreet::Leg));
+        filters
+    }
+
+    /// How well a street matches the query. 0 is a perfect match, higher is worse.
+    ///
+    /// The name counts the most, then the leg and then the type. If no leg was asked for,
+    /// streets that are not split into legs are preferred.
+    ///
+    /// # Arguments
+    ///
+    /// * `street`: The street to compare to the query
+    ///
+    /// returns: u32
+    pub fn score(&self, street: &FoundStreet) -> u32 {
+        let name = words(&self.name);
+        let full_name = match &self.street_type {
+            Some(street_type) => [name.clone(), words(street_type)].concat(),
+            None => name.clone(),
+        };
+        let found = words(&street.name);
+
+        let mut score = if found == full_name {
+            0
+        } else if found.starts_with(&name) {
+            2
+        } else if name.iter().all(|word| found.contains(word)) {
+            4
+        } else {
+            6
+        };
+        score += match (&self.leg, &street.leg) {
+            (Some(leg), Some(found)) if leg == found => 0,
+            (Some(_), Some(_)) => 2,
+            (Some(_), None) => 1,
+            (None, Some(_)) => 1,
+            (None, None) => 0,
+        };
+        if let Some(street_type) = &self.street_type {
+            let found_type = street.street_type.as_deref().map(words);
+            if found_type != Some(words(street_type)) {
+                score += 1;
+            }
+        }
+        score
+    }
+
+    /// Sorts streets by how well they match the query, best first.
+    ///
+    /// # Arguments
+    ///
+    /// * `streets`: The streets to sort, e.g. the results of
+    ///   [TransitClient::street](crate::TransitClient::street)
+    ///
+    /// returns: Vec<Street>
+    pub fn rank(&self, mut streets: Vec<FoundStreet>) -> Vec<FoundStreet> {
+        streets.sort_by_cached_key(|street| (self.score(street), street.name.clone(), street.key));
+        streets
+    }
+}
+
+impl FromStr for StreetQuery {
+    type Err = &'static str;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut tokens: Vec<&str> = text
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .map(|token| token.trim_end_matches('.'))
+            .filter(|token| !token.is_empty())
+            .collect();
+        if tokens.is_empty() {
+            return Err("The street can not be empty");
+        }
+
+        let leg = match tokens.as_slice() {
+            [_, .., last] => street_leg(last),
+            _ => None,
+        };
+        if leg.is_some() {
+            tokens.pop();
+        }
+
+        let mut street_type = None;
+        if let [_, .., last] = tokens.as_slice() {
+            if let Some(found) = street_type_of(&STREET_TYPES, last) {
+                street_type = Some(found.to_string());
+                tokens.pop();
+            } else if let Some(french) = street_type_of(&FRENCH_STREET_TYPES, tokens[0]) {
+                tokens[0] = french;
+            }
+        }
+
+        Ok(Self {
+            name: tokens.join(" "),
+            street_type,
+            leg,
+        })
+    }
+}
+
+/// Expands a possibly abbreviated street type
+pub(crate) fn street_type_of(
+    types: &[(&'static str, &[&str])],
+    token: &str,
+) -> Option<&'static str> {
+    let token = words(token).concat();
+    types
+        .iter()
+        .find(|(_, abbreviations)| abbreviations.contains(&token.as_str()))
+        .map(|(street_type, _)| *street_type)
+}
+
+/// Parses a direction, that is written after a street, in English or French
+fn street_leg(token: &str) -> Option<StreetLeg> {
+    match words(token).concat().as_str() {
+        "n" | "north" | "nord" => Some(StreetLeg::North),
+        "e" | "east" | "est" => Some(StreetLeg::East),
+        "s" | "south" | "sud" => Some(StreetLeg::South),
+        "w" | "west" | "o" | "ouest" => Some(StreetLeg::West),
+        _ => None,
+    }
+}
+
 /// A filter when getting the schedule for a stop
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Stop {
@@ -199,6 +603,18 @@ pub enum Stop {
 
     /// Limit the results per returned route
     MaxResultsPerRoute(u32),
+
+    /// Only return results after this date and time, which may be on another day
+    StartDateTime(PrimitiveDateTime),
+
+    /// Only return results before this date and time, which may be on another day
+    EndDateTime(PrimitiveDateTime),
+
+    /// Only return results for the specified variants. Their routes are requested from the API,
+    /// and scheduled stops of other variants are removed from the results.
+    ///
+    /// Can be combined with [Stop::Routes], like in a [StopScheduleQuery].
+    Variants(Vec<VariantKey>),
 }
 
 impl From<Stop> for UrlParameter {
@@ -220,6 +636,16 @@ impl From<Stop> for UrlParameter {
             format!("{hours}:{minutes}:00")
         }
 
+        /// Format the date and time correctly:
+        /// Format is: YYYY-MM-DDTHH:MM:SS
+        fn format_date_time(date_time: PrimitiveDateTime) -> String {
+            date_time
+                .format(format_description!(
+                    "[year]-[month]-[day]T[hour]:[minute]:[second]"
+                ))
+                .unwrap()
+        }
+
         let out = match value {
             Stop::Routes(r) => {
                 match r.len() {
@@ -243,7 +669,571 @@ impl From<Stop> for UrlParameter {
                 format!("&end={}", format_time(hours, minutes))
             }
             Stop::MaxResultsPerRoute(m) => format!("&max-results-per-route={m}"),
+            Stop::StartDateTime(start) => format!("&start={}", format_date_time(start)),
+            Stop::EndDateTime(end) => format!("&end={}", format_date_time(end)),
+            Stop::Variants(variants) => {
+                let mut routes: Vec<String> = Vec::new();
+                for variant in variants {
+                    let route = variant.route().to_string();
+                    if !routes.contains(&route) {
+                        routes.push(route);
+                    }
+                }
+                match routes.len() {
+                    0 => String::new(),
+                    1 => format!("&route={}", routes[0]),
+                    _ => format!("&routes={}", routes.join(",")),
+                }
+            }
         };
         UrlParameter(out)
     }
 }
+
+time::serde::format_description!(
+    date_time_formatter,
+    PrimitiveDateTime,
+    "[year]-[month]-[day]T[hour]:[minute]:[second]"
+);
+
+/// The filters of a stop schedule, checked for invalid or conflicting values.
+///
+/// Unlike [Stop::Start] and [Stop::End], the bounds carry a date, so windows can cross midnight.
+/// Routes and variants can be combined: the schedule contains every variant of the given
+/// routes, and the given variants of other routes.
+///
+/// # Examples
+///
+/// ```
+/// use time::macros::datetime;
+/// use transit_api_client::prelude::*;
+///
+/// let query = filters::StopScheduleQuery::new()
+///     .start(datetime!(2023-06-01 23:00))
+///     .end(datetime!(2023-06-02 1:00))
+///     .route(BusType::Regular(16))
+///     .variant("BLUE-0-S".parse().unwrap())
+///     .max_results_per_route(5);
+/// assert!(query.validate().is_ok());
+///
+/// let invalid = query.end(datetime!(2023-06-01 22:00));
+/// assert!(invalid.validate().is_err());
+/// ```
+#[derive(Debug, Default, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub struct StopScheduleQuery {
+    #[serde(default, with = "date_time_formatter::option")]
+    start: Option<PrimitiveDateTime>,
+    #[serde(default, with = "date_time_formatter::option")]
+    end: Option<PrimitiveDateTime>,
+    #[serde(default)]
+    routes: Vec<BusType>,
+    #[serde(default)]
+    variants: Vec<VariantKey>,
+    #[serde(default)]
+    max_results_per_route: Option<u32>,
+}
+
+impl StopScheduleQuery {
+    /// Creates a query, that uses the defaults of the API for everything
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only return results after this date and time. Defaults to now.
+    pub fn start(mut self, start: PrimitiveDateTime) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    /// Only return results before this date and time. Defaults to two hours after the start.
+    pub fn end(mut self, end: PrimitiveDateTime) -> Self {
+        self.end = Some(end);
+        self
+    }
+
+    /// Also return results for this route. Defaults to all routes.
+    pub fn route(mut self, route: BusType) -> Self {
+        if !self.routes.contains(&route) {
+            self.routes.push(route);
+        }
+        self
+    }
+
+    /// Also return results for this variant of a route. Defaults to all variants.
+    pub fn variant(mut self, variant: VariantKey) -> Self {
+        if !self.variants.contains(&variant) {
+            self.variants.push(variant);
+        }
+        self
+    }
+
+    /// Limit the results per returned route
+    pub fn max_results_per_route(mut self, limit: u32) -> Self {
+        self.max_results_per_route = Some(limit);
+        self
+    }
+
+    /// Checks that the settings make sense on their own and together.
+    ///
+    /// returns: Result<(), &str>
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if let (Some(start), Some(end)) = (self.start, self.end) {
+            if end <= start {
+                return Err("The end of a stop schedule has to be after its start");
+            }
+        }
+        if self.max_results_per_route == Some(0) {
+            return Err("At least one result per route has to be returned");
+        }
+        Ok(())
+    }
+
+    /// The routes to request from the API: the given routes and the routes of the variants
+    fn requested_routes(&self) -> Vec<BusType> {
+        let mut routes = self.routes.clone();
+        for variant in &self.variants {
+            if !routes.contains(variant.route()) {
+                routes.push(variant.route().clone());
+            }
+        }
+        routes
+    }
+
+    /// Splits stop filters into a query of their routes and variants, and the URL parameters of
+    /// the other filters. Routes and variants are combined into one parameter, see
+    /// [Stop::Variants].
+    pub(crate) fn from_filters(filters: Vec<Stop>) -> (Self, UrlParameter) {
+        let mut query = Self::new();
+        let mut url_parameters = String::new();
+        for filter in filters {
+            match filter {
+                Stop::Routes(routes) => {
+                    for route in routes {
+                        query = query.route(BusType::Regular(route));
+                    }
+                }
+                Stop::Variants(variants) => {
+                    for variant in variants {
+                        query = query.variant(variant);
+                    }
+                }
+                filter => url_parameters.push_str(&UrlParameter::from(filter).0),
+            }
+        }
+        url_parameters.push_str(&UrlParameter::from(&query).0);
+        (query, UrlParameter(url_parameters))
+    }
+
+    /// Removes scheduled stops of variants, that were not asked for, and routes without any
+    /// scheduled stops left. The API can only filter by route.
+    pub(crate) fn retain(&self, schedule: &mut Schedule) {
+        if self.variants.is_empty() {
+            return;
+        }
+        for route_schedule in &mut schedule.route_schedules {
+            if self.routes.contains(&route_schedule.route.key) {
+                continue;
+            }
+            route_schedule
+                .scheduled_stops
+                .retain(|scheduled_stop| self.variants.contains(&scheduled_stop.variant.key));
+        }
+        schedule
+            .route_schedules
+            .retain(|route_schedule| !route_schedule.scheduled_stops.is_empty());
+    }
+}
+
+impl From<&StopScheduleQuery> for UrlParameter {
+    fn from(value: &StopScheduleQuery) -> Self {
+        let mut out = String::new();
+        if let Some(start) = value.start {
+            out.push_str(&UrlParameter::from(Stop::StartDateTime(start)).0);
+        }
+        if let Some(end) = value.end {
+            out.push_str(&UrlParameter::from(Stop::EndDateTime(end)).0);
+        }
+        let routes: Vec<String> = value
+            .requested_routes()
+            .iter()
+            .map(BusType::to_string)
+            .collect();
+        match routes.len() {
+            0 => {}
+            1 => out.push_str(&format!("&route={}", routes[0])),
+            _ => out.push_str(&format!("&routes={}", routes.join(","))),
+        }
+        if let Some(limit) = value.max_results_per_route {
+            out.push_str(&UrlParameter::from(Stop::MaxResultsPerRoute(limit)).0);
+        }
+        UrlParameter(out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::*;
+    use crate::structs::stops::{self, test::departure};
+    use time::macros::{date, datetime, time};
+
+    fn street(key: u32, name: &str, street_type: &str, leg: Option<StreetLeg>) -> Street {
+        Street {
+            key,
+            name: name.to_string(),
+            street_type: Some(street_type.to_string()),
+            leg,
+        }
+    }
+
+    fn query(
+        name: &str,
+        street_type: Option<&str>,
+        leg: Option<StreetLeg>,
+    ) -> filters::StreetQuery {
+        filters::StreetQuery {
+            name: name.to_string(),
+            street_type: street_type.map(str::to_string),
+            leg,
+        }
+    }
+
+    fn query_string(query: &filters::TripPlanQuery) -> String {
+        crate::structs::UrlParameter::from(query).0
+    }
+
+    #[test]
+    fn mode() {
+        let modes = [
+            (filters::Mode::DepartBefore, "depart-before"),
+            (filters::Mode::DepartAfter, "depart-after"),
+            (filters::Mode::ArriveBefore, "arrive-before"),
+            (filters::Mode::ArriveAfter, "arrive-after"),
+        ];
+        for (mode, expected) in modes {
+            assert_eq!(mode.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn trip_plan_query_string() {
+        let query = filters::TripPlanQuery::new;
+        assert_eq!(query_string(&query()), "");
+        assert_eq!(
+            query_string(&query().date(date!(2023 - 06 - 01))),
+            "&date=2023-06-01"
+        );
+        assert_eq!(query_string(&query().time(time!(7:05:30))), "&time=07:05");
+        assert_eq!(query_string(&query().time(time!(23:59))), "&time=23:59");
+        assert_eq!(
+            query_string(&query().mode(filters::Mode::ArriveAfter)),
+            "&mode=arrive-after"
+        );
+        assert_eq!(query_string(&query().walk_speed(4.5)), "&walk-speed=4.5");
+        assert_eq!(
+            query_string(&query().max_walk_time(10)),
+            "&max-walk-time=10"
+        );
+        assert_eq!(
+            query_string(&query().min_transfer_wait(2)),
+            "&min-transfer-wait=2"
+        );
+        assert_eq!(
+            query_string(&query().max_transfer_wait(15)),
+            "&max-transfer-wait=15"
+        );
+        assert_eq!(query_string(&query().max_transfers(0)), "&max-transfers=0");
+
+        // The order of the setters does not matter, and later values replace earlier ones
+        let all = query()
+            .max_transfers(2)
+            .max_transfer_wait(15)
+            .min_transfer_wait(2)
+            .max_walk_time(10)
+            .walk_speed(4.5)
+            .mode(filters::Mode::DepartBefore)
+            .mode(filters::Mode::ArriveBefore)
+            .time(time!(17:30))
+            .date(date!(2023 - 12 - 24));
+        assert_eq!(
+            query_string(&all),
+            "&date=2023-12-24&time=17:30&mode=arrive-before&walk-speed=4.5&max-walk-time=10\
+             &min-transfer-wait=2&max-transfer-wait=15&max-transfers=2"
+        );
+    }
+
+    #[test]
+    fn trip_plan_query_validation() {
+        let query = filters::TripPlanQuery::new;
+        assert!(query().validate().is_ok());
+        assert!(query().walk_speed(0.1).validate().is_ok());
+        assert!(query().walk_speed(0.0).validate().is_err());
+        assert!(query().walk_speed(-4.5).validate().is_err());
+        assert!(query().walk_speed(f32::NAN).validate().is_err());
+        assert!(query().walk_speed(f32::INFINITY).validate().is_err());
+        assert!(query()
+            .min_transfer_wait(5)
+            .max_transfer_wait(5)
+            .validate()
+            .is_ok());
+        assert!(query()
+            .min_transfer_wait(6)
+            .max_transfer_wait(5)
+            .validate()
+            .is_err());
+        // Either bound alone is fine
+        assert!(query().min_transfer_wait(60).validate().is_ok());
+        assert!(query().max_transfer_wait(0).validate().is_ok());
+    }
+
+    #[test]
+    fn trip_plan_query_from_filters() -> Result<(), &'static str> {
+        let given = vec![
+            filters::TripPlan::Mode(filters::Mode::DepartBefore),
+            filters::TripPlan::Time(8, 3),
+            filters::TripPlan::MaxTransfers(1),
+        ];
+        let query = filters::TripPlanQuery::try_from(given)?;
+        assert_eq!(
+            query,
+            filters::TripPlanQuery::new()
+                .mode(filters::Mode::DepartBefore)
+                .time(time!(8:03))
+                .max_transfers(1)
+        );
+        assert_eq!(
+            query.filters(),
+            vec![
+                filters::TripPlan::Time(8, 3),
+                filters::TripPlan::Mode(filters::Mode::DepartBefore),
+                filters::TripPlan::MaxTransfers(1),
+            ]
+        );
+
+        let invalid = [
+            vec![filters::TripPlan::Time(25, 99)],
+            vec![filters::TripPlan::Time(12, 60)],
+            vec![
+                filters::TripPlan::Mode(filters::Mode::DepartBefore),
+                filters::TripPlan::Mode(filters::Mode::ArriveAfter),
+            ],
+            vec![filters::TripPlan::WalkSpeed(-1.0)],
+            vec![
+                filters::TripPlan::MinTransferWait(10),
+                filters::TripPlan::MaxTransferWait(5),
+            ],
+        ];
+        for given in invalid {
+            assert!(
+                filters::TripPlanQuery::try_from(given.clone()).is_err(),
+                "{given:?}"
+            );
+        }
+        Ok(())
+    }
+
+    fn stop_query_string(query: &filters::StopScheduleQuery) -> String {
+        crate::structs::UrlParameter::from(query).0
+    }
+
+    #[test]
+    fn stop_schedule_query_string() {
+        let query = filters::StopScheduleQuery::new;
+        assert_eq!(stop_query_string(&query()), "");
+        assert_eq!(
+            stop_query_string(&query().start(datetime!(2023-06-01 23:30))),
+            "&start=2023-06-01T23:30:00"
+        );
+        assert_eq!(
+            stop_query_string(&query().end(datetime!(2023-06-02 0:30))),
+            "&end=2023-06-02T00:30:00"
+        );
+        assert_eq!(
+            stop_query_string(&query().route(BusType::Regular(16))),
+            "&route=16"
+        );
+        assert_eq!(
+            stop_query_string(&query().variant("BLUE-0-S".parse().unwrap())),
+            "&route=BLUE"
+        );
+        assert_eq!(
+            stop_query_string(&query().max_results_per_route(3)),
+            "&max-results-per-route=3"
+        );
+
+        let all = query()
+            .max_results_per_route(3)
+            .variant("16-1-K".parse().unwrap())
+            .variant("BLUE-0-S".parse().unwrap())
+            .route(BusType::Regular(11))
+            .route(BusType::Regular(16))
+            .route(BusType::Regular(11))
+            .end(datetime!(2023-06-02 0:30))
+            .start(datetime!(2023-06-01 23:30));
+        assert_eq!(
+            stop_query_string(&all),
+            "&start=2023-06-01T23:30:00&end=2023-06-02T00:30:00&routes=11,16,BLUE\
+             &max-results-per-route=3"
+        );
+    }
+
+    #[test]
+    fn stop_schedule_query_validation() {
+        let query = filters::StopScheduleQuery::new;
+        assert!(query().validate().is_ok());
+        assert!(query()
+            .start(datetime!(2023-06-01 23:30))
+            .end(datetime!(2023-06-02 0:30))
+            .validate()
+            .is_ok());
+        assert!(query()
+            .start(datetime!(2023-06-01 23:30))
+            .end(datetime!(2023-06-01 23:30))
+            .validate()
+            .is_err());
+        assert!(query()
+            .start(datetime!(2023-06-02 0:30))
+            .end(datetime!(2023-06-01 23:30))
+            .validate()
+            .is_err());
+        assert!(query().max_results_per_route(1).validate().is_ok());
+        assert!(query().max_results_per_route(0).validate().is_err());
+    }
+
+    #[test]
+    fn stop_schedule_from_filters() {
+        let (query, url_parameters) = filters::StopScheduleQuery::from_filters(vec![
+            filters::Stop::Routes(vec![16]),
+            filters::Stop::MaxResultsPerRoute(3),
+            filters::Stop::Variants(vec!["18-0-A".parse().unwrap()]),
+        ]);
+        assert_eq!(url_parameters.0, "&max-results-per-route=3&routes=16,18");
+
+        // All variants of route 16 are kept, although a variant of another route is asked for
+        let mut schedule = stops::test::schedule(vec![
+            departure(
+                "1",
+                "16-1-K",
+                datetime!(2023-06-01 7:00),
+                datetime!(2023-06-01 7:00),
+            ),
+            departure(
+                "2",
+                "16-0-D",
+                datetime!(2023-06-01 7:10),
+                datetime!(2023-06-01 7:10),
+            ),
+        ]);
+        query.retain(&mut schedule);
+        assert_eq!(schedule.route_schedules[0].scheduled_stops.len(), 2);
+
+        let (query, url_parameters) =
+            filters::StopScheduleQuery::from_filters(vec![filters::Stop::Variants(vec!["16-1-K"
+                .parse()
+                .unwrap()])]);
+        assert_eq!(url_parameters.0, "&route=16");
+        query.retain(&mut schedule);
+        assert_eq!(schedule.route_schedules[0].scheduled_stops.len(), 1);
+        assert_eq!(
+            schedule.route_schedules[0].scheduled_stops[0].variant.key,
+            "16-1-K".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn stop_schedule_query_serde() -> Result<(), serde_json::Error> {
+        let query = filters::StopScheduleQuery::new()
+            .start(datetime!(2023-06-01 23:30))
+            .route(BusType::Regular(16))
+            .variant("BLUE-0-S".parse().unwrap());
+        let json = serde_json::to_value(&query)?;
+        assert_eq!(json["start"], "2023-06-01T23:30:00");
+        assert_eq!(json["routes"][0], 16);
+        assert_eq!(json["variants"][0], "BLUE-0-S");
+        assert_eq!(
+            serde_json::from_value::<filters::StopScheduleQuery>(json)?,
+            query
+        );
+        assert_eq!(
+            serde_json::from_str::<filters::StopScheduleQuery>("{}")?,
+            filters::StopScheduleQuery::new()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_street_query() -> Result<(), &'static str> {
+        assert_eq!(
+            "Portage Ave. E".parse::<filters::StreetQuery>()?,
+            query("Portage", Some("Avenue"), Some(StreetLeg::East))
+        );
+        assert_eq!(
+            "henderson hwy north".parse::<filters::StreetQuery>()?,
+            query("henderson", Some("Highway"), Some(StreetLeg::North))
+        );
+        assert_eq!(
+            "St Anne's Rd".parse::<filters::StreetQuery>()?,
+            query("St Anne's", Some("Road"), None)
+        );
+        assert_eq!(
+            "Main".parse::<filters::StreetQuery>()?,
+            query("Main", None, None)
+        );
+        assert_eq!(
+            "Boul Provencher".parse::<filters::StreetQuery>()?,
+            query("Boulevard Provencher", None, None)
+        );
+        assert_eq!(
+            "rue des Meurons ouest".parse::<filters::StreetQuery>()?,
+            query("Rue des Meurons", None, Some(StreetLeg::West))
+        );
+        // A single word is always the name
+        assert_eq!("E".parse::<filters::StreetQuery>()?, query("E", None, None));
+        assert!(" , ".parse::<filters::StreetQuery>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn street_query_filters() {
+        assert_eq!(
+            query("Portage", Some("Avenue"), Some(StreetLeg::East)).filters(),
+            vec![
+                filters::OwnedStreet::Name("Portage".to_string()),
+                filters::OwnedStreet::Type("Avenue".to_string()),
+                filters::OwnedStreet::Leg(StreetLeg::East),
+            ]
+        );
+        assert_eq!(
+            query("Main", None, None).filters(),
+            vec![filters::OwnedStreet::Name("Main".to_string())]
+        );
+    }
+
+    #[test]
+    fn rank_streets() {
+        let streets = vec![
+            street(2904, "Portage Avenue", "Avenue", Some(StreetLeg::East)),
+            street(3000, "Portage Place", "Place", None),
+            street(2903, "Portage Avenue", "Avenue", None),
+        ];
+
+        let keys = |query: filters::StreetQuery| -> Vec<u32> {
+            query
+                .rank(streets.clone())
+                .iter()
+                .map(|street| street.key)
+                .collect()
+        };
+        assert_eq!(
+            keys(query("Portage", Some("Avenue"), None)),
+            vec![2903, 2904, 3000]
+        );
+        assert_eq!(
+            keys(query("Portage", Some("Avenue"), Some(StreetLeg::East))),
+            vec![2904, 2903, 3000]
+        );
+        assert_eq!(
+            keys(query("Portage", Some("Place"), None)),
+            vec![3000, 2903, 2904]
+        );
+    }
+}
@@ -0,0 +1,544 @@
+// TransitTrail - Navigate Winnipeg Transit with a different style
+// Copyright (C) - 2023 Foxx Azalea Pinkerton, Max Fehlinger
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Offline access to the static [GTFS](https://gtfs.org/schedule/reference/) feed published by
+//! Winnipeg Transit. It can be used to plan trips without calling the API. Live schedules can be
+//! exported as GTFS-realtime with the [realtime] module.
+//!
+
+pub mod realtime;
+pub mod router;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use time::{macros::format_description, Date};
+
+use crate::structs::{common::GeoLocation, Error};
+
+pub use router::*;
+
+/// A stop, as it is defined in `stops.txt`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stop {
+    /// The `stop_id` used to reference the stop in the other files of the feed
+    pub id: String,
+
+    /// The stop number that is shown to riders. Uses the `stop_code`, or the `stop_id` if no
+    /// code is set and the id is numeric.
+    pub number: Option<u32>,
+
+    /// The stop name
+    pub name: String,
+
+    /// A geographical point describing where the stop is located.
+    pub centre: GeoLocation,
+}
+
+/// A route, as it is defined in `routes.txt`
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Route {
+    /// The `route_id` used to reference the route in the other files of the feed
+    pub id: String,
+
+    /// The short name of the route, e.g. `16` or `BLUE`
+    pub short_name: String,
+
+    /// The long name of the route, e.g. `Selkirk-Osborne`
+    pub long_name: Option<String>,
+
+    /// The background colour of the route's badge as hex, without a leading `#`
+    pub color: Option<String>,
+
+    /// The text colour of the route's badge as hex, without a leading `#`
+    pub text_color: Option<String>,
+}
+
+/// A trip, as it is defined in `trips.txt`, together with its stop times
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Trip {
+    /// The `trip_id` used to reference the trip in the other files of the feed
+    pub id: String,
+
+    /// The index of the route in [Feed::routes]
+    pub route: usize,
+
+    /// The service calendar that determines on which days the trip runs
+    pub service_id: String,
+
+    /// The text on the bus' destination sign
+    pub headsign: Option<String>,
+
+    /// When the trip serves which stop, ordered by the `stop_sequence`
+    pub stop_times: Vec<StopTime>,
+}
+
+/// When a trip serves a stop
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StopTime {
+    /// The index of the stop in [Feed::stops]
+    pub stop: usize,
+
+    /// Seconds after midnight of the service day. May be more than 24 hours for trips that run
+    /// past midnight.
+    pub arrival: i32,
+
+    /// Seconds after midnight of the service day. May be more than 24 hours for trips that run
+    /// past midnight.
+    pub departure: i32,
+}
+
+/// On which days of the week and in which period a service runs (`calendar.txt`)
+#[derive(Clone, Debug)]
+struct Calendar {
+    /// Monday to Sunday
+    days: [bool; 7],
+    start: Date,
+    end: Date,
+}
+
+/// A parsed GTFS feed
+#[derive(Clone, Debug, Default)]
+pub struct Feed {
+    /// All stops of the feed
+    pub stops: Vec<Stop>,
+
+    /// All routes of the feed
+    pub routes: Vec<Route>,
+
+    /// All trips of the feed
+    pub trips: Vec<Trip>,
+
+    calendars: HashMap<String, Calendar>,
+
+    /// Dates on which a service was added (`true`) or removed (`false`)
+    exceptions: HashMap<String, Vec<(Date, bool)>>,
+}
+
+impl Feed {
+    /// Reads an extracted GTFS feed from a directory.
+    ///
+    /// The files `stops.txt`, `routes.txt`, `trips.txt` and `stop_times.txt` are required,
+    /// `calendar.txt` and `calendar_dates.txt` are read, if they exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory`: The directory the feed was extracted to
+    ///
+    /// returns: Result<Feed, Error>
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use transit_api_client::gtfs::Feed;
+    ///
+    /// let feed = Feed::from_dir("google_transit").unwrap();
+    /// println!("The feed has {} stops", feed.stops.len());
+    /// ```
+    pub fn from_dir<P: AsRef<Path>>(directory: P) -> Result<Self, Error> {
+        let directory = directory.as_ref();
+        let optional = |file: &str| -> Result<Option<String>, Error> {
+            let path = directory.join(file);
+            if path.exists() {
+                Ok(Some(fs::read_to_string(path)?))
+            } else {
+                Ok(None)
+            }
+        };
+
+        Self::parse(
+            &fs::read_to_string(directory.join("stops.txt"))?,
+            &fs::read_to_string(directory.join("routes.txt"))?,
+            &fs::read_to_string(directory.join("trips.txt"))?,
+            &fs::read_to_string(directory.join("stop_times.txt"))?,
+            optional("calendar.txt")?.as_deref(),
+            optional("calendar_dates.txt")?.as_deref(),
+        )
+    }
+
+    /// Parses a GTFS feed from the contents of its files.
+    ///
+    /// # Arguments
+    ///
+    /// * `stops`: The contents of `stops.txt`
+    /// * `routes`: The contents of `routes.txt`
+    /// * `trips`: The contents of `trips.txt`
+    /// * `stop_times`: The contents of `stop_times.txt`
+    /// * `calendar`: The contents of `calendar.txt`, if the feed has one
+    /// * `calendar_dates`: The contents of `calendar_dates.txt`, if the feed has one
+    ///
+    /// returns: Result<Feed, Error>
+    pub fn parse(
+        stops: &str,
+        routes: &str,
+        trips: &str,
+        stop_times: &str,
+        calendar: Option<&str>,
+        calendar_dates: Option<&str>,
+    ) -> Result<Self, Error> {
+        let mut feed = Feed::default();
+
+        let table = Table::parse(stops);
+        let mut stop_ids = HashMap::new();
+        for row in &table.rows {
+            // Stations and entrances are not served by any trip
+            if table.get(row, "location_type").unwrap_or("0") != "0" {
+                continue;
+            }
+            let id = table.require(row, "stop_id", "stops.txt")?;
+            let parse_coordinate = |column: &str| -> Result<f64, Error> {
+                let value = table.require(row, column, "stops.txt")?;
+                value
+                    .parse()
+                    .map_err(|_| Error::Gtfs(format!("stops.txt: invalid {column} `{value}`")))
+            };
+            stop_ids.insert(id.to_string(), feed.stops.len());
+            feed.stops.push(Stop {
+                id: id.to_string(),
+                number: table.get(row, "stop_code").unwrap_or(id).parse().ok(),
+                name: table.get(row, "stop_name").unwrap_or_default().to_string(),
+                centre: GeoLocation::new(
+                    parse_coordinate("stop_lat")?,
+                    parse_coordinate("stop_lon")?,
+                ),
+            });
+        }
+
+        let table = Table::parse(routes);
+        let mut route_ids = HashMap::new();
+        for row in &table.rows {
+            let id = table.require(row, "route_id", "routes.txt")?;
+            route_ids.insert(id.to_string(), feed.routes.len());
+            feed.routes.push(Route {
+                id: id.to_string(),
+                short_name: table.get(row, "route_short_name").unwrap_or(id).to_string(),
+                long_name: table.get(row, "route_long_name").map(str::to_string),
+                color: table.get(row, "route_color").map(str::to_string),
+                text_color: table.get(row, "route_text_color").map(str::to_string),
+            });
+        }
+
+        let table = Table::parse(trips);
+        let mut trip_ids = HashMap::new();
+        for row in &table.rows {
+            let id = table.require(row, "trip_id", "trips.txt")?;
+            let route = table.require(row, "route_id", "trips.txt")?;
+            trip_ids.insert(id.to_string(), feed.trips.len());
+            feed.trips.push(Trip {
+                id: id.to_string(),
+                route: *route_ids
+                    .get(route)
+                    .ok_or_else(|| Error::Gtfs(format!("trips.txt: unknown route `{route}`")))?,
+                service_id: table.require(row, "service_id", "trips.txt")?.to_string(),
+                headsign: table.get(row, "trip_headsign").map(str::to_string),
+                stop_times: Vec::new(),
+            });
+        }
+
+        let mut raw_stop_times: Vec<Vec<RawStopTime>> = vec![Vec::new(); feed.trips.len()];
+        let table = Table::parse(stop_times);
+        for row in &table.rows {
+            let trip = table.require(row, "trip_id", "stop_times.txt")?;
+            let stop = table.require(row, "stop_id", "stop_times.txt")?;
+            let sequence = table.require(row, "stop_sequence", "stop_times.txt")?;
+            let trip = *trip_ids
+                .get(trip)
+                .ok_or_else(|| Error::Gtfs(format!("stop_times.txt: unknown trip `{trip}`")))?;
+            let stop = *stop_ids
+                .get(stop)
+                .ok_or_else(|| Error::Gtfs(format!("stop_times.txt: unknown stop `{stop}`")))?;
+            raw_stop_times[trip].push((
+                sequence.parse().map_err(|_| {
+                    Error::Gtfs(format!(
+                        "stop_times.txt: invalid stop_sequence `{sequence}`"
+                    ))
+                })?,
+                stop,
+                table.get(row, "arrival_time").map(parse_time).transpose()?,
+                table
+                    .get(row, "departure_time")
+                    .map(parse_time)
+                    .transpose()?,
+            ));
+        }
+        for (trip, mut raw) in feed.trips.iter_mut().zip(raw_stop_times) {
+            raw.sort_by_key(|(sequence, ..)| *sequence);
+            trip.stop_times = interpolate(&trip.id, &raw)?;
+        }
+
+        if let Some(calendar) = calendar {
+            let table = Table::parse(calendar);
+            for row in &table.rows {
+                let mut days = [false; 7];
+                for (day, column) in days.iter_mut().zip([
+                    "monday",
+                    "tuesday",
+                    "wednesday",
+                    "thursday",
+                    "friday",
+                    "saturday",
+                    "sunday",
+                ]) {
+                    *day = table.get(row, column) == Some("1");
+                }
+                feed.calendars.insert(
+                    table
+                        .require(row, "service_id", "calendar.txt")?
+                        .to_string(),
+                    Calendar {
+                        days,
+                        start: parse_date(table.require(row, "start_date", "calendar.txt")?)?,
+                        end: parse_date(table.require(row, "end_date", "calendar.txt")?)?,
+                    },
+                );
+            }
+        }
+
+        if let Some(calendar_dates) = calendar_dates {
+            let table = Table::parse(calendar_dates);
+            for row in &table.rows {
+                let service = table.require(row, "service_id", "calendar_dates.txt")?;
+                let date = parse_date(table.require(row, "date", "calendar_dates.txt")?)?;
+                let added = table.require(row, "exception_type", "calendar_dates.txt")? == "1";
+                feed.exceptions
+                    .entry(service.to_string())
+                    .or_default()
+                    .push((date, added));
+            }
+        }
+
+        Ok(feed)
+    }
+
+    /// Checks whether a service runs on the given date, using the calendar and its exceptions.
+    ///
+    /// # Arguments
+    ///
+    /// * `service_id`: The id of the service, e.g. from [Trip::service_id]
+    /// * `date`: The date of the service day
+    ///
+    /// returns: bool
+    pub fn is_active(&self, service_id: &str, date: Date) -> bool {
+        if let Some(exceptions) = self.exceptions.get(service_id) {
+            if let Some((_, added)) = exceptions.iter().find(|(d, _)| *d == date) {
+                return *added;
+            }
+        }
+
+        match self.calendars.get(service_id) {
+            Some(calendar) => {
+                calendar.start <= date
+                    && date <= calendar.end
+                    && calendar.days[date.weekday().number_days_from_monday() as usize]
+            }
+            None => false,
+        }
+    }
+}
+
+/// A CSV file of the feed, split into its header and rows.
+struct Table {
+    columns: HashMap<String, usize>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Parses CSV as it is used in GTFS: Fields may be quoted and quotes inside of them are
+    /// escaped by doubling them.
+    fn parse(text: &str) -> Self {
+        let mut records = Vec::new();
+        let mut record = Vec::new();
+        let mut field = String::new();
+        let mut in_quotes = false;
+
+        let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+        while let Some(c) = chars.next() {
+            if in_quotes {
+                match c {
+                    '"' if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    '"' => in_quotes = false,
+                    _ => field.push(c),
+                }
+            } else {
+                match c {
+                    '"' => in_quotes = true,
+                    ',' => record.push(std::mem::take(&mut field)),
+                    '\r' => {}
+                    '\n' => {
+                        record.push(std::mem::take(&mut field));
+                        records.push(std::mem::take(&mut record));
+                    }
+                    _ => field.push(c),
+                }
+            }
+        }
+        if !field.is_empty() || !record.is_empty() {
+            record.push(field);
+            records.push(record);
+        }
+        records.retain(|record| !(record.len() == 1 && record[0].trim().is_empty()));
+
+        let mut records = records.into_iter();
+        let columns = records
+            .next()
+            .unwrap_or_default()
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| (name.trim().to_string(), index))
+            .collect();
+
+        Self {
+            columns,
+            rows: records.collect(),
+        }
+    }
+
+    /// Gets the value of a column in a row. Empty values are treated as missing.
+    fn get<'a>(&self, row: &'a [String], column: &str) -> Option<&'a str> {
+        self.columns
+            .get(column)
+            .and_then(|&index| row.get(index))
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Gets the value of a column, that is required by the specification.
+    fn require<'a>(&self, row: &'a [String], column: &str, file: &str) -> Result<&'a str, Error> {
+        self.get(row, column)
+            .ok_or_else(|| Error::Gtfs(format!("{file}: missing value for `{column}`")))
+    }
+}
+
+/// Parses a GTFS time (`H:MM:SS`, hours may exceed 24) into seconds after midnight.
+fn parse_time(value: &str) -> Result<i32, Error> {
+    let mut parts = value.split(':').map(|part| part.trim().parse::<i32>());
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(hours)), Some(Ok(minutes)), Some(Ok(seconds)), None) => {
+            Ok(hours * 3600 + minutes * 60 + seconds)
+        }
+        _ => Err(Error::Gtfs(format!("Invalid time `{value}`"))),
+    }
+}
+
+/// Parses a GTFS date (`YYYYMMDD`)
+fn parse_date(value: &str) -> Result<Date, Error> {
+    Date::parse(value, format_description!("[year][month][day]"))
+        .map_err(|_| Error::Gtfs(format!("Invalid date `{value}`")))
+}
+
+/// (stop_sequence, stop, arrival, departure) of a stop time, before sorting and filling in
+/// missing times
+type RawStopTime = (u32, usize, Option<i32>, Option<i32>);
+
+/// Fills in the times of stops, which are not timepoints, by interpolating linearly between the
+/// surrounding timepoints.
+fn interpolate(trip: &str, raw: &[RawStopTime]) -> Result<Vec<StopTime>, Error> {
+    let known: Vec<Option<(i32, i32)>> = raw
+        .iter()
+        .map(|(_, _, arrival, departure)| match (arrival, departure) {
+            (Some(a), Some(d)) => Some((*a, *d)),
+            (Some(t), None) | (None, Some(t)) => Some((*t, *t)),
+            (None, None) => None,
+        })
+        .collect();
+
+    let mut out = Vec::with_capacity(raw.len());
+    for (index, (_, stop, ..)) in raw.iter().enumerate() {
+        let (arrival, departure) = match known[index] {
+            Some(times) => times,
+            None => {
+                let previous = (0..index).rev().find_map(|i| known[i].map(|(_, d)| (i, d)));
+                let next = (index + 1..raw.len()).find_map(|i| known[i].map(|(a, _)| (i, a)));
+                match (previous, next) {
+                    (Some((from, start)), Some((to, end))) => {
+                        let time =
+                            start + (end - start) * (index - from) as i32 / (to - from) as i32;
+                        (time, time)
+                    }
+                    _ => {
+                        return Err(Error::Gtfs(format!(
+                            "stop_times.txt: trip `{trip}` does not start and end with a time"
+                        )))
+                    }
+                }
+            }
+        };
+        out.push(StopTime {
+            stop: *stop,
+            arrival,
+            departure,
+        });
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use time::macros::date;
+
+    #[test]
+    fn csv() {
+        let table = Table::parse("\u{feff}a,b,c\r\n1,\"two, \"\"quoted\"\"\",3\r\n\r\n4,,6");
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.get(&table.rows[0], "b"), Some("two, \"quoted\""));
+        assert_eq!(table.get(&table.rows[1], "b"), None);
+        assert_eq!(table.get(&table.rows[1], "c"), Some("6"));
+    }
+
+    #[test]
+    fn calendar() -> Result<(), Error> {
+        let feed = Feed::parse(
+            "stop_id,stop_name,stop_lat,stop_lon\n10064,Osborne at Glasgow,49.86912,-97.1375\n",
+            "route_id,route_short_name\n16,16\n",
+            "route_id,service_id,trip_id\n16,weekday,1\n",
+            "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n1,25:01:00,25:01:00,10064,1\n",
+            Some("service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\nweekday,1,1,1,1,1,0,0,20230101,20231231\n"),
+            Some("service_id,date,exception_type\nweekday,20230703,2\nweekday,20230708,1\n"),
+        )?;
+
+        assert_eq!(feed.stops[0].number, Some(10064));
+        assert_eq!(feed.trips[0].stop_times[0].arrival, 25 * 3600 + 60);
+        // A regular Thursday
+        assert!(feed.is_active("weekday", date!(2023 - 07 - 06)));
+        // Canada Day (observed) and a Saturday with added service
+        assert!(!feed.is_active("weekday", date!(2023 - 07 - 03)));
+        assert!(feed.is_active("weekday", date!(2023 - 07 - 08)));
+        // A regular Sunday and a date outside of the calendar
+        assert!(!feed.is_active("weekday", date!(2023 - 07 - 09)));
+        assert!(!feed.is_active("weekday", date!(2024 - 01 - 03)));
+        Ok(())
+    }
+
+    #[test]
+    fn interpolation() -> Result<(), Error> {
+        let stop_times = interpolate(
+            "1",
+            &[
+                (1, 0, Some(100), Some(100)),
+                (2, 1, None, None),
+                (3, 2, Some(300), None),
+            ],
+        )?;
+        assert_eq!(stop_times[1].arrival, 200);
+        assert_eq!(stop_times[2].departure, 300);
+        Ok(())
+    }
+}
@@ -0,0 +1,602 @@
+// TransitTrail - Navigate Winnipeg Transit with a different style
+// Copyright (C) - 2023 Foxx Azalea Pinkerton, Max Fehlinger
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Exports live [Schedule]s as a [GTFS-realtime](https://gtfs.org/realtime/reference/) feed of
+//! `TripUpdate`s, so tools that do not understand the API's JSON can use them.
+//!
+//! Only the parts of the specification that can be filled with data from the API are included.
+//!
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+
+use prost::Message;
+use serde::{Serialize, Serializer};
+use time::{macros::format_description, Duration, OffsetDateTime, PrimitiveDateTime};
+
+use crate::structs::{
+    stops::{Bus, Schedule, Time},
+    Error,
+};
+
+/// The version of the GTFS-realtime specification, that is exported
+pub const GTFS_REALTIME_VERSION: &str = "2.0";
+
+/// The contents of a feed
+#[derive(Clone, PartialEq, Serialize, Message)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedMessage {
+    /// Metadata about this feed and feed message
+    #[prost(message, required, tag = "1")]
+    pub header: FeedHeader,
+
+    /// Contents of the feed
+    #[prost(message, repeated, tag = "2")]
+    pub entity: Vec<FeedEntity>,
+}
+
+impl FeedMessage {
+    /// Encodes the feed in the protocol buffer format, which is usually served to clients.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.encode_to_vec()
+    }
+
+    /// Decodes a feed from the protocol buffer format.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes`: The encoded feed
+    ///
+    /// returns: Result<FeedMessage, Error>
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Self::decode(bytes).map_err(|why| Error::Gtfs(why.to_string()))
+    }
+
+    /// Serializes the feed as JSON, with the field names in camel case, enumerations by name and
+    /// 64 bit integers as strings.
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+/// Metadata about a feed, included in feed messages
+#[derive(Clone, PartialEq, Serialize, Message)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedHeader {
+    /// Version of the feed specification
+    #[prost(string, required, tag = "1")]
+    pub gtfs_realtime_version: String,
+
+    /// If the feed contains everything, or only changes. Uses [Incrementality]
+    #[prost(enumeration = "Incrementality", optional, tag = "2")]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_incrementality"
+    )]
+    pub incrementality: Option<i32>,
+
+    /// When the content of this feed has been created, in POSIX time
+    #[prost(uint64, optional, tag = "3")]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_64_bit"
+    )]
+    pub timestamp: Option<u64>,
+}
+
+/// Determines whether the current fetch is incremental
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum Incrementality {
+    /// The feed contains all updates
+    FullDataset = 0,
+
+    /// The feed only contains changes since the last message
+    Differential = 1,
+}
+
+/// A definition (or update) of an entity in the transit feed
+#[derive(Clone, PartialEq, Serialize, Message)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedEntity {
+    /// The ids are used only to provide incrementality support
+    #[prost(string, required, tag = "1")]
+    pub id: String,
+
+    /// Whether this entity is to be deleted
+    #[prost(bool, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_deleted: Option<bool>,
+
+    /// Realtime update of the progress of a trip
+    #[prost(message, optional, tag = "3")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trip_update: Option<TripUpdate>,
+}
+
+/// Realtime update of the progress of a vehicle along a trip
+#[derive(Clone, PartialEq, Serialize, Message)]
+#[serde(rename_all = "camelCase")]
+pub struct TripUpdate {
+    /// The trip that this message applies to
+    #[prost(message, required, tag = "1")]
+    pub trip: TripDescriptor,
+
+    /// Updates to the stop times of the trip, ordered by their stop sequence
+    #[prost(message, repeated, tag = "2")]
+    pub stop_time_update: Vec<StopTimeUpdate>,
+
+    /// Additional information on the vehicle that is serving this trip
+    #[prost(message, optional, tag = "3")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vehicle: Option<VehicleDescriptor>,
+
+    /// When the vehicle's real-time progress was measured, in POSIX time
+    #[prost(uint64, optional, tag = "4")]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_64_bit"
+    )]
+    pub timestamp: Option<u64>,
+}
+
+/// Identifies an instance of a GTFS trip
+#[derive(Clone, PartialEq, Serialize, Message)]
+#[serde(rename_all = "camelCase")]
+pub struct TripDescriptor {
+    /// The trip_id from the GTFS feed that this selector refers to
+    #[prost(string, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trip_id: Option<String>,
+
+    /// The scheduled start time of this trip instance (`HH:MM:SS`)
+    #[prost(string, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<String>,
+
+    /// The scheduled start date of this trip instance (`YYYYMMDD`)
+    #[prost(string, optional, tag = "3")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_date: Option<String>,
+
+    /// The relation between this trip and the static schedule. Uses
+    /// [TripScheduleRelationship]
+    #[prost(enumeration = "TripScheduleRelationship", optional, tag = "4")]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_trip_relationship"
+    )]
+    pub schedule_relationship: Option<i32>,
+
+    /// The route_id from the GTFS feed that this selector refers to
+    #[prost(string, optional, tag = "5")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route_id: Option<String>,
+}
+
+/// The relation between a trip and the static schedule
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum TripScheduleRelationship {
+    /// Trip that is running in accordance with its GTFS schedule
+    Scheduled = 0,
+
+    /// An extra trip that was added in addition to a running schedule
+    Added = 1,
+
+    /// A trip that is running with no schedule associated to it
+    Unscheduled = 2,
+
+    /// A trip that existed in the schedule but was removed
+    Canceled = 3,
+}
+
+/// Identification information for the vehicle performing the trip
+#[derive(Clone, PartialEq, Serialize, Message)]
+#[serde(rename_all = "camelCase")]
+pub struct VehicleDescriptor {
+    /// Internal system identification of the vehicle
+    #[prost(string, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    /// User visible label, i.e., something that must be shown to the passenger
+    #[prost(string, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// Realtime update for arrival and/or departure events for a given stop on a trip
+#[derive(Clone, PartialEq, Serialize, Message)]
+#[serde(rename_all = "camelCase")]
+pub struct StopTimeUpdate {
+    /// Must be the same as in stop_times.txt in the corresponding GTFS feed
+    #[prost(uint32, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequence: Option<u32>,
+
+    /// When the bus arrives at the stop
+    #[prost(message, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arrival: Option<StopTimeEvent>,
+
+    /// When the bus departs from the stop
+    #[prost(message, optional, tag = "3")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub departure: Option<StopTimeEvent>,
+
+    /// Must be the same as in stops.txt in the corresponding GTFS feed
+    #[prost(string, optional, tag = "4")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_id: Option<String>,
+
+    /// The relation between the stop time and the static schedule. Uses
+    /// [StopScheduleRelationship]
+    #[prost(enumeration = "StopScheduleRelationship", optional, tag = "5")]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_stop_relationship"
+    )]
+    pub schedule_relationship: Option<i32>,
+}
+
+/// The relation between a stop time and the static schedule
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum StopScheduleRelationship {
+    /// The vehicle is proceeding in accordance with its static schedule of stops
+    Scheduled = 0,
+
+    /// The stop is skipped, i.e., the vehicle will not stop at this stop
+    Skipped = 1,
+
+    /// No data is given for this stop
+    NoData = 2,
+}
+
+/// Timing information for a single predicted event (either arrival or departure)
+#[derive(Clone, PartialEq, Serialize, Message)]
+#[serde(rename_all = "camelCase")]
+pub struct StopTimeEvent {
+    /// Delay (in seconds) compared to the scheduled time. Positive values mean the bus is late.
+    #[prost(int32, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delay: Option<i32>,
+
+    /// Estimated time of the event, in POSIX time
+    #[prost(int64, optional, tag = "2")]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_64_bit"
+    )]
+    pub time: Option<i64>,
+}
+
+/// Converts the schedules of stops into a feed of trip updates.
+///
+/// Each scheduled stop becomes a `StopTimeUpdate` of its trip. The trip is identified by the
+/// first part of the scheduled stop's key, the stop sequence by the part after the last `-`.
+/// Cancelled scheduled stops are skipped, and a trip of which all stops are cancelled is
+/// cancelled. The bus of the next stop of the trip becomes its vehicle, or the bus of its last
+/// stop, if the trip is over.
+///
+/// # Arguments
+///
+/// * `schedules`: The schedules of the stops, e.g. from
+///   [stop_schedule](crate::TransitClient::stop_schedule)
+/// * `timestamp`: When the schedules were fetched. Its offset is used to convert the local times
+///   of the schedules into POSIX time.
+///
+/// returns: FeedMessage
+///
+/// # Examples
+///
+/// ```no_run
+/// use transit_api_client::gtfs::realtime;
+/// use transit_api_client::prelude::*;
+/// use transit_api_client::timezone;
+///
+/// # tokio_test::block_on(async {
+/// let client = TransitClient::new("<YOUR_API_TOKEN>".to_string());
+/// let mut schedules = Vec::new();
+/// for stop in [10064, 10185] {
+///     schedules.push(client.stop_schedule(stop, vec![], Usage::Normal).await.unwrap());
+/// }
+/// let feed = realtime::trip_updates(&schedules, timezone::now());
+/// let bytes = feed.to_bytes();
+/// # });
+/// ```
+pub fn trip_updates(schedules: &[Schedule], timestamp: OffsetDateTime) -> FeedMessage {
+    let offset = timestamp.offset();
+    let posix = |time: PrimitiveDateTime| time.assume_offset(offset).unix_timestamp();
+    let event = |time: &Option<Time>| -> Option<StopTimeEvent> {
+        let time = time.as_ref()?;
+        Some(StopTimeEvent {
+            delay: time.delay().map(|delay| delay.whole_seconds() as i32),
+            time: Some(posix(time.best())),
+        })
+    };
+
+    let now = PrimitiveDateTime::new(timestamp.date(), timestamp.time());
+    // How far a stop is from now, upcoming stops first
+    let distance = |time: PrimitiveDateTime| {
+        if time >= now {
+            (false, time - now)
+        } else {
+            (true, now - time)
+        }
+    };
+
+    // Sorted by the id of the trip, so the feed does not change, if nothing changed
+    let mut trips: BTreeMap<String, TripUpdate> = BTreeMap::new();
+    let mut vehicles: HashMap<String, ((bool, Duration), &Bus)> = HashMap::new();
+    for schedule in schedules {
+        for route_schedule in &schedule.route_schedules {
+            for scheduled_stop in &route_schedule.scheduled_stops {
+                let (trip_id, stop_sequence) = match scheduled_stop.key.rsplit_once('-') {
+                    Some((trip, sequence)) => (trip.to_string(), sequence.parse().ok()),
+                    None => (scheduled_stop.key.clone(), None),
+                };
+
+                let times = &scheduled_stop.times;
+                let start = times.scheduled();
+
+                // The trip is served by the bus of its stop closest to now
+                if let (Some(bus), Some(time)) = (&scheduled_stop.bus, times.best()) {
+                    let distance = distance(time);
+                    let closest = vehicles.get(&trip_id);
+                    if !matches!(closest, Some((closest, _)) if *closest <= distance) {
+                        vehicles.insert(trip_id.clone(), (distance, bus));
+                    }
+                }
+
+                let update = trips.entry(trip_id.clone()).or_insert_with(|| TripUpdate {
+                    trip: TripDescriptor {
+                        trip_id: Some(trip_id),
+                        start_time: None,
+                        start_date: start.and_then(|start| {
+                            start.format(format_description!("[year][month][day]")).ok()
+                        }),
+                        schedule_relationship: Some(TripScheduleRelationship::Scheduled as i32),
+                        route_id: Some(route_schedule.route.key.to_string()),
+                    },
+                    stop_time_update: Vec::new(),
+                    vehicle: None,
+                    timestamp: Some(timestamp.unix_timestamp() as u64),
+                });
+
+                update.stop_time_update.push(StopTimeUpdate {
+                    stop_sequence,
+                    arrival: event(&times.arrival),
+                    departure: event(&times.departure),
+                    stop_id: Some(schedule.stop.key.to_string()),
+                    schedule_relationship: Some(if scheduled_stop.cancelled {
+                        StopScheduleRelationship::Skipped as i32
+                    } else {
+                        StopScheduleRelationship::Scheduled as i32
+                    }),
+                });
+            }
+        }
+    }
+
+    let entity = trips
+        .into_iter()
+        .map(|(id, mut update)| {
+            update.vehicle = vehicles.get(&id).map(|(_, bus)| VehicleDescriptor {
+                id: Some(bus.key.to_string()),
+                label: Some(bus.key.to_string()),
+            });
+            update
+                .stop_time_update
+                .sort_by_key(|update| update.stop_sequence);
+            let skipped = Some(StopScheduleRelationship::Skipped as i32);
+            if update
+                .stop_time_update
+                .iter()
+                .all(|update| update.schedule_relationship == skipped)
+            {
+                update.trip.schedule_relationship = Some(TripScheduleRelationship::Canceled as i32);
+            }
+            FeedEntity {
+                id,
+                is_deleted: None,
+                trip_update: Some(update),
+            }
+        })
+        .collect();
+
+    FeedMessage {
+        header: FeedHeader {
+            gtfs_realtime_version: GTFS_REALTIME_VERSION.to_string(),
+            incrementality: Some(Incrementality::FullDataset as i32),
+            timestamp: Some(timestamp.unix_timestamp() as u64),
+        },
+        entity,
+    }
+}
+
+/// Serializes a 64 bit integer as a string, as the protocol buffer JSON mapping does.
+fn serialize_64_bit<T: Display, S: Serializer>(
+    value: &Option<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serializer.collect_str(value),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Serializes an enumeration by its name, as the protocol buffer JSON mapping does.
+fn serialize_name<S: Serializer>(
+    name: Option<&'static str>,
+    value: &Option<i32>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match (name, value) {
+        (Some(name), _) => serializer.serialize_str(name),
+        (None, Some(value)) => serializer.serialize_i32(*value),
+        (None, None) => serializer.serialize_none(),
+    }
+}
+
+fn serialize_incrementality<S: Serializer>(
+    value: &Option<i32>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let name = value
+        .and_then(Incrementality::from_i32)
+        .map(|value| match value {
+            Incrementality::FullDataset => "FULL_DATASET",
+            Incrementality::Differential => "DIFFERENTIAL",
+        });
+    serialize_name(name, value, serializer)
+}
+
+fn serialize_trip_relationship<S: Serializer>(
+    value: &Option<i32>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let name = value
+        .and_then(TripScheduleRelationship::from_i32)
+        .map(|value| match value {
+            TripScheduleRelationship::Scheduled => "SCHEDULED",
+            TripScheduleRelationship::Added => "ADDED",
+            TripScheduleRelationship::Unscheduled => "UNSCHEDULED",
+            TripScheduleRelationship::Canceled => "CANCELED",
+        });
+    serialize_name(name, value, serializer)
+}
+
+fn serialize_stop_relationship<S: Serializer>(
+    value: &Option<i32>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let name = value
+        .and_then(StopScheduleRelationship::from_i32)
+        .map(|value| match value {
+            StopScheduleRelationship::Scheduled => "SCHEDULED",
+            StopScheduleRelationship::Skipped => "SKIPPED",
+            StopScheduleRelationship::NoData => "NO_DATA",
+        });
+    serialize_name(name, value, serializer)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::gtfs::realtime;
+    use crate::prelude::*;
+    use crate::structs::stops::{self, test::departure};
+    use time::macros::datetime;
+
+    /// A late bus and a cancelled one
+    fn schedule() -> Schedule {
+        let mut late = departure(
+            "18813225-47",
+            "16-1-K",
+            datetime!(2023-06-01 7:10),
+            datetime!(2023-06-01 7:12:30),
+        );
+        late.times.arrival = late.times.departure.clone();
+        late.bus = Some(Bus {
+            key: 511,
+            bike_rack: true,
+            wifi: false,
+        });
+        let cancelled = ScheduledStop {
+            cancelled: true,
+            ..departure(
+                "18813226-47",
+                "16-1-K",
+                datetime!(2023-06-01 7:25),
+                datetime!(2023-06-01 7:25),
+            )
+        };
+        stops::test::schedule(vec![late, cancelled])
+    }
+
+    #[test]
+    fn trip_updates() -> Result<(), crate::structs::Error> {
+        let timestamp = datetime!(2023-06-01 7:00 -5);
+        let feed = realtime::trip_updates(&[schedule()], timestamp);
+        log::info!("feed={:?}", &feed);
+
+        assert_eq!(feed.entity.len(), 2);
+        let late = feed.entity[0].trip_update.as_ref().unwrap();
+        assert_eq!(late.trip.trip_id.as_deref(), Some("18813225"));
+        assert_eq!(late.trip.route_id.as_deref(), Some("16"));
+        assert_eq!(late.vehicle.as_ref().unwrap().id.as_deref(), Some("511"));
+        let stop_time = &late.stop_time_update[0];
+        assert_eq!(stop_time.stop_sequence, Some(47));
+        assert_eq!(stop_time.stop_id.as_deref(), Some("10064"));
+        assert_eq!(stop_time.departure.as_ref().unwrap().delay, Some(150));
+        assert_eq!(
+            stop_time.departure.as_ref().unwrap().time,
+            Some(datetime!(2023-06-01 7:12:30 -5).unix_timestamp())
+        );
+
+        let cancelled = feed.entity[1].trip_update.as_ref().unwrap();
+        assert_eq!(
+            cancelled.trip.schedule_relationship,
+            Some(realtime::TripScheduleRelationship::Canceled as i32)
+        );
+        // The first stop of a trip has no arrival
+        assert_eq!(cancelled.stop_time_update[0].arrival, None);
+
+        assert_eq!(realtime::FeedMessage::from_bytes(&feed.to_bytes())?, feed);
+        let json = feed.to_json()?;
+        assert!(json.contains(r#""gtfsRealtimeVersion":"2.0""#));
+        assert!(json.contains(r#""scheduleRelationship":"CANCELED""#));
+        assert!(json.contains(r#""scheduleRelationship":"SKIPPED""#));
+        // 64 bit integers are strings in JSON
+        assert!(json.contains(r#""timestamp":"1685620800""#));
+        assert!(json.contains(&format!(
+            r#""time":"{}""#,
+            datetime!(2023-06-01 7:12:30 -5).unix_timestamp()
+        )));
+        Ok(())
+    }
+
+    #[test]
+    fn vehicle() {
+        let passing = |key: &str, time, bus: Option<u32>| ScheduledStop {
+            bus: bus.map(|key| Bus {
+                key,
+                bike_rack: true,
+                wifi: false,
+            }),
+            ..departure(key, "16-1-K", time, time)
+        };
+        let schedule = stops::test::schedule(vec![
+            passing("18813227-46", datetime!(2023-06-01 6:55), Some(511)),
+            passing("18813227-45", datetime!(2023-06-01 6:50), None),
+            passing("18813227-48", datetime!(2023-06-01 7:10), Some(513)),
+            passing("18813227-47", datetime!(2023-06-01 7:05), Some(512)),
+            passing("18813228-46", datetime!(2023-06-01 6:40), Some(514)),
+            passing("18813228-47", datetime!(2023-06-01 6:45), Some(515)),
+        ]);
+        let feed = realtime::trip_updates(&[schedule], datetime!(2023-06-01 7:00 -5));
+        let vehicles: Vec<Option<&str>> = feed
+            .entity
+            .iter()
+            .map(|entity| {
+                let update = entity.trip_update.as_ref().unwrap();
+                update.vehicle.as_ref().unwrap().id.as_deref()
+            })
+            .collect();
+        // The bus of the next stop, or of the last one, if the trip is over
+        assert_eq!(vehicles, vec![Some("512"), Some("515")]);
+    }
+}
@@ -0,0 +1,1126 @@
+// TransitTrail - Navigate Winnipeg Transit with a different style
+// Copyright (C) - 2023 Foxx Azalea Pinkerton, Max Fehlinger
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! A journey planner, that works offline on a [Feed]. It implements
+//! [RAPTOR](https://www.microsoft.com/en-us/research/publication/round-based-public-transit-routing/)
+//! and returns the same [Plan]s as the [trip_planner](crate::TransitClient::trip_planner)
+//! endpoint.
+//!
+
+use std::collections::HashMap;
+
+use time::{Date, Duration, PrimitiveDateTime};
+
+use crate::filters::{self, Mode};
+use crate::gtfs::{self, Feed};
+use crate::structs::{
+    common::{GeoLocation, PartialLocation},
+    routes::{badges::Style, Blue, Regular, Route, Variant},
+    stops::BusType,
+    trip_planner::{self, Bounds, Durations, Plan, Ride, Segment, Times, Transfer, TripStop, Walk},
+    Error,
+};
+use crate::timezone;
+
+/// Stops closer than this (in metres, as the crow flies) are connected with footpaths, which can
+/// be used to transfer between them.
+pub const TRANSFER_RADIUS: f64 = 400.0;
+
+/// Walking distances are estimated as the direct distance multiplied by this factor, as streets
+/// rarely lead directly to the destination.
+pub const DETOUR_FACTOR: f64 = 1.3;
+
+/// How far (in seconds) before or after the requested time a search starts, when planning with
+/// [Mode::DepartBefore] or [Mode::ArriveAfter].
+const SEARCH_WINDOW: i32 = 60 * 60;
+
+/// An arrival time, that has not been reached (yet).
+const UNREACHED: i32 = i32::MAX;
+
+/// Plans trips on a [Feed], without using the API.
+///
+/// # Examples
+///
+/// ```no_run
+/// use transit_api_client::gtfs::{Feed, Router};
+/// use transit_api_client::prelude::*;
+///
+/// let router = Router::new(Feed::from_dir("google_transit").unwrap());
+/// let plans = router
+///     .plan(
+///         PartialLocation::Point(49.86917, -97.1391),
+///         PartialLocation::Stop(10185),
+///         vec![filters::TripPlan::MaxTransfers(2)],
+///     )
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Router {
+    feed: Feed,
+
+    /// Trips grouped by the exact sequence of stops they serve
+    patterns: Vec<Pattern>,
+
+    /// Stops within the [TRANSFER_RADIUS] of each stop, with their direct distance in metres
+    footpaths: Vec<Vec<(usize, f64)>>,
+
+    /// The stop numbers, which riders know, mapped to the index of the stop in the feed
+    stop_numbers: HashMap<u32, usize>,
+}
+
+/// A sequence of stops, that is served by at least one trip
+#[derive(Debug)]
+struct Pattern {
+    stops: Vec<usize>,
+    trips: Vec<usize>,
+}
+
+/// The trips of all patterns, which run on the searched day, in the direction they are searched
+/// in.
+struct Timetable {
+    patterns: Vec<ActivePattern>,
+
+    /// For every stop, the patterns serving it together with the stop's position in the pattern
+    stop_patterns: Vec<Vec<(usize, usize)>>,
+}
+
+struct ActivePattern {
+    stops: Vec<usize>,
+
+    /// Sorted by their departure at the first stop
+    trips: Vec<ActiveTrip>,
+}
+
+struct ActiveTrip {
+    /// The index of the trip in the feed
+    trip: usize,
+    arrivals: Vec<i32>,
+    departures: Vec<i32>,
+}
+
+/// How a stop was reached in a round of the search
+#[derive(Clone, Copy, Debug)]
+enum Label {
+    /// Walked there from the start of the search
+    Access { seconds: i32 },
+
+    /// Rode a trip of a pattern, between two positions of that pattern
+    Ride {
+        pattern: usize,
+        trip: usize,
+        board: usize,
+        alight: usize,
+    },
+
+    /// Walked there from another stop, after riding to that stop
+    Walk { from: usize, seconds: i32 },
+}
+
+/// Where a leg of a journey starts or ends
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Place {
+    Origin,
+    Destination,
+    Stop(usize),
+}
+
+impl Place {
+    /// Swaps origin and destination, when a journey was searched backwards
+    fn flipped(self) -> Self {
+        match self {
+            Place::Origin => Place::Destination,
+            Place::Destination => Place::Origin,
+            stop => stop,
+        }
+    }
+}
+
+/// A part of a journey, found in the search
+#[derive(Clone, Debug)]
+enum Leg {
+    Walk {
+        from: Place,
+        to: Place,
+        seconds: i32,
+    },
+    Ride {
+        trip: usize,
+        from: usize,
+        to: usize,
+        departure: i32,
+        arrival: i32,
+    },
+}
+
+/// The origin or destination of a trip
+struct Endpoint {
+    centre: GeoLocation,
+    stop: Option<usize>,
+}
+
+/// The options of a search, taken from [filters::TripPlan]s
+struct Query {
+    date: Date,
+    time: i32,
+    mode: Mode,
+
+    /// Metres per second
+    walk_speed: f64,
+
+    /// Seconds
+    max_walk: i32,
+
+    /// Seconds
+    min_wait: i32,
+
+    /// Seconds
+    max_wait: i32,
+    max_transfers: usize,
+}
+
+impl Query {
+    /// Reads the filters. Options that are not specified default to the same values as the
+    /// settings of the app.
+    fn new(filters: Vec<filters::TripPlan>) -> Self {
+        let now = timezone::now();
+        let mut query = Query {
+            date: now.date(),
+            time: now.hour() as i32 * 3600 + now.minute() as i32 * 60,
+            mode: Mode::default(),
+            walk_speed: 4.0 / 3.6,
+            max_walk: 30 * 60,
+            min_wait: 0,
+            max_wait: 60 * 60,
+            max_transfers: 10,
+        };
+
+        for filter in filters {
+            match filter {
+                filters::TripPlan::Date(date) => query.date = date,
+                filters::TripPlan::Time(hours, minutes) => {
+                    query.time = hours as i32 * 3600 + minutes as i32 * 60
+                }
+                filters::TripPlan::Mode(mode) => query.mode = mode,
+                filters::TripPlan::WalkSpeed(speed) => query.walk_speed = speed as f64 / 3.6,
+                filters::TripPlan::MaxWalkTime(minutes) => query.max_walk = minutes as i32 * 60,
+                filters::TripPlan::MinTransferWait(minutes) => query.min_wait = minutes as i32 * 60,
+                filters::TripPlan::MaxTransferWait(minutes) => query.max_wait = minutes as i32 * 60,
+                filters::TripPlan::MaxTransfers(transfers) => {
+                    query.max_transfers = transfers as usize
+                }
+            }
+        }
+
+        query
+    }
+
+    /// How many seconds it takes to walk between two points
+    fn walk_seconds(&self, distance: f64) -> i32 {
+        (distance * DETOUR_FACTOR / self.walk_speed).ceil() as i32
+    }
+}
+
+impl Router {
+    /// Prepares a feed for planning trips. This groups the trips into patterns and finds the
+    /// footpaths between stops, so it should only be done once per feed.
+    ///
+    /// # Arguments
+    ///
+    /// * `feed`: The GTFS feed to plan trips on
+    ///
+    /// returns: Router
+    pub fn new(feed: Feed) -> Self {
+        let mut pattern_ids: HashMap<Vec<usize>, usize> = HashMap::new();
+        let mut patterns: Vec<Pattern> = Vec::new();
+        for (index, trip) in feed.trips.iter().enumerate() {
+            if trip.stop_times.len() < 2 {
+                continue;
+            }
+            let stops: Vec<usize> = trip.stop_times.iter().map(|st| st.stop).collect();
+            let pattern = *pattern_ids.entry(stops.clone()).or_insert_with(|| {
+                patterns.push(Pattern {
+                    stops,
+                    trips: Vec::new(),
+                });
+                patterns.len() - 1
+            });
+            patterns[pattern].trips.push(index);
+        }
+
+        // Sweep over the stops sorted by latitude, so only stops in a narrow band have to be
+        // compared with each other.
+        let mut footpaths = vec![Vec::new(); feed.stops.len()];
+        let mut by_latitude: Vec<usize> = (0..feed.stops.len()).collect();
+        by_latitude.sort_by(|a, b| {
+            feed.stops[*a]
+                .centre
+                .latitude
+                .total_cmp(&feed.stops[*b].centre.latitude)
+        });
+        let band = TRANSFER_RADIUS / 111_000.0;
+        for (i, &a) in by_latitude.iter().enumerate() {
+            for &b in &by_latitude[i + 1..] {
+                let (from, to) = (&feed.stops[a].centre, &feed.stops[b].centre);
+                if to.latitude - from.latitude > band {
+                    break;
+                }
+                let distance = from.distance(to);
+                if distance <= TRANSFER_RADIUS {
+                    footpaths[a].push((b, distance));
+                    footpaths[b].push((a, distance));
+                }
+            }
+        }
+
+        let stop_numbers = feed
+            .stops
+            .iter()
+            .enumerate()
+            .filter_map(|(index, stop)| stop.number.map(|number| (number, index)))
+            .collect();
+
+        Self {
+            feed,
+            patterns,
+            footpaths,
+            stop_numbers,
+        }
+    }
+
+    /// The feed trips are planned on
+    pub fn feed(&self) -> &Feed {
+        &self.feed
+    }
+
+    /// Plans trips from an origin to a destination.
+    ///
+    /// Only [PartialLocation::Stop] and [PartialLocation::Point] can be used, as addresses,
+    /// monuments and intersections are not part of the feed. The filters are applied like the
+    /// [trip_planner](crate::TransitClient::trip_planner) endpoint does. Options that are not
+    /// specified default to the same values as the settings of the app.
+    ///
+    /// A plan is returned for every number of transfers that leads to an earlier arrival (or a
+    /// later departure, when arriving before a time), and for walking directly, if the
+    /// destination is close enough.
+    ///
+    /// # Arguments
+    ///
+    /// * `origin`: A location, where the trip should start
+    /// * `destination`: A location, where the trip should end
+    /// * `filters`: Any potential filters, to personalize the trip
+    ///
+    /// returns: Result<Vec\<Plan\>, Error>
+    pub fn plan(
+        &self,
+        origin: PartialLocation<'_>,
+        destination: PartialLocation<'_>,
+        filters: Vec<filters::TripPlan>,
+    ) -> Result<Vec<Plan>, Error> {
+        let query = Query::new(filters);
+        let origin = self.endpoint(&origin)?;
+        let destination = self.endpoint(&destination)?;
+        let access = self.nearby(&origin, &query);
+        let egress = self.nearby(&destination, &query);
+
+        let (backwards, start) = match query.mode {
+            Mode::DepartAfter => (false, query.time),
+            Mode::DepartBefore => (false, query.time - SEARCH_WINDOW),
+            Mode::ArriveBefore => (true, query.time),
+            Mode::ArriveAfter => (true, query.time + SEARCH_WINDOW),
+        };
+
+        let mut journeys = if backwards {
+            let timetable = self.timetable(query.date, true);
+            self.search(&timetable, &egress, &access, -start, &query)
+                .into_iter()
+                .map(reverse)
+                .collect()
+        } else {
+            let timetable = self.timetable(query.date, false);
+            self.search(&timetable, &access, &egress, start, &query)
+        };
+
+        let walk = query.walk_seconds(origin.centre.distance(&destination.centre));
+        if walk <= query.max_walk {
+            journeys.push(vec![Leg::Walk {
+                from: Place::Origin,
+                to: Place::Destination,
+                seconds: walk,
+            }]);
+        }
+
+        let mut plans: Vec<Plan> = journeys
+            .iter()
+            .map(|legs| self.to_plan(legs, &query, start, backwards, &origin, &destination))
+            .filter(|plan| match query.mode {
+                Mode::DepartBefore => plan.times.start <= Some(datetime(query.date, query.time)),
+                Mode::ArriveAfter => plan.times.end >= Some(datetime(query.date, query.time)),
+                _ => true,
+            })
+            .collect();
+        plans.sort_by_key(|plan| plan.times.end);
+
+        Ok(plans)
+    }
+
+    /// Finds the stop in the feed or uses the geographic point
+    fn endpoint(&self, location: &PartialLocation<'_>) -> Result<Endpoint, Error> {
+        match location {
+            PartialLocation::Point(latitude, longitude) => Ok(Endpoint {
+                centre: GeoLocation::new(*latitude, *longitude),
+                stop: None,
+            }),
+            PartialLocation::Stop(number) => {
+                let stop = *self.stop_numbers.get(number).ok_or_else(|| {
+                    Error::Gtfs(format!("Stop #{number} is not part of the feed"))
+                })?;
+                Ok(Endpoint {
+                    centre: self.feed.stops[stop].centre.clone(),
+                    stop: Some(stop),
+                })
+            }
+            other => Err(Error::Gtfs(format!(
+                "`{other}` can not be planned offline. Use a stop or a point instead."
+            ))),
+        }
+    }
+
+    /// All stops within walking distance of an endpoint, with the seconds it takes to walk there
+    fn nearby(&self, endpoint: &Endpoint, query: &Query) -> Vec<(usize, i32)> {
+        self.feed
+            .stops
+            .iter()
+            .enumerate()
+            .map(|(index, stop)| {
+                if endpoint.stop == Some(index) {
+                    (index, 0)
+                } else {
+                    (
+                        index,
+                        query.walk_seconds(endpoint.centre.distance(&stop.centre)),
+                    )
+                }
+            })
+            .filter(|(_, seconds)| *seconds <= query.max_walk)
+            .collect()
+    }
+
+    /// Collects the trips, that run on the date (or started on the previous day and run past
+    /// midnight). When searching backwards, all trips are reversed and their times negated, so
+    /// the same search can find the latest departure.
+    fn timetable(&self, date: Date, backwards: bool) -> Timetable {
+        let days = [Some((date, 0)), date.previous_day().map(|d| (d, -86_400))];
+
+        let mut patterns = Vec::with_capacity(self.patterns.len());
+        for pattern in &self.patterns {
+            let mut trips = Vec::new();
+            for &index in &pattern.trips {
+                let trip = &self.feed.trips[index];
+                for &(day, offset) in days.iter().flatten() {
+                    if !self.feed.is_active(&trip.service_id, day) {
+                        continue;
+                    }
+                    let mut arrivals: Vec<i32> = trip
+                        .stop_times
+                        .iter()
+                        .map(|st| st.arrival + offset)
+                        .collect();
+                    let mut departures: Vec<i32> = trip
+                        .stop_times
+                        .iter()
+                        .map(|st| st.departure + offset)
+                        .collect();
+                    if arrivals.last().copied().unwrap_or_default() < 0 {
+                        // Ended before the searched day
+                        continue;
+                    }
+                    if backwards {
+                        (arrivals, departures) = (
+                            departures.iter().rev().map(|t| -t).collect(),
+                            arrivals.iter().rev().map(|t| -t).collect(),
+                        );
+                    }
+                    trips.push(ActiveTrip {
+                        trip: index,
+                        arrivals,
+                        departures,
+                    });
+                }
+            }
+            trips.sort_by_key(|trip| trip.departures[0]);
+
+            let mut stops = pattern.stops.clone();
+            if backwards {
+                stops.reverse();
+            }
+            patterns.push(ActivePattern { stops, trips });
+        }
+
+        let mut stop_patterns = vec![Vec::new(); self.feed.stops.len()];
+        for (index, pattern) in patterns.iter().enumerate() {
+            if pattern.trips.is_empty() {
+                continue;
+            }
+            for (position, &stop) in pattern.stops.iter().enumerate() {
+                stop_patterns[stop].push((index, position));
+            }
+        }
+
+        Timetable {
+            patterns,
+            stop_patterns,
+        }
+    }
+
+    /// Runs the rounds of RAPTOR. Every round allows one more trip to be taken. A journey is
+    /// returned for every round, that improves the arrival at the target.
+    fn search(
+        &self,
+        timetable: &Timetable,
+        access: &[(usize, i32)],
+        egress: &[(usize, i32)],
+        start: i32,
+        query: &Query,
+    ) -> Vec<Vec<Leg>> {
+        let stop_count = self.feed.stops.len();
+        let rounds = query.max_transfers + 1;
+        let mut arrivals = vec![vec![UNREACHED; stop_count]; rounds + 1];
+        let mut labels: Vec<Vec<Option<Label>>> = vec![vec![None; stop_count]; rounds + 1];
+        let mut best = vec![UNREACHED; stop_count];
+        let mut marked = vec![false; stop_count];
+
+        for &(stop, seconds) in access {
+            let arrival = start + seconds;
+            if arrival < arrivals[0][stop] {
+                arrivals[0][stop] = arrival;
+                best[stop] = arrival;
+                labels[0][stop] = Some(Label::Access { seconds });
+                marked[stop] = true;
+            }
+        }
+
+        let mut journeys = Vec::new();
+        let mut best_target = UNREACHED;
+        for round in 1..=rounds {
+            // Every pattern has to be scanned from the first stop, that was improved
+            let mut queue: HashMap<usize, usize> = HashMap::new();
+            for stop in (0..stop_count).filter(|&stop| marked[stop]) {
+                for &(pattern, position) in &timetable.stop_patterns[stop] {
+                    let first = queue.entry(pattern).or_insert(position);
+                    *first = (*first).min(position);
+                }
+            }
+            marked.iter_mut().for_each(|m| *m = false);
+            arrivals[round] = arrivals[round - 1].clone();
+
+            for (pattern_index, first) in queue {
+                let pattern = &timetable.patterns[pattern_index];
+                // The trip currently ridden and where it was boarded
+                let mut current: Option<(usize, usize)> = None;
+
+                for position in first..pattern.stops.len() {
+                    let stop = pattern.stops[position];
+
+                    if let Some((trip, board)) = current {
+                        let arrival = pattern.trips[trip].arrivals[position];
+                        if arrival < best[stop].min(best_target) {
+                            arrivals[round][stop] = arrival;
+                            best[stop] = arrival;
+                            labels[round][stop] = Some(Label::Ride {
+                                pattern: pattern_index,
+                                trip,
+                                board,
+                                alight: position,
+                            });
+                            marked[stop] = true;
+                        }
+                    }
+
+                    let previous = arrivals[round - 1][stop];
+                    if previous == UNREACHED {
+                        continue;
+                    }
+                    let transfer = round > 1;
+                    let ready = if transfer {
+                        previous + query.min_wait
+                    } else {
+                        previous
+                    };
+                    let candidate = pattern
+                        .trips
+                        .partition_point(|trip| trip.departures[position] < ready);
+                    let Some(trip) = pattern.trips.get(candidate) else {
+                        continue;
+                    };
+                    let departure = trip.departures[position];
+                    if transfer && departure - previous > query.max_wait {
+                        continue;
+                    }
+                    let earlier = match current {
+                        Some((ridden, _)) => departure < pattern.trips[ridden].departures[position],
+                        None => true,
+                    };
+                    if earlier {
+                        current = Some((candidate, position));
+                    }
+                }
+            }
+
+            // Walk to stops close by, but only directly after riding
+            for stop in 0..stop_count {
+                if !matches!(labels[round][stop], Some(Label::Ride { .. })) {
+                    continue;
+                }
+                let from = arrivals[round][stop];
+                for &(neighbour, metres) in &self.footpaths[stop] {
+                    let seconds = query.walk_seconds(metres);
+                    if seconds > query.max_walk {
+                        continue;
+                    }
+                    let arrival = from + seconds;
+                    if arrival < best[neighbour].min(best_target) {
+                        arrivals[round][neighbour] = arrival;
+                        best[neighbour] = arrival;
+                        labels[round][neighbour] = Some(Label::Walk {
+                            from: stop,
+                            seconds,
+                        });
+                        marked[neighbour] = true;
+                    }
+                }
+            }
+
+            let mut target = None;
+            for &(stop, seconds) in egress {
+                let arrival = arrivals[round][stop].saturating_add(seconds);
+                if arrival < best_target {
+                    best_target = arrival;
+                    target = Some((stop, seconds));
+                }
+            }
+            if let Some((stop, seconds)) = target {
+                journeys.push(reconstruct(timetable, &labels, round, stop, seconds));
+            }
+
+            if !marked.contains(&true) {
+                break;
+            }
+        }
+
+        journeys
+    }
+
+    /// Turns the legs of a journey into a plan, as it is returned from the API
+    fn to_plan(
+        &self,
+        legs: &[Leg],
+        query: &Query,
+        start: i32,
+        backwards: bool,
+        origin: &Endpoint,
+        destination: &Endpoint,
+    ) -> Plan {
+        let mut segments = Vec::new();
+        let mut walking = 0;
+        let mut riding = 0;
+        // When the last ride of the journey ended
+        let mut arrived: Option<(usize, i32)> = None;
+
+        for (index, leg) in legs.iter().enumerate() {
+            match leg {
+                Leg::Walk { from, to, seconds } => {
+                    walking += seconds;
+                    let next_departure = legs[index + 1..].iter().find_map(|leg| match leg {
+                        Leg::Ride { departure, .. } => Some(*departure),
+                        _ => None,
+                    });
+                    // Walking between two rides is a transfer
+                    if let (Some((_, arrival)), Some(departure), Place::Stop(_)) =
+                        (arrived, next_departure, to)
+                    {
+                        segments.push(Segment::Transfer(Transfer {
+                            bounds: self.bounds_of_places(&[*from, *to], origin, destination),
+                            from: self.trip_stop(*from, origin, destination),
+                            times: times(query.date, arrival, departure, *seconds, 0),
+                            to: self.trip_stop(*to, origin, destination),
+                        }));
+                        arrived = None;
+                        continue;
+                    }
+
+                    // Walking from an origin, that is a stop, to that stop is no walk
+                    let skip = match (from, to) {
+                        (Place::Origin, Place::Stop(stop)) => origin.stop == Some(*stop),
+                        (Place::Stop(stop), Place::Destination) => destination.stop == Some(*stop),
+                        _ => false,
+                    };
+                    if skip {
+                        continue;
+                    }
+
+                    let (begin, end) = match (arrived, next_departure) {
+                        (Some((_, arrival)), _) => (arrival, arrival + seconds),
+                        (None, Some(departure)) => (departure - seconds, departure),
+                        // Walking the whole way
+                        (None, None) if backwards => (start - seconds, start),
+                        (None, None) => (start, start + seconds),
+                    };
+                    segments.push(Segment::Walk(Walk {
+                        bounds: self.bounds_of_places(&[*from, *to], origin, destination),
+                        from: Some(self.trip_stop(*from, origin, destination)),
+                        times: times(query.date, begin, end, *seconds, 0),
+                        to: Some(self.trip_stop(*to, origin, destination)),
+                        instructions: None,
+                    }));
+                }
+                Leg::Ride {
+                    trip,
+                    from,
+                    to,
+                    departure,
+                    arrival,
+                } => {
+                    // Waiting at the same stop for the next bus is a transfer as well
+                    if let Some((stop, arrived_at)) = arrived {
+                        segments.push(Segment::Transfer(Transfer {
+                            bounds: self.bounds_of_places(
+                                &[Place::Stop(stop)],
+                                origin,
+                                destination,
+                            ),
+                            from: self.trip_stop(Place::Stop(stop), origin, destination),
+                            times: times(query.date, arrived_at, *departure, 0, 0),
+                            to: self.trip_stop(Place::Stop(*from), origin, destination),
+                        }));
+                    }
+
+                    riding += arrival - departure;
+                    segments.push(Segment::Ride(self.ride(
+                        *trip,
+                        *from,
+                        *to,
+                        times(query.date, *departure, *arrival, 0, arrival - departure),
+                    )));
+                    arrived = Some((*to, *arrival));
+                }
+            }
+        }
+
+        let time = datetime(query.date, start);
+        let (begin, end) = match (segments.first(), segments.last()) {
+            (Some(first), Some(last)) => (
+                first.times().start.unwrap_or(time),
+                last.times().end.unwrap_or(time),
+            ),
+            _ => (time, time),
+        };
+        let total = ((end - begin).whole_seconds() as i32 + 30) / 60;
+        let walking = minutes(walking);
+        let riding = minutes(riding);
+
+        Plan {
+            times: Times {
+                start: Some(begin),
+                end: Some(end),
+                durations: Durations {
+                    total: total as u32,
+                    walking,
+                    waiting: (total as u32).saturating_sub(walking + riding),
+                    riding,
+                },
+            },
+            segments,
+        }
+    }
+
+    /// Creates a ride segment for a part of a trip
+    fn ride(&self, trip: usize, from: usize, to: usize, times: Times) -> Ride {
+        let trip = &self.feed.trips[trip];
+        let route = &self.feed.routes[trip.route];
+
+        let board = trip.stop_times.iter().position(|st| st.stop == from);
+        let alight = board.and_then(|board| {
+            trip.stop_times[board..]
+                .iter()
+                .position(|st| st.stop == to)
+                .map(|alight| board + alight)
+        });
+        let bounds = match (board, alight) {
+            (Some(board), Some(alight)) => Bounds::from_points(
+                trip.stop_times[board..=alight]
+                    .iter()
+                    .map(|st| &self.feed.stops[st.stop].centre),
+            ),
+            _ => None,
+        };
+
+        Ride {
+            bounds,
+            bus: None,
+            route: route_of(route),
+            times,
+            variant: Variant {
+                key: bus_type_of(route).into(),
+                name: match &trip.headsign {
+                    Some(headsign) => Some(format!("{} to {}", route.short_name, headsign)),
+                    None => route.long_name.clone(),
+                },
+            },
+            to: None,
+            from: None,
+        }
+    }
+
+    /// Converts a place into the representation used in plans
+    fn trip_stop(&self, place: Place, origin: &Endpoint, destination: &Endpoint) -> TripStop {
+        let location = |endpoint: &Endpoint| match endpoint.stop {
+            Some(stop) => trip_planner::Location::Stop(self.stop(stop)),
+            None => trip_planner::Location::Point(endpoint.centre.clone()),
+        };
+        match place {
+            Place::Origin => TripStop::Origin(location(origin)),
+            Place::Destination => TripStop::Destination(location(destination)),
+            Place::Stop(stop) => TripStop::Stop(self.stop(stop)),
+        }
+    }
+
+    fn stop(&self, stop: usize) -> trip_planner::Stop {
+        let stop = &self.feed.stops[stop];
+        trip_planner::Stop {
+            key: stop.number.unwrap_or_default(),
+            name: stop.name.clone(),
+            centre: stop.centre.clone(),
+        }
+    }
+
+    fn bounds_of_places(
+        &self,
+        places: &[Place],
+        origin: &Endpoint,
+        destination: &Endpoint,
+    ) -> Option<Bounds> {
+        Bounds::from_points(places.iter().map(|place| match place {
+            Place::Origin => &origin.centre,
+            Place::Destination => &destination.centre,
+            Place::Stop(stop) => &self.feed.stops[*stop].centre,
+        }))
+    }
+}
+
+/// Follows the labels back from the target to the start of the search
+fn reconstruct(
+    timetable: &Timetable,
+    labels: &[Vec<Option<Label>>],
+    mut round: usize,
+    mut stop: usize,
+    egress: i32,
+) -> Vec<Leg> {
+    let mut legs = vec![Leg::Walk {
+        from: Place::Stop(stop),
+        to: Place::Destination,
+        seconds: egress,
+    }];
+
+    loop {
+        match labels[round][stop] {
+            Some(Label::Ride {
+                pattern,
+                trip,
+                board,
+                alight,
+            }) => {
+                let pattern = &timetable.patterns[pattern];
+                let trip = &pattern.trips[trip];
+                legs.push(Leg::Ride {
+                    trip: trip.trip,
+                    from: pattern.stops[board],
+                    to: pattern.stops[alight],
+                    departure: trip.departures[board],
+                    arrival: trip.arrivals[alight],
+                });
+                stop = pattern.stops[board];
+                round -= 1;
+            }
+            Some(Label::Walk { from, seconds }) => {
+                // Walks directly after each other are combined into one
+                match legs.last_mut() {
+                    Some(Leg::Walk {
+                        from: walk_from,
+                        seconds: walk_seconds,
+                        ..
+                    }) => {
+                        *walk_from = Place::Stop(from);
+                        *walk_seconds += seconds;
+                    }
+                    _ => legs.push(Leg::Walk {
+                        from: Place::Stop(from),
+                        to: Place::Stop(stop),
+                        seconds,
+                    }),
+                }
+                stop = from;
+            }
+            Some(Label::Access { seconds }) => {
+                legs.push(Leg::Walk {
+                    from: Place::Origin,
+                    to: Place::Stop(stop),
+                    seconds,
+                });
+                break;
+            }
+            // Reached in an earlier round
+            None if round > 0 => round -= 1,
+            None => break,
+        }
+    }
+
+    legs.reverse();
+    legs
+}
+
+/// Turns a journey, that was searched backwards, around
+fn reverse(legs: Vec<Leg>) -> Vec<Leg> {
+    legs.into_iter()
+        .rev()
+        .map(|leg| match leg {
+            Leg::Walk { from, to, seconds } => Leg::Walk {
+                from: to.flipped(),
+                to: from.flipped(),
+                seconds,
+            },
+            Leg::Ride {
+                trip,
+                from,
+                to,
+                departure,
+                arrival,
+            } => Leg::Ride {
+                trip,
+                from: to,
+                to: from,
+                departure: -arrival,
+                arrival: -departure,
+            },
+        })
+        .collect()
+}
+
+/// Creates a route as it is returned from the API. BLUE routes are the only routes, whose short
+/// name is not a number.
+/// The key of a GTFS route, like the API would return it
+fn bus_type_of(route: &gtfs::Route) -> BusType {
+    match route.short_name.parse() {
+        Ok(number) => BusType::Regular(number),
+        Err(_) => BusType::Blue(route.short_name.clone()),
+    }
+}
+
+fn route_of(route: &gtfs::Route) -> Route {
+    let colour = |colour: &Option<String>, default: &str| match colour {
+        Some(colour) => format!("#{colour}"),
+        None => default.to_string(),
+    };
+    let badge_style = Style {
+        background_color: colour(&route.color, "#FFFFFF"),
+        border_color: colour(&route.text_color, "#000000"),
+        color: colour(&route.text_color, "#000000"),
+        ..Default::default()
+    };
+
+    match route.short_name.parse::<u32>() {
+        Ok(number) => Route::Regular(Regular {
+            key: number,
+            number,
+            name: route.long_name.clone().unwrap_or_default(),
+            badge_label: number,
+            badge_style,
+            ..Default::default()
+        }),
+        Err(_) => Route::Blue(Blue {
+            key: route.short_name.clone(),
+            number: route.short_name.clone(),
+            badge_label: route.short_name.clone(),
+            badge_style,
+            ..Default::default()
+        }),
+    }
+}
+
+/// A time on the service day
+fn datetime(date: Date, seconds: i32) -> PrimitiveDateTime {
+    date.midnight() + Duration::seconds(seconds as i64)
+}
+
+fn minutes(seconds: i32) -> u32 {
+    ((seconds.max(0) + 30) / 60) as u32
+}
+
+/// Times of a segment, in which all time is spent walking, riding, or waiting
+fn times(date: Date, start: i32, end: i32, walking: i32, riding: i32) -> Times {
+    let total = minutes(end - start);
+    let walking = minutes(walking);
+    let riding = minutes(riding);
+    Times {
+        start: Some(datetime(date, start)),
+        end: Some(datetime(date, end)),
+        durations: Durations {
+            total,
+            walking,
+            waiting: total.saturating_sub(walking + riding),
+            riding,
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::gtfs::{Feed, Router};
+    use crate::prelude::*;
+    use time::macros::{date, datetime};
+
+    /// Two routes: 11 goes A -> B -> C, 16 goes C -> D
+    fn router() -> Router {
+        let feed = Feed::parse(
+            "stop_id,stop_code,stop_name,stop_lat,stop_lon
+1,10001,A,49.8951,-97.1384
+2,10002,B,49.8900,-97.1500
+3,10003,C,49.8850,-97.1600
+4,10004,D,49.8800,-97.1700",
+            "route_id,route_short_name,route_long_name,route_color,route_text_color
+11,11,Portage-Kildonan,FFFFFF,000000
+16,16,Selkirk-Osborne,FFFFFF,000000",
+            "route_id,service_id,trip_id,trip_headsign
+11,daily,11-a,Polo Park
+11,daily,11-b,Polo Park
+16,daily,16-a,Osborne
+16,daily,16-b,Osborne",
+            "trip_id,arrival_time,departure_time,stop_id,stop_sequence
+11-a,07:00:00,07:00:00,1,1
+11-a,07:05:00,07:05:00,2,2
+11-a,07:10:00,07:10:00,3,3
+11-b,07:30:00,07:30:00,1,1
+11-b,07:35:00,07:35:00,2,2
+11-b,07:40:00,07:40:00,3,3
+16-a,07:15:00,07:15:00,3,1
+16-a,07:25:00,07:25:00,4,2
+16-b,07:45:00,07:45:00,3,1
+16-b,07:55:00,07:55:00,4,2",
+            Some(
+                "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date
+daily,1,1,1,1,1,1,1,20230101,20301231",
+            ),
+            None,
+        )
+        .unwrap();
+        Router::new(feed)
+    }
+
+    fn filters(mode: filters::Mode, hours: u8, minutes: u8) -> Vec<filters::TripPlan> {
+        vec![
+            filters::TripPlan::Date(date!(2023 - 06 - 01)),
+            filters::TripPlan::Time(hours, minutes),
+            filters::TripPlan::Mode(mode),
+            // Only allow walking to stops right next to the origin or destination
+            filters::TripPlan::MaxWalkTime(10),
+        ]
+    }
+
+    #[test]
+    fn transfer() {
+        let plans = router()
+            .plan(
+                PartialLocation::Stop(10001),
+                PartialLocation::Stop(10004),
+                filters(filters::Mode::DepartAfter, 6, 50),
+            )
+            .unwrap();
+        log::info!("plans={:?}", &plans);
+        assert_eq!(plans.len(), 1);
+        let plan = &plans[0];
+        assert_eq!(plan.times.start, Some(datetime!(2023-06-01 7:00)));
+        assert_eq!(plan.times.end, Some(datetime!(2023-06-01 7:25)));
+        assert_eq!(plan.times.durations.riding, 20);
+        assert_eq!(plan.times.durations.waiting, 5);
+        assert!(matches!(
+            plan.segments.as_slice(),
+            [
+                trip::Segment::Ride(_),
+                trip::Segment::Transfer(_),
+                trip::Segment::Ride(_)
+            ]
+        ));
+    }
+
+    #[test]
+    fn arrive_before() {
+        let plans = router()
+            .plan(
+                PartialLocation::Stop(10001),
+                PartialLocation::Stop(10004),
+                filters(filters::Mode::ArriveBefore, 8, 0),
+            )
+            .unwrap();
+        log::info!("plans={:?}", &plans);
+        assert_eq!(plans.len(), 1);
+        assert_eq!(plans[0].times.start, Some(datetime!(2023-06-01 7:30)));
+        assert_eq!(plans[0].times.end, Some(datetime!(2023-06-01 7:55)));
+    }
+
+    #[test]
+    fn transfer_filters() {
+        let router = router();
+        let mut no_transfers = filters(filters::Mode::DepartAfter, 6, 50);
+        no_transfers.push(filters::TripPlan::MaxTransfers(0));
+        let plans = router
+            .plan(
+                PartialLocation::Stop(10001),
+                PartialLocation::Stop(10004),
+                no_transfers,
+            )
+            .unwrap();
+        assert!(plans.is_empty());
+
+        let mut long_transfer = filters(filters::Mode::DepartAfter, 6, 50);
+        long_transfer.push(filters::TripPlan::MinTransferWait(10));
+        let plans = router
+            .plan(
+                PartialLocation::Stop(10001),
+                PartialLocation::Stop(10004),
+                long_transfer,
+            )
+            .unwrap();
+        assert_eq!(plans.len(), 1);
+        assert_eq!(plans[0].times.end, Some(datetime!(2023-06-01 7:55)));
+    }
+
+    #[test]
+    fn walk_from_point() {
+        let plans = router()
+            .plan(
+                // About 150 metres from stop A
+                PartialLocation::Point(49.8964, -97.1390),
+                PartialLocation::Stop(10003),
+                filters(filters::Mode::DepartAfter, 6, 50),
+            )
+            .unwrap();
+        log::info!("plans={:?}", &plans);
+        let plan = plans.first().unwrap();
+        assert!(matches!(
+            plan.segments.as_slice(),
+            [trip::Segment::Walk(_), trip::Segment::Ride(_)]
+        ));
+        assert_eq!(plan.times.end, Some(datetime!(2023-06-01 7:10)));
+    }
+}
@@ -0,0 +1,429 @@
+// TransitTrail - Navigate Winnipeg Transit with a different style
+// Copyright (C) - 2023 Foxx Azalea Pinkerton, Max Fehlinger
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Headways (the time between two buses of the same route) at a stop.
+//!
+//! Compares the scheduled and estimated headways of each route or variant, and finds buses that
+//! bunch up, and gaps in the service.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use transit_api_client::headways::{self, Grouping, Options};
+//! use transit_api_client::prelude::*;
+//!
+//! # tokio_test::block_on(async {
+//! let client = TransitClient::new("<YOUR_API_TOKEN>".to_string());
+//! let schedule = client.stop_schedule(10064, vec![], Usage::Normal).await.unwrap();
+//! let departures = headways::departures(&schedule);
+//! for analysis in headways::analyze(&departures, Grouping::Route, &Options::default()) {
+//!     println!("{}: {} bunches", analysis.key, analysis.bunching.len());
+//! }
+//! # });
+//! ```
+//!
+
+use std::collections::BTreeMap;
+
+use time::{Duration, PrimitiveDateTime};
+
+use crate::structs::stops::Schedule;
+
+/// A bus departing from a stop
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Departure {
+    /// The key of the route
+    pub route: String,
+
+    /// The key of the variant
+    pub variant: String,
+
+    /// When the bus is scheduled to depart
+    pub scheduled: PrimitiveDateTime,
+
+    /// When the bus is estimated to depart. The same as the scheduled time, if there is no
+    /// estimate.
+    pub estimated: PrimitiveDateTime,
+}
+
+/// The departures of all buses in a schedule. Cancelled buses do not depart, so they are left
+/// out.
+///
+/// # Arguments
+///
+/// * `schedule`: The schedule of a stop
+///
+/// returns: Vec<Departure>
+pub fn departures(schedule: &Schedule) -> Vec<Departure> {
+    schedule
+        .route_schedules
+        .iter()
+        .flat_map(|route_schedule| {
+            route_schedule
+                .scheduled_stops
+                .iter()
+                .filter(|scheduled_stop| !scheduled_stop.cancelled)
+                .filter_map(|scheduled_stop| {
+                    let time = scheduled_stop.times.time()?;
+                    Some(Departure {
+                        route: route_schedule.route.key.to_string(),
+                        variant: scheduled_stop.variant.key.to_string(),
+                        scheduled: time.scheduled,
+                        estimated: time.best(),
+                    })
+                })
+        })
+        .collect()
+}
+
+/// By what departures are grouped before computing their headways
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Grouping {
+    /// All variants of a route together
+    Route,
+
+    /// Every variant by itself
+    Variant,
+}
+
+/// When buses are bunched up, or there is a gap in the service
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Options {
+    /// Two buses that depart at most this far apart are bunched up
+    pub bunching: Duration,
+
+    /// A headway that is this many times the scheduled headway at that time of day is a gap in
+    /// the service
+    pub gap_factor: f64,
+}
+
+impl Default for Options {
+    /// Bunched up within 2 minutes, a gap at twice the scheduled headway
+    fn default() -> Self {
+        Self {
+            bunching: Duration::minutes(2),
+            gap_factor: 2.0,
+        }
+    }
+}
+
+/// Two consecutive departures of the same route or variant
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Interval {
+    /// The earlier departure
+    pub first: Departure,
+
+    /// The later departure
+    pub second: Departure,
+
+    /// The estimated time between the two departures
+    pub headway: Duration,
+}
+
+/// The headways of a route or variant
+#[derive(Clone, Debug, PartialEq)]
+pub struct Analysis {
+    /// The key of the route or variant
+    pub key: String,
+
+    /// The departures, ordered by their estimated time
+    pub departures: Vec<Departure>,
+
+    /// The times between the scheduled departures, in order
+    pub scheduled_headways: Vec<Duration>,
+
+    /// The times between the estimated departures, in order
+    pub estimated_headways: Vec<Duration>,
+
+    /// Consecutive departures that are closer than [Options::bunching]
+    pub bunching: Vec<Interval>,
+
+    /// Consecutive departures that are further apart than [Options::gap_factor] times the
+    /// scheduled headway at that time of day
+    pub gaps: Vec<Interval>,
+}
+
+impl Analysis {
+    /// The mean time between scheduled departures
+    pub fn mean_scheduled_headway(&self) -> Option<Duration> {
+        mean(&self.scheduled_headways)
+    }
+
+    /// The mean time between estimated departures
+    pub fn mean_estimated_headway(&self) -> Option<Duration> {
+        mean(&self.estimated_headways)
+    }
+
+    /// The longest time between estimated departures
+    pub fn max_estimated_headway(&self) -> Option<Duration> {
+        self.estimated_headways.iter().max().copied()
+    }
+
+    /// How many buses are scheduled to depart per hour, between the first and the last
+    /// scheduled departure
+    pub fn buses_per_hour(&self) -> Option<f64> {
+        let headway = self.mean_scheduled_headway()?;
+        if headway.is_zero() {
+            return None;
+        }
+        Some(3600.0 / headway.as_seconds_f64())
+    }
+}
+
+fn mean(durations: &[Duration]) -> Option<Duration> {
+    if durations.is_empty() {
+        return None;
+    }
+    Some(durations.iter().sum::<Duration>() / durations.len() as u32)
+}
+
+/// Computes the headways of every route or variant.
+///
+/// # Arguments
+///
+/// * `departures`: The departures from a single stop, e.g. from [departures] of a live schedule
+///   or of the schedule of a [Timetable](crate::timetable::Timetable)
+/// * `grouping`: If the headways are computed per route, or per variant
+/// * `options`: When buses are bunched up, or there is a gap
+///
+/// returns: Vec<Analysis> Ordered by the key of the route or variant
+pub fn analyze(departures: &[Departure], grouping: Grouping, options: &Options) -> Vec<Analysis> {
+    let mut groups: BTreeMap<&str, Vec<Departure>> = BTreeMap::new();
+    for departure in departures {
+        let key = match grouping {
+            Grouping::Route => &departure.route,
+            Grouping::Variant => &departure.variant,
+        };
+        groups.entry(key).or_default().push(departure.clone());
+    }
+
+    groups
+        .into_iter()
+        .map(|(key, mut departures)| {
+            departures.sort_by_key(|departure| departure.scheduled);
+            let scheduled: Vec<PrimitiveDateTime> =
+                departures.iter().map(|d| d.scheduled).collect();
+            let scheduled_headways = differences(scheduled.iter().copied());
+            departures.sort_by_key(|departure| departure.estimated);
+            let estimated_headways = differences(departures.iter().map(|d| d.estimated));
+
+            let intervals = departures.windows(2).zip(&estimated_headways);
+            let bunching = intervals
+                .clone()
+                .filter(|(_, headway)| **headway <= options.bunching)
+                .map(|(pair, headway)| interval(pair, *headway))
+                .collect();
+            let gaps = intervals
+                .filter(
+                    |(pair, headway)| match slot_headway(&scheduled, pair[1].scheduled) {
+                        Some(slot) => {
+                            headway.as_seconds_f64() >= slot.as_seconds_f64() * options.gap_factor
+                        }
+                        None => false,
+                    },
+                )
+                .map(|(pair, headway)| interval(pair, *headway))
+                .collect();
+
+            Analysis {
+                key: key.to_string(),
+                departures,
+                scheduled_headways,
+                estimated_headways,
+                bunching,
+                gaps,
+            }
+        })
+        .collect()
+}
+
+fn differences(times: impl Iterator<Item = PrimitiveDateTime>) -> Vec<Duration> {
+    let times: Vec<PrimitiveDateTime> = times.collect();
+    times.windows(2).map(|pair| pair[1] - pair[0]).collect()
+}
+
+/// The scheduled headway in front of a scheduled departure, or after it for the first one. Peak
+/// and off-peak service have very different headways, so a headway is only compared to the one
+/// scheduled at the same time of day.
+fn slot_headway(scheduled: &[PrimitiveDateTime], time: PrimitiveDateTime) -> Option<Duration> {
+    let before = scheduled.partition_point(|other| *other < time);
+    match before.checked_sub(1) {
+        Some(previous) => Some(time - scheduled[previous]),
+        None => scheduled
+            .iter()
+            .find(|other| **other > time)
+            .map(|next| *next - time),
+    }
+}
+
+fn interval(pair: &[Departure], headway: Duration) -> Interval {
+    Interval {
+        first: pair[0].clone(),
+        second: pair[1].clone(),
+        headway,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::headways::{self, Departure, Grouping, Options};
+    use time::macros::datetime;
+    use time::{Duration, PrimitiveDateTime};
+
+    fn departure(
+        variant: &str,
+        scheduled: PrimitiveDateTime,
+        estimated: PrimitiveDateTime,
+    ) -> Departure {
+        Departure {
+            route: variant.split('-').next().unwrap().to_string(),
+            variant: variant.to_string(),
+            scheduled,
+            estimated,
+        }
+    }
+
+    fn departures() -> Vec<Departure> {
+        vec![
+            departure(
+                "16-1-K",
+                datetime!(2023-06-01 7:00),
+                datetime!(2023-06-01 7:00),
+            ),
+            // Late, so it catches up with the next bus
+            departure(
+                "16-1-K",
+                datetime!(2023-06-01 7:10),
+                datetime!(2023-06-01 7:19),
+            ),
+            departure(
+                "16-1-K",
+                datetime!(2023-06-01 7:20),
+                datetime!(2023-06-01 7:20),
+            ),
+            departure(
+                "16-0-D",
+                datetime!(2023-06-01 7:30),
+                datetime!(2023-06-01 7:31),
+            ),
+            departure(
+                "11-1-G",
+                datetime!(2023-06-01 7:05),
+                datetime!(2023-06-01 7:05),
+            ),
+            departure(
+                "11-1-G",
+                datetime!(2023-06-01 7:35),
+                datetime!(2023-06-01 7:35),
+            ),
+        ]
+    }
+
+    #[test]
+    fn by_route() {
+        let analyses = headways::analyze(&departures(), Grouping::Route, &Options::default());
+        assert_eq!(analyses.len(), 2);
+        assert_eq!(analyses[0].key, "11");
+        assert_eq!(analyses[0].estimated_headways, vec![Duration::minutes(30)]);
+        assert!(analyses[0].bunching.is_empty());
+        assert!(analyses[0].gaps.is_empty());
+
+        let route = &analyses[1];
+        assert_eq!(route.key, "16");
+        assert_eq!(route.scheduled_headways, vec![Duration::minutes(10); 3]);
+        assert_eq!(
+            route.estimated_headways,
+            vec![
+                Duration::minutes(19),
+                Duration::minutes(1),
+                Duration::minutes(11)
+            ]
+        );
+        assert_eq!(route.mean_scheduled_headway(), Some(Duration::minutes(10)));
+        assert_eq!(route.buses_per_hour(), Some(6.0));
+        assert_eq!(route.max_estimated_headway(), Some(Duration::minutes(19)));
+
+        assert_eq!(route.bunching.len(), 1);
+        assert_eq!(
+            route.bunching[0].first.scheduled,
+            datetime!(2023-06-01 7:10)
+        );
+        assert_eq!(
+            route.bunching[0].second.scheduled,
+            datetime!(2023-06-01 7:20)
+        );
+        assert_eq!(route.gaps.len(), 0);
+
+        let strict = Options {
+            gap_factor: 1.5,
+            ..Options::default()
+        };
+        let analyses = headways::analyze(&departures(), Grouping::Route, &strict);
+        assert_eq!(analyses[1].gaps.len(), 1);
+        assert_eq!(analyses[1].gaps[0].headway, Duration::minutes(19));
+    }
+
+    #[test]
+    fn full_day() {
+        // Every 10 minutes in the peaks, every 30 minutes midday and hourly in the evening
+        let mut times = Vec::new();
+        let mut time = datetime!(2023-06-01 6:00);
+        while time < datetime!(2023-06-01 23:00) {
+            times.push(time);
+            time += match time.hour() {
+                6..=8 | 15..=17 => Duration::minutes(10),
+                9..=14 => Duration::minutes(30),
+                _ => Duration::hours(1),
+            };
+        }
+        let on_time: Vec<Departure> = times
+            .iter()
+            .map(|time| departure("16-1-K", *time, *time))
+            .collect();
+        let analyses = headways::analyze(&on_time, Grouping::Route, &Options::default());
+        assert_eq!(analyses[0].departures.len(), on_time.len());
+        assert!(analyses[0].gaps.is_empty());
+        assert!(analyses[0].bunching.is_empty());
+
+        // The noon bus is 50 minutes late, so the 12:30 bus comes an hour after the 11:30 one
+        let late: Vec<Departure> = times
+            .iter()
+            .map(|time| {
+                if *time == datetime!(2023-06-01 12:00) {
+                    departure("16-1-K", *time, datetime!(2023-06-01 12:50))
+                } else {
+                    departure("16-1-K", *time, *time)
+                }
+            })
+            .collect();
+        let analyses = headways::analyze(&late, Grouping::Route, &Options::default());
+        assert_eq!(analyses[0].gaps.len(), 1);
+        let gap = &analyses[0].gaps[0];
+        assert_eq!(gap.first.scheduled, datetime!(2023-06-01 11:30));
+        assert_eq!(gap.second.scheduled, datetime!(2023-06-01 12:30));
+        assert_eq!(gap.headway, Duration::hours(1));
+    }
+
+    #[test]
+    fn by_variant() {
+        let analyses = headways::analyze(&departures(), Grouping::Variant, &Options::default());
+        let keys: Vec<&str> = analyses.iter().map(|a| a.key.as_str()).collect();
+        assert_eq!(keys, vec!["11-1-G", "16-0-D", "16-1-K"]);
+        // A single departure has no headway
+        assert!(analyses[1].estimated_headways.is_empty());
+        assert_eq!(analyses[1].buses_per_hour(), None);
+        assert_eq!(analyses[2].bunching.len(), 1);
+    }
+}
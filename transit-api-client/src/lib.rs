@@ -36,12 +36,21 @@
 #[cfg(test)]
 use tokio_test as _;
 
-use time::{macros::datetime, PrimitiveDateTime};
-
+pub mod advisories;
+pub mod analytics;
 pub mod endpoints;
+pub mod export;
 pub mod filters;
+pub mod gtfs;
+pub mod headways;
 pub mod prelude;
+pub mod search;
+pub mod spatial;
+pub mod store;
 pub mod structs;
+pub mod timetable;
+pub mod timezone;
+pub mod tracking;
 
 /// The client that houses all the methods for the API and handles connections to the API.
 #[derive(Debug)]
@@ -101,10 +110,6 @@ impl TransitClient {
     }
 }
 
-/// A [PrimitiveDateTime] that represents the UNIX Epoch (January 1st, 1970 at 12:00 am).
-/// This value can then be used as a default value for PrimitiveDateTimes
-pub(crate) const UNIX_EPOCH: PrimitiveDateTime = datetime!(1970-01-01 0:00);
-
 /// Creates a Transit Client from environment variables
 #[cfg(test)]
 pub fn testing_client() -> TransitClient {
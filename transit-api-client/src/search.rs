@@ -0,0 +1,504 @@
+// TransitTrail - Navigate Winnipeg Transit with a different style
+// Copyright (C) - 2023 Foxx Azalea Pinkerton, Max Fehlinger
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! A fuzzy search over cached stops, streets and monuments, that works without the API.
+//!
+//! Every word of the query has to match a word of a result. Words match exactly, as a prefix
+//! (so results show up while typing), or with a few typos. Abbreviated street types match the
+//! full ones, e.g. "Hwy" matches "Highway". Results with fewer typos come first, ties are broken
+//! by the distance to a point, if one is given.
+//!
+//! # Example
+//!
+//! ```
+//! use transit_api_client::prelude::*;
+//! use transit_api_client::search::{SearchIndex, SearchItem};
+//!
+//! let mut index = SearchIndex::new();
+//! index.insert(SearchItem::Street(Street {
+//!     key: 2715,
+//!     name: "Osborne Street".to_string(),
+//!     street_type: Some("Street".to_string()),
+//!     leg: None,
+//! }));
+//!
+//! let results = index.search("osbrone st", None, 10);
+//! assert_eq!(results[0].item.name(), "Osborne Street");
+//! ```
+//!
+
+use serde::{Deserialize, Serialize};
+
+use crate::filters::{street_type_of, STREET_TYPES};
+use crate::structs::{
+    common::{GeoLocation, Monument, Street},
+    stops::Stop,
+};
+
+/// Something that can be found with a [SearchIndex]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum SearchItem {
+    /// A bus stop, found by its name or number
+    #[serde(rename = "stop")]
+    Stop(Stop),
+
+    /// A street, found by its name, type and leg
+    #[serde(rename = "street")]
+    Street(Street),
+
+    /// A point of interest, found by its name and categories
+    #[serde(rename = "monument")]
+    Monument(Monument),
+}
+
+impl SearchItem {
+    /// The name of the item, as it should be displayed
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Stop(stop) => &stop.name,
+            Self::Street(street) => &street.name,
+            Self::Monument(monument) => &monument.name,
+        }
+    }
+
+    /// Where the item is located. Streets do not have a single location.
+    pub fn location(&self) -> Option<&GeoLocation> {
+        match self {
+            Self::Stop(stop) => Some(&stop.centre),
+            Self::Street(_) => None,
+            Self::Monument(monument) => Some(&monument.address.centre),
+        }
+    }
+
+    /// All the texts the item can be found by
+    fn texts(&self) -> Vec<String> {
+        match self {
+            Self::Stop(stop) => vec![stop.name.clone(), stop.number.to_string()],
+            Self::Street(street) => {
+                let mut texts = vec![street.name.clone()];
+                texts.extend(street.street_type.clone());
+                texts.extend(street.leg.as_ref().map(|leg| leg.to_string()));
+                texts
+            }
+            Self::Monument(monument) => {
+                let mut texts = vec![monument.name.clone()];
+                texts.extend(monument.categories.iter().cloned());
+                texts
+            }
+        }
+    }
+}
+
+impl From<Stop> for SearchItem {
+    fn from(value: Stop) -> Self {
+        Self::Stop(value)
+    }
+}
+
+impl From<Street> for SearchItem {
+    fn from(value: Street) -> Self {
+        Self::Street(value)
+    }
+}
+
+impl From<Monument> for SearchItem {
+    fn from(value: Monument) -> Self {
+        Self::Monument(value)
+    }
+}
+
+/// A result of [SearchIndex::search]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult<'a> {
+    /// The item that was found
+    pub item: &'a SearchItem,
+
+    /// How well the item matches the query. 0 is a perfect match, every typo adds 2 and every
+    /// word that was only matched by its beginning adds 1.
+    pub score: u32,
+
+    /// The distance in metres to the point the search was near, if both have a location.
+    pub distance: Option<f64>,
+}
+
+/// An index to search stops, streets and monuments by name.
+#[derive(Clone, Debug, Default)]
+pub struct SearchIndex {
+    items: Vec<SearchItem>,
+
+    /// The normalized words of each item
+    words: Vec<Vec<String>>,
+}
+
+impl SearchIndex {
+    /// Creates an empty index
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an item to the index
+    ///
+    /// # Arguments
+    ///
+    /// * `item`: The stop, street or monument to add
+    ///
+    /// returns: ()
+    pub fn insert(&mut self, item: impl Into<SearchItem>) {
+        let item = item.into();
+        let mut words: Vec<String> = item
+            .texts()
+            .into_iter()
+            .flat_map(|text| words(&text))
+            .flat_map(expand)
+            .collect();
+        words.sort();
+        words.dedup();
+        self.words.push(words);
+        self.items.push(item);
+    }
+
+    /// The number of items in the index
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Whether the index contains no items
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Searches the index.
+    ///
+    /// # Arguments
+    ///
+    /// * `query`: What the user typed. Case, accents and punctuation are ignored.
+    /// * `near`: Results closer to this point are ranked higher, if they match equally well
+    /// * `limit`: The maximum number of results
+    ///
+    /// returns: Vec<SearchResult>
+    pub fn search(
+        &self,
+        query: &str,
+        near: Option<&GeoLocation>,
+        limit: usize,
+    ) -> Vec<SearchResult<'_>> {
+        let query: Vec<Vec<String>> = words(query).into_iter().map(expand).collect();
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        let mut results: Vec<SearchResult> = self
+            .items
+            .iter()
+            .zip(&self.words)
+            .filter_map(|(item, words)| {
+                let score = query.iter().try_fold(0, |score, query_words| {
+                    query_words
+                        .iter()
+                        .flat_map(|query_word| {
+                            words.iter().filter_map(move |word| cost(query_word, word))
+                        })
+                        .min()
+                        .map(|cost| score + cost)
+                })?;
+                Some(SearchResult {
+                    item,
+                    score,
+                    distance: near
+                        .zip(item.location())
+                        .map(|(near, location)| near.distance(location)),
+                })
+            })
+            .collect();
+
+        results.sort_by(|a, b| {
+            a.score
+                .cmp(&b.score)
+                .then_with(|| match (a.distance, b.distance) {
+                    (Some(a), Some(b)) => a.total_cmp(&b),
+                    (Some(_), None) => std::cmp::Ordering::Less,
+                    (None, Some(_)) => std::cmp::Ordering::Greater,
+                    (None, None) => std::cmp::Ordering::Equal,
+                })
+                .then_with(|| a.item.name().cmp(b.item.name()))
+        });
+        results.truncate(limit);
+        results
+    }
+}
+
+impl<I: Into<SearchItem>> FromIterator<I> for SearchIndex {
+    fn from_iter<T: IntoIterator<Item = I>>(iter: T) -> Self {
+        let mut index = Self::new();
+        index.extend(iter);
+        index
+    }
+}
+
+impl<I: Into<SearchItem>> Extend<I> for SearchIndex {
+    fn extend<T: IntoIterator<Item = I>>(&mut self, iter: T) {
+        for item in iter {
+            self.insert(item);
+        }
+    }
+}
+
+/// Splits a text into lowercase words without accents
+pub(crate) fn words(text: &str) -> Vec<String> {
+    text.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'à' | 'á' | 'â' | 'ä' => 'a',
+            'ç' => 'c',
+            'è' | 'é' | 'ê' | 'ë' => 'e',
+            'ì' | 'í' | 'î' | 'ï' => 'i',
+            'ò' | 'ó' | 'ô' | 'ö' => 'o',
+            'ù' | 'ú' | 'û' | 'ü' => 'u',
+            c if c.is_alphanumeric() => c,
+            _ => ' ',
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+/// A word and, if it is an abbreviated street type, the full street type, e.g. "hwy" and
+/// "highway". The word itself is kept, as it may also be the beginning of another word.
+fn expand(word: String) -> Vec<String> {
+    let street_type = street_type_of(&STREET_TYPES, &word).map(str::to_lowercase);
+    let mut words = vec![word];
+    words.extend(street_type.filter(|street_type| *street_type != words[0]));
+    words
+}
+
+/// How many typos are allowed in a word of this length
+fn max_typos(length: usize) -> usize {
+    match length {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// The cost of matching a query word to a word of an item, if they match at all
+fn cost(query: &str, word: &str) -> Option<u32> {
+    if query == word {
+        return Some(0);
+    }
+    if word.starts_with(query) {
+        return Some(1);
+    }
+
+    let query: Vec<char> = query.chars().collect();
+    let word: Vec<char> = word.chars().collect();
+    let allowed = max_typos(query.len());
+    if allowed == 0 {
+        return None;
+    }
+    let typos = edit_distance(&query, &word);
+    if typos <= allowed {
+        return Some(2 * typos as u32);
+    }
+    // The user might not have finished typing the word yet
+    if word.len() > query.len() {
+        let typos = edit_distance(&query, &word[..query.len()]);
+        if typos <= allowed {
+            return Some(2 * typos as u32 + 1);
+        }
+    }
+    None
+}
+
+/// The optimal string alignment distance: insertions, deletions, substitutions and swaps of
+/// neighbouring characters count as one edit each.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + substitution);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::*;
+    use crate::search::{SearchIndex, SearchItem};
+
+    fn street(key: u32, name: &str, street_type: &str, leg: Option<StreetLeg>) -> Street {
+        Street {
+            key,
+            name: name.to_string(),
+            street_type: Some(street_type.to_string()),
+            leg,
+        }
+    }
+
+    fn stop(key: u32, name: &str, latitude: f64, longitude: f64) -> Stop {
+        Stop {
+            key,
+            name: name.to_string(),
+            number: key,
+            distances: None,
+            direction: Direction::Northbound,
+            side: Side::Nearside,
+            street: street(2715, "Osborne Street", "Street", None),
+            cross_street: street(1486, "Glasgow Avenue", "Avenue", None),
+            centre: GeoLocation::new(latitude, longitude),
+            internal_name: None,
+            sequence_on_street: None,
+            icon_style: None,
+        }
+    }
+
+    fn index() -> SearchIndex {
+        let mut index = SearchIndex::new();
+        index.extend([
+            stop(10064, "Northbound Osborne at Glasgow", 49.8691, -97.1375),
+            stop(10625, "Northbound Osborne at Stradbrook", 49.8802, -97.1449),
+        ]);
+        index.extend([
+            street(2715, "Osborne Street", "Street", None),
+            street(2265, "Main Street", "Street", None),
+            street(3212, "Provencher Boulevard", "Boulevard", None),
+            street(837, "Pembina Highway", "Highway", None),
+            street(1023, "Henderson Highway", "Highway", Some(StreetLeg::North)),
+            street(4187, "Rue Des Meurons", "Street", None),
+            street(4188, "Boulevard Provencher", "Boulevard", None),
+        ]);
+        index.insert(SearchItem::Monument(Monument {
+            key: 1,
+            name: "Université de Saint-Boniface".to_string(),
+            categories: vec!["Education".to_string()],
+            address: Address {
+                key: 1,
+                street: street(4187, "Rue Des Meurons", "Street", None),
+                street_number: 200,
+                centre: GeoLocation::new(49.8882, -97.1205),
+            },
+        }));
+        index
+    }
+
+    fn names<'a>(results: &[crate::search::SearchResult<'a>]) -> Vec<&'a str> {
+        results.iter().map(|result| result.item.name()).collect()
+    }
+
+    #[test]
+    fn exact_and_prefix() {
+        let index = index();
+        assert_eq!(index.len(), 10);
+
+        let results = index.search("Main Street", None, 10);
+        assert_eq!(names(&results), vec!["Main Street"]);
+        assert_eq!(results[0].score, 0);
+
+        // While typing
+        let results = index.search("pemb", None, 10);
+        assert_eq!(names(&results), vec!["Pembina Highway"]);
+        assert_eq!(results[0].score, 1);
+
+        // Stop numbers
+        let results = index.search("10625", None, 10);
+        assert_eq!(names(&results), vec!["Northbound Osborne at Stradbrook"]);
+
+        // Legs and categories
+        assert_eq!(
+            names(&index.search("henderson north", None, 10)),
+            vec!["Henderson Highway"]
+        );
+        assert_eq!(
+            names(&index.search("education", None, 10)),
+            vec!["Université de Saint-Boniface"]
+        );
+        assert!(index.search("", None, 10).is_empty());
+        assert!(index.search("nothing like this", None, 10).is_empty());
+    }
+
+    #[test]
+    fn typos() {
+        let index = index();
+        assert_eq!(
+            names(&index.search("provencer", None, 10)),
+            vec!["Boulevard Provencher", "Provencher Boulevard"]
+        );
+        assert_eq!(
+            names(&index.search("Pemibna Hwy", None, 10)),
+            vec!["Pembina Highway"]
+        );
+        assert_eq!(
+            names(&index.search("provencher blvd", None, 10)),
+            vec!["Boulevard Provencher", "Provencher Boulevard"]
+        );
+        assert_eq!(
+            names(&index.search("henderson hwy n", None, 10)),
+            vec!["Henderson Highway"]
+        );
+        assert_eq!(
+            names(&index.search("Pemibna High", None, 10)),
+            vec!["Pembina Highway"]
+        );
+        // Accents are ignored
+        assert_eq!(
+            names(&index.search("universite boni", None, 10)),
+            vec!["Université de Saint-Boniface"]
+        );
+        assert_eq!(names(&index.search("mian", None, 10)), vec!["Main Street"]);
+        // Short words must be typed correctly
+        assert!(index.search("man", None, 10).is_empty());
+    }
+
+    #[test]
+    fn proximity() {
+        let index = index();
+        let near_glasgow = GeoLocation::new(49.8680, -97.1370);
+        let near_stradbrook = GeoLocation::new(49.8810, -97.1450);
+
+        let results = index.search("northbound osborne", Some(&near_glasgow), 10);
+        assert_eq!(
+            names(&results),
+            vec![
+                "Northbound Osborne at Glasgow",
+                "Northbound Osborne at Stradbrook"
+            ]
+        );
+        assert!(results[0].distance.unwrap() < 200.0);
+
+        let results = index.search("northbound osborne", Some(&near_stradbrook), 1);
+        assert_eq!(names(&results), vec!["Northbound Osborne at Stradbrook"]);
+
+        // Streets have no location, so they come after stops that match equally well
+        let results = index.search("osborne", Some(&near_glasgow), 10);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[2].item.name(), "Osborne Street");
+        assert_eq!(results[2].distance, None);
+    }
+}
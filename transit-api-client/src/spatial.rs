@@ -0,0 +1,496 @@
+// TransitTrail - Navigate Winnipeg Transit with a different style
+// Copyright (C) - 2023 Foxx Azalea Pinkerton, Max Fehlinger
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! An in-memory spatial index, to find stops near a point without asking the API.
+//!
+//! The index is a k-d tree over the stops, built once from e.g.
+//! [get_all_stops](crate::TransitClient::get_all_stops). Queries only take microseconds, so they
+//! can be run on every movement of a map.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use transit_api_client::prelude::*;
+//! use transit_api_client::spatial::SpatialIndex;
+//!
+//! # tokio_test::block_on(async {
+//! let client = TransitClient::new("<YOUR_API_TOKEN>".to_string());
+//! let index = SpatialIndex::new(client.get_all_stops().await.unwrap());
+//! for (stop, distance) in index.within_radius(&GeoLocation::new(49.895, -97.138), 250.0) {
+//!     println!("Stop {} is {:.0}m away", stop.id, distance);
+//! }
+//! # });
+//! ```
+//!
+
+use crate::{
+    gtfs,
+    structs::{
+        common::{GeoLocation, EARTH_RADIUS},
+        stops::{PartialStop, Stop},
+        trip_planner::Bounds,
+    },
+};
+
+/// Something that is located at a single point on the map, so it can be put into a
+/// [SpatialIndex].
+pub trait Located {
+    /// Where the item is located
+    fn location(&self) -> &GeoLocation;
+}
+
+impl Located for GeoLocation {
+    fn location(&self) -> &GeoLocation {
+        self
+    }
+}
+
+impl Located for PartialStop {
+    fn location(&self) -> &GeoLocation {
+        &self.position
+    }
+}
+
+impl Located for Stop {
+    fn location(&self) -> &GeoLocation {
+        &self.centre
+    }
+}
+
+impl Located for gtfs::Stop {
+    fn location(&self) -> &GeoLocation {
+        &self.centre
+    }
+}
+
+impl<T: Located> Located for &T {
+    fn location(&self) -> &GeoLocation {
+        (*self).location()
+    }
+}
+
+/// A k-d tree for nearest neighbour, radius and bounding box queries.
+///
+/// The points are projected onto a plane around their mean latitude, which is accurate enough
+/// at the scale of a city. The plane is only used to skip parts of the tree: nearest items are
+/// ranked by, and distances that are returned are always, great-circle distances in metres.
+#[derive(Clone, Debug)]
+pub struct SpatialIndex<T> {
+    /// The items in tree order: the median of each range is its node, with the lower half on the
+    /// left and the upper half on the right.
+    entries: Vec<Entry<T>>,
+
+    /// Metres per degree of longitude at the mean latitude
+    scale: f64,
+
+    /// The cosine of the latitude furthest from the equator, where a degree of longitude is the
+    /// shortest
+    min_cos: f64,
+}
+
+/// A point to search the nearest items for
+struct Target<'a> {
+    point: &'a GeoLocation,
+    /// The projected position of the point
+    projected: [f64; 2],
+    /// The square root of the smallest product of the cosines of the latitudes of the point and
+    /// an item
+    cos_factor: f64,
+}
+
+#[derive(Clone, Debug)]
+struct Entry<T> {
+    item: T,
+    /// Projected position in metres, (x: longitude, y: latitude)
+    point: [f64; 2],
+}
+
+/// Metres per degree of latitude
+const METRES_PER_DEGREE: f64 = EARTH_RADIUS * std::f64::consts::PI / 180.0;
+
+impl<T: Located> SpatialIndex<T> {
+    /// Builds the index.
+    ///
+    /// # Arguments
+    ///
+    /// * `items`: The items to index, e.g. [PartialStop]s or [Stop]s
+    ///
+    /// returns: SpatialIndex<T>
+    pub fn new(items: Vec<T>) -> Self {
+        let mean_latitude = if items.is_empty() {
+            0.0
+        } else {
+            items
+                .iter()
+                .map(|item| item.location().latitude)
+                .sum::<f64>()
+                / items.len() as f64
+        };
+        let scale = METRES_PER_DEGREE * mean_latitude.to_radians().cos();
+        let min_cos = items
+            .iter()
+            .map(|item| item.location().latitude.to_radians().cos())
+            .fold(1.0, f64::min);
+
+        let mut entries: Vec<Entry<T>> = items
+            .into_iter()
+            .map(|item| {
+                let location = item.location();
+                let point = [
+                    location.longitude * scale,
+                    location.latitude * METRES_PER_DEGREE,
+                ];
+                Entry { item, point }
+            })
+            .collect();
+        build(&mut entries, 0);
+
+        Self {
+            entries,
+            scale,
+            min_cos,
+        }
+    }
+
+    /// The number of items in the index
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the index contains no items
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// All items of the index, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.entries.iter().map(|entry| &entry.item)
+    }
+
+    /// The `k` items closest to a point, with their distance in metres, closest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `point`: Where to search from
+    /// * `k`: How many items to return at most
+    ///
+    /// returns: Vec<(&T, f64)>
+    pub fn nearest(&self, point: &GeoLocation, k: usize) -> Vec<(&T, f64)> {
+        if k == 0 {
+            return Vec::new();
+        }
+        // Sorted by the great-circle distance
+        let mut best: Vec<(usize, f64)> = Vec::with_capacity(k + 1);
+        let target = Target {
+            point,
+            projected: self.project(point),
+            cos_factor: (self.min_cos * point.latitude.to_radians().cos()).sqrt(),
+        };
+        self.search_nearest(0, self.entries.len(), 0, &target, k, &mut best);
+        best.into_iter()
+            .map(|(index, distance)| (&self.entries[index].item, distance))
+            .collect()
+    }
+
+    /// All items within a radius around a point, with their distance in metres, closest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `point`: The centre of the circle
+    /// * `radius`: The radius in metres
+    ///
+    /// returns: Vec<(&T, f64)>
+    pub fn within_radius(&self, point: &GeoLocation, radius: f64) -> Vec<(&T, f64)> {
+        let bounds = point.bounding_box(radius);
+        let mut found = Vec::new();
+        self.search_bounds(
+            0,
+            self.entries.len(),
+            0,
+            &self.project(&bounds.minimum),
+            &self.project(&bounds.maximum),
+            &mut found,
+        );
+        let mut found = self.with_distances(point, found.into_iter());
+        found.retain(|(_, distance)| *distance <= radius);
+        found
+    }
+
+    /// All items within a bounding box, in no particular order.
+    ///
+    /// # Arguments
+    ///
+    /// * `bounds`: The bounding box, including its edges
+    ///
+    /// returns: Vec<&T>
+    pub fn within_bounds(&self, bounds: &Bounds) -> Vec<&T> {
+        let mut found = Vec::new();
+        self.search_bounds(
+            0,
+            self.entries.len(),
+            0,
+            &self.project(&bounds.minimum),
+            &self.project(&bounds.maximum),
+            &mut found,
+        );
+        found
+            .into_iter()
+            .map(|index| &self.entries[index].item)
+            .collect()
+    }
+
+    fn project(&self, point: &GeoLocation) -> [f64; 2] {
+        [
+            point.longitude * self.scale,
+            point.latitude * METRES_PER_DEGREE,
+        ]
+    }
+
+    /// Replaces the projected distances by great-circle distances
+    fn with_distances(
+        &self,
+        point: &GeoLocation,
+        indices: impl Iterator<Item = usize>,
+    ) -> Vec<(&T, f64)> {
+        let mut found: Vec<(&T, f64)> = indices
+            .map(|index| {
+                let item = &self.entries[index].item;
+                (item, point.distance(item.location()))
+            })
+            .collect();
+        found.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        found
+    }
+
+    fn search_nearest(
+        &self,
+        start: usize,
+        end: usize,
+        axis: usize,
+        target: &Target,
+        k: usize,
+        best: &mut Vec<(usize, f64)>,
+    ) {
+        if start >= end {
+            return;
+        }
+        let mid = (start + end) / 2;
+        let entry = &self.entries[mid];
+        let point = &entry.point;
+        let distance = target.point.distance(entry.item.location());
+        if best.len() < k || distance < best[best.len() - 1].1 {
+            let position = best.partition_point(|(_, other)| *other <= distance);
+            best.insert(position, (mid, distance));
+            best.truncate(k);
+        }
+
+        let offset = target.projected[axis] - point[axis];
+        let (near, far) = if offset < 0.0 {
+            ((start, mid), (mid + 1, end))
+        } else {
+            ((mid + 1, end), (start, mid))
+        };
+        self.search_nearest(near.0, near.1, 1 - axis, target, k, best);
+        // Every item on the far side is at least this far away. Along the longitude axis, they
+        // differ by at least `longitude`, and by the haversine formula
+        // `hav(distance / R) >= cos(lat1) * cos(lat2) * hav(longitude)`.
+        let bound = match axis {
+            0 => {
+                let longitude = (offset.abs() / self.scale).to_radians();
+                let sin = target.cos_factor * (longitude / 2.0).sin();
+                2.0 * EARTH_RADIUS * sin.min(1.0).asin()
+            }
+            _ => offset.abs(),
+        };
+        if best.len() < k || bound <= best[best.len() - 1].1 {
+            self.search_nearest(far.0, far.1, 1 - axis, target, k, best);
+        }
+    }
+
+    fn search_bounds(
+        &self,
+        start: usize,
+        end: usize,
+        axis: usize,
+        minimum: &[f64; 2],
+        maximum: &[f64; 2],
+        found: &mut Vec<usize>,
+    ) {
+        if start >= end {
+            return;
+        }
+        let mid = (start + end) / 2;
+        let point = &self.entries[mid].point;
+        if (minimum[0]..=maximum[0]).contains(&point[0])
+            && (minimum[1]..=maximum[1]).contains(&point[1])
+        {
+            found.push(mid);
+        }
+        if minimum[axis] <= point[axis] {
+            self.search_bounds(start, mid, 1 - axis, minimum, maximum, found);
+        }
+        if point[axis] <= maximum[axis] {
+            self.search_bounds(mid + 1, end, 1 - axis, minimum, maximum, found);
+        }
+    }
+}
+
+impl<T: Located> FromIterator<T> for SpatialIndex<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+/// Puts the entries into tree order
+fn build<T>(entries: &mut [Entry<T>], axis: usize) {
+    if entries.len() <= 1 {
+        return;
+    }
+    let mid = entries.len() / 2;
+    entries.select_nth_unstable_by(mid, |a, b| a.point[axis].total_cmp(&b.point[axis]));
+    let (left, right) = entries.split_at_mut(mid);
+    build(left, 1 - axis);
+    build(&mut right[1..], 1 - axis);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::*;
+    use crate::spatial::SpatialIndex;
+
+    /// A grid of stops around downtown Winnipeg, about 110m apart
+    fn stops() -> Vec<PartialStop> {
+        let mut stops = Vec::new();
+        for i in 0..20 {
+            for j in 0..20 {
+                stops.push(PartialStop {
+                    id: 10000 + i * 100 + j,
+                    position: GeoLocation::new(
+                        49.88 + i as f64 * 0.001,
+                        -97.15 + j as f64 * 0.0015,
+                    ),
+                    icon_style: PartialStopIconStyle::Blue,
+                });
+            }
+        }
+        stops
+    }
+
+    /// The results of the index have to be the same as going through every stop
+    fn brute_force(stops: &[PartialStop], point: &GeoLocation) -> Vec<(u32, f64)> {
+        let mut all: Vec<(u32, f64)> = stops
+            .iter()
+            .map(|stop| (stop.id, point.distance(&stop.position)))
+            .collect();
+        all.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        all
+    }
+
+    #[test]
+    fn nearest() {
+        let stops = stops();
+        let index = SpatialIndex::new(stops.clone());
+        assert_eq!(index.len(), 400);
+
+        for point in [
+            GeoLocation::new(49.8951, -97.1384),
+            GeoLocation::new(49.8875, -97.1313),
+            GeoLocation::new(49.95, -97.2),
+        ] {
+            let expected = brute_force(&stops, &point);
+            let found = index.nearest(&point, 5);
+            assert_eq!(found.len(), 5);
+            for ((stop, distance), (id, expected)) in found.iter().zip(expected) {
+                assert!((distance - expected).abs() < 1e-6, "{} != {}", stop.id, id);
+            }
+        }
+
+        assert!(index
+            .nearest(&GeoLocation::new(49.89, -97.14), 0)
+            .is_empty());
+        assert_eq!(
+            index.nearest(&GeoLocation::new(49.89, -97.14), 1000).len(),
+            400
+        );
+    }
+
+    #[test]
+    fn nearest_far_north() {
+        // The great circle to stop 3 bends towards the pole, so it is closer than the difference
+        // in longitude times the length of a degree of longitude at 80° suggests
+        let stop = |id, latitude, longitude| PartialStop {
+            id,
+            position: GeoLocation::new(latitude, longitude),
+            icon_style: PartialStopIconStyle::Blue,
+        };
+        let stops = vec![
+            stop(1, 80.0, -40.6),
+            stop(2, 60.0, 40.0),
+            stop(3, 80.0, 40.5),
+        ];
+        let index = SpatialIndex::new(stops.clone());
+        let point = GeoLocation::new(80.0, 0.0);
+        assert_eq!(brute_force(&stops, &point)[0].0, 3);
+        assert_eq!(index.nearest(&point, 1)[0].0.id, 3);
+    }
+
+    #[test]
+    fn within_radius() {
+        let stops = stops();
+        let index = SpatialIndex::new(stops.clone());
+        let point = GeoLocation::new(49.8951, -97.1384);
+
+        let expected: Vec<u32> = brute_force(&stops, &point)
+            .into_iter()
+            .filter(|(_, distance)| *distance <= 250.0)
+            .map(|(id, _)| id)
+            .collect();
+        let found: Vec<u32> = index
+            .within_radius(&point, 250.0)
+            .into_iter()
+            .map(|(stop, _)| stop.id)
+            .collect();
+        assert!(!found.is_empty());
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn within_bounds() {
+        let stops = stops();
+        let index: SpatialIndex<&PartialStop> = stops.iter().collect();
+        let bounds = trip::Bounds {
+            maximum: GeoLocation::new(49.8855, -97.142),
+            minimum: GeoLocation::new(49.8825, -97.146),
+        };
+
+        let mut found: Vec<u32> = index
+            .within_bounds(&bounds)
+            .into_iter()
+            .map(|stop| stop.id)
+            .collect();
+        found.sort();
+        let expected: Vec<u32> = stops
+            .iter()
+            .filter(|stop| bounds.contains(&stop.position))
+            .map(|stop| stop.id)
+            .collect();
+        assert_eq!(found, expected);
+        assert_eq!(found.len(), 9);
+
+        let empty: SpatialIndex<GeoLocation> = SpatialIndex::new(vec![]);
+        assert!(empty.within_bounds(&bounds).is_empty());
+        assert!(empty.nearest(&bounds.minimum, 3).is_empty());
+    }
+}
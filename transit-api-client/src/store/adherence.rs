@@ -0,0 +1,470 @@
+// TransitTrail - Navigate Winnipeg Transit with a different style
+// Copyright (C) - 2023 Foxx Azalea Pinkerton, Max Fehlinger
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Records how well buses keep to their schedule.
+//!
+//! The [Recorder] asks for the [stop_schedule](crate::TransitClient::stop_schedule) of some stops
+//! in an interval, and saves every scheduled stop as an [Observation] in the [Store]. The last
+//! observation of a scheduled stop before the bus departed holds the best estimate of when it
+//! actually departed, see [Store::actual_departures].
+//!
+
+use rusqlite::{params, types::Type, Row};
+use time::{
+    format_description::FormatItem, macros::format_description, OffsetDateTime, PrimitiveDateTime,
+};
+
+use crate::{
+    structs::{stops::Schedule, Error, Usage},
+    timezone, TransitClient,
+};
+
+use super::Store;
+
+/// What the API said about a bus passing a stop at one point in time
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Observation {
+    /// The key of the [ScheduledStop](crate::structs::stops::ScheduledStop)
+    pub scheduled_stop: String,
+
+    /// The key of the stop
+    pub stop: u32,
+
+    /// The key of the route
+    pub route: String,
+
+    /// The key of the variant of the route
+    pub variant: String,
+
+    /// The key of the bus, if it was known
+    pub bus: Option<u32>,
+
+    /// If the scheduled stop was cancelled
+    pub cancelled: bool,
+
+    /// When the bus was scheduled to arrive
+    pub scheduled_arrival: Option<PrimitiveDateTime>,
+
+    /// When the bus was estimated to arrive
+    pub estimated_arrival: Option<PrimitiveDateTime>,
+
+    /// When the bus was scheduled to depart
+    pub scheduled_departure: Option<PrimitiveDateTime>,
+
+    /// When the bus was estimated to depart
+    pub estimated_departure: Option<PrimitiveDateTime>,
+
+    /// When the observation was made, in the time zone of the schedule
+    pub observed_at: PrimitiveDateTime,
+}
+
+impl Observation {
+    /// How much later than scheduled the bus was estimated to depart. Negative, if it was
+    /// estimated to depart early.
+    pub fn departure_delay(&self) -> Option<time::Duration> {
+        Some(self.estimated_departure? - self.scheduled_departure?)
+    }
+
+    /// Creates the observations of all scheduled stops in a schedule
+    ///
+    /// # Arguments
+    ///
+    /// * `schedule`: The schedule of a stop
+    /// * `observed_at`: When the schedule was fetched, in the time zone of the schedule
+    ///
+    /// returns: Vec<Observation>
+    pub fn from_schedule(schedule: &Schedule, observed_at: PrimitiveDateTime) -> Vec<Self> {
+        schedule
+            .route_schedules
+            .iter()
+            .flat_map(|route_schedule| {
+                route_schedule
+                    .scheduled_stops
+                    .iter()
+                    .map(move |scheduled_stop| {
+                        let arrival = scheduled_stop.times.arrival.as_ref();
+                        let departure = scheduled_stop.times.departure.as_ref();
+                        Self {
+                            scheduled_stop: scheduled_stop.key.clone(),
+                            stop: schedule.stop.key,
+                            route: route_schedule.route.key.to_string(),
+                            variant: scheduled_stop.variant.key.to_string(),
+                            bus: scheduled_stop.bus.as_ref().map(|bus| bus.key),
+                            cancelled: scheduled_stop.cancelled,
+                            scheduled_arrival: arrival.map(|time| time.scheduled),
+                            estimated_arrival: arrival.and_then(|time| time.estimated),
+                            scheduled_departure: departure.map(|time| time.scheduled),
+                            estimated_departure: departure.and_then(|time| time.estimated),
+                            observed_at,
+                        }
+                    })
+            })
+            .collect()
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            scheduled_stop: row.get(0)?,
+            stop: row.get(1)?,
+            route: row.get(2)?,
+            variant: row.get(3)?,
+            bus: row.get(4)?,
+            cancelled: row.get(5)?,
+            scheduled_arrival: parse(row.get(6)?),
+            estimated_arrival: parse(row.get(7)?),
+            scheduled_departure: parse(row.get(8)?),
+            estimated_departure: parse(row.get(9)?),
+            observed_at: {
+                let observed_at: String = row.get(10)?;
+                PrimitiveDateTime::parse(&observed_at, FORMAT).map_err(|why| {
+                    rusqlite::Error::FromSqlConversionFailure(10, Type::Text, Box::new(why))
+                })?
+            },
+        })
+    }
+}
+
+const COLUMNS: &str = "scheduled_stop, stop, route, variant, bus, cancelled, scheduled_arrival, \
+    estimated_arrival, scheduled_departure, estimated_departure, observed_at";
+
+impl Store {
+    /// Saves observations. Observations of the same scheduled stop at the same stop and time
+    /// replace each other.
+    pub fn save_observations(&mut self, observations: &[Observation]) -> Result<(), Error> {
+        let transaction = self.connection.transaction()?;
+        {
+            let mut statement = transaction.prepare_cached(&format!(
+                "INSERT OR REPLACE INTO observations ({COLUMNS})
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)"
+            ))?;
+            for observation in observations {
+                statement.execute(params![
+                    observation.scheduled_stop,
+                    observation.stop,
+                    observation.route,
+                    observation.variant,
+                    observation.bus,
+                    observation.cancelled,
+                    observation.scheduled_arrival.map(format),
+                    observation.estimated_arrival.map(format),
+                    observation.scheduled_departure.map(format),
+                    observation.estimated_departure.map(format),
+                    format(observation.observed_at),
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// All observations of a scheduled stop, oldest first
+    pub fn observations(&self, scheduled_stop: &str) -> Result<Vec<Observation>, Error> {
+        let mut statement = self.connection.prepare_cached(&format!(
+            "SELECT {COLUMNS} FROM observations WHERE scheduled_stop = ?1
+                ORDER BY stop, observed_at"
+        ))?;
+        let observations = statement
+            .query_map(params![scheduled_stop], Observation::from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(observations)
+    }
+
+    /// The actual departures of all scheduled stops, that were scheduled to depart in a time
+    /// range, ordered by their scheduled departure.
+    ///
+    /// The actual departure is the last observation that was made before the bus was estimated
+    /// to depart. If the bus was only observed after that, the first observation is used.
+    ///
+    /// # Arguments
+    ///
+    /// * `from`: The start of the range, inclusive
+    /// * `to`: The end of the range, exclusive
+    ///
+    /// returns: Result<Vec<Observation>, Error>
+    pub fn actual_departures(
+        &self,
+        from: PrimitiveDateTime,
+        to: PrimitiveDateTime,
+    ) -> Result<Vec<Observation>, Error> {
+        let mut statement = self.connection.prepare_cached(&format!(
+            "SELECT {COLUMNS} FROM observations
+                WHERE scheduled_departure >= ?1 AND scheduled_departure < ?2
+                ORDER BY scheduled_departure, scheduled_stop, stop, observed_at"
+        ))?;
+        let observations = statement
+            .query_map(params![format(from), format(to)], Observation::from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut departures: Vec<Observation> = Vec::new();
+        let mut current: Option<Observation> = None;
+        for observation in observations {
+            let departed = |observation: &Observation| {
+                observation
+                    .estimated_departure
+                    .map_or(false, |departure| observation.observed_at > departure)
+            };
+            match current.take() {
+                Some(last)
+                    if last.scheduled_stop == observation.scheduled_stop
+                        && last.stop == observation.stop =>
+                {
+                    // Keep the newer observation, as long as the bus had not left yet
+                    current = Some(if departed(&observation) {
+                        last
+                    } else {
+                        observation
+                    });
+                }
+                last => {
+                    departures.extend(last);
+                    current = Some(observation);
+                }
+            }
+        }
+        departures.extend(current);
+        Ok(departures)
+    }
+}
+
+/// Periodically records the schedules of stops into a [Store].
+///
+/// # Example
+///
+/// ```no_run
+/// use transit_api_client::prelude::*;
+/// use transit_api_client::store::{adherence::Recorder, Store};
+///
+/// # tokio_test::block_on(async {
+/// let client = TransitClient::new("<YOUR_API_TOKEN>".to_string());
+/// let store = Store::open("adherence.sqlite").unwrap();
+/// let mut recorder = Recorder::new(&client, store, vec![10064, 10185])
+///     .interval(std::time::Duration::from_secs(30));
+/// recorder.run().await.unwrap();
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct Recorder<'a> {
+    client: &'a TransitClient,
+    store: Store,
+    stops: Vec<u32>,
+    interval: std::time::Duration,
+}
+
+impl<'a> Recorder<'a> {
+    /// Creates a recorder, that samples every minute.
+    ///
+    /// # Arguments
+    ///
+    /// * `client`: The client to fetch the schedules with
+    /// * `store`: Where the observations are saved
+    /// * `stops`: The keys of the stops to observe
+    ///
+    /// returns: Recorder
+    pub fn new(client: &'a TransitClient, store: Store, stops: Vec<u32>) -> Self {
+        Self {
+            client,
+            store,
+            stops,
+            interval: std::time::Duration::from_secs(60),
+        }
+    }
+
+    /// Sets how long to wait between samples
+    pub fn interval(mut self, interval: std::time::Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// The store the observations are saved in
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Stops recording and returns the store
+    pub fn into_store(self) -> Store {
+        self.store
+    }
+
+    /// Fetches the schedules of all stops once and saves them. Stops whose schedule could not
+    /// be fetched are logged and skipped.
+    ///
+    /// returns: Result<usize, Error> The number of saved observations
+    pub async fn sample(&mut self) -> Result<usize, Error> {
+        let mut observations = Vec::new();
+        for &stop in &self.stops {
+            match self.client.stop_schedule(stop, vec![], Usage::Normal).await {
+                Ok(schedule) => {
+                    let now = timezone::local(OffsetDateTime::now_utc());
+                    observations.extend(Observation::from_schedule(&schedule, now));
+                }
+                Err(why) => log::warn!("Could not fetch the schedule of stop {stop}: {why:?}"),
+            }
+        }
+        self.store.save_observations(&observations)?;
+        log::debug!("Recorded {} observations", observations.len());
+        Ok(observations.len())
+    }
+
+    /// Samples the stops forever, waiting for the interval between the samples. Only returns,
+    /// if the observations could not be saved.
+    pub async fn run(&mut self) -> Result<(), Error> {
+        loop {
+            self.sample().await?;
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+}
+
+/// How times are stored in the database
+const FORMAT: &[FormatItem] = format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]");
+
+fn format(time: PrimitiveDateTime) -> String {
+    time.format(FORMAT).unwrap_or_default()
+}
+
+fn parse(time: Option<String>) -> Option<PrimitiveDateTime> {
+    PrimitiveDateTime::parse(&time?, FORMAT).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::*;
+    use crate::store::{adherence::Observation, Store};
+    use crate::structs::stops::{self, test::departure};
+    use time::macros::datetime;
+    use time::{Duration, PrimitiveDateTime};
+
+    fn observation(
+        scheduled_stop: &str,
+        scheduled: PrimitiveDateTime,
+        estimated: PrimitiveDateTime,
+        observed_at: PrimitiveDateTime,
+    ) -> Observation {
+        Observation {
+            scheduled_stop: scheduled_stop.to_string(),
+            stop: 10064,
+            route: "16".to_string(),
+            variant: "16-1-K".to_string(),
+            bus: Some(511),
+            cancelled: false,
+            scheduled_arrival: None,
+            estimated_arrival: None,
+            scheduled_departure: Some(scheduled),
+            estimated_departure: Some(estimated),
+            observed_at,
+        }
+    }
+
+    #[test]
+    fn from_schedule() {
+        let schedule = stops::test::schedule(vec![ScheduledStop {
+            bus: Some(Bus {
+                key: 511,
+                bike_rack: true,
+                wifi: false,
+            }),
+            ..departure(
+                "18813225-47",
+                "16-1-K",
+                datetime!(2023-06-01 7:10),
+                datetime!(2023-06-01 7:12:30),
+            )
+        }]);
+
+        let observations = Observation::from_schedule(&schedule, datetime!(2023-06-01 7:00));
+        assert_eq!(
+            observations,
+            vec![Observation {
+                scheduled_arrival: None,
+                estimated_arrival: None,
+                ..observation(
+                    "18813225-47",
+                    datetime!(2023-06-01 7:10),
+                    datetime!(2023-06-01 7:12:30),
+                    datetime!(2023-06-01 7:00),
+                )
+            }]
+        );
+        assert_eq!(
+            observations[0].departure_delay(),
+            Some(Duration::seconds(150))
+        );
+    }
+
+    #[test]
+    fn actual_departures() -> Result<(), crate::structs::Error> {
+        let mut store = Store::open_in_memory()?;
+        let scheduled = datetime!(2023-06-01 7:10);
+        store.save_observations(&[
+            observation(
+                "1-1",
+                scheduled,
+                datetime!(2023-06-01 7:10),
+                datetime!(2023-06-01 7:00),
+            ),
+            observation(
+                "1-1",
+                scheduled,
+                datetime!(2023-06-01 7:13),
+                datetime!(2023-06-01 7:05),
+            ),
+            observation(
+                "1-1",
+                scheduled,
+                datetime!(2023-06-01 7:14),
+                datetime!(2023-06-01 7:12),
+            ),
+            // The bus had already left, so the estimate is not useful anymore
+            observation(
+                "1-1",
+                scheduled,
+                datetime!(2023-06-01 7:14),
+                datetime!(2023-06-01 7:15),
+            ),
+            // Only observed after it departed
+            observation(
+                "2-1",
+                datetime!(2023-06-01 7:40),
+                datetime!(2023-06-01 7:39),
+                datetime!(2023-06-01 7:45),
+            ),
+            observation(
+                "2-1",
+                datetime!(2023-06-01 7:40),
+                datetime!(2023-06-01 7:39),
+                datetime!(2023-06-01 7:50),
+            ),
+            // Outside the range
+            observation(
+                "3-1",
+                datetime!(2023-06-01 9:00),
+                datetime!(2023-06-01 9:00),
+                datetime!(2023-06-01 8:55),
+            ),
+        ])?;
+        assert_eq!(store.observations("1-1")?.len(), 4);
+
+        let departures =
+            store.actual_departures(datetime!(2023-06-01 7:00), datetime!(2023-06-01 8:00))?;
+        assert_eq!(departures.len(), 2);
+        assert_eq!(departures[0].scheduled_stop, "1-1");
+        assert_eq!(departures[0].observed_at, datetime!(2023-06-01 7:12));
+        assert_eq!(departures[0].departure_delay(), Some(Duration::minutes(4)));
+        assert_eq!(departures[1].scheduled_stop, "2-1");
+        assert_eq!(departures[1].observed_at, datetime!(2023-06-01 7:45));
+        assert_eq!(departures[1].departure_delay(), Some(Duration::minutes(-1)));
+        Ok(())
+    }
+}
@@ -0,0 +1,391 @@
+// TransitTrail - Navigate Winnipeg Transit with a different style
+// Copyright (C) - 2023 Foxx Azalea Pinkerton, Max Fehlinger
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Tracks which service advisories are new, updated or removed.
+//!
+//! The [Tracker] asks for the [service_advisories](crate::TransitClient::service_advisories) in
+//! an interval, and compares them to the advisories it saw before, which are kept in the
+//! [Store] with their key and when they were updated. Every difference becomes an
+//! [AdvisoryEvent].
+//!
+
+use rusqlite::params;
+
+use crate::{
+    structs::{
+        service_advisories::{Category, Priority, ServiceAdvisory},
+        Error, Usage,
+    },
+    TransitClient,
+};
+
+use super::{now, Store};
+
+/// A change of the service advisories
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AdvisoryEvent {
+    /// An advisory, that was not seen before
+    New(ServiceAdvisory),
+
+    /// An advisory, that was updated since it was last seen
+    Updated {
+        /// The advisory, as it was last seen
+        previous: ServiceAdvisory,
+
+        /// The advisory, as it is now
+        current: ServiceAdvisory,
+    },
+
+    /// An advisory, that is no longer returned by the API. Holds the advisory as it was last
+    /// seen.
+    Removed(ServiceAdvisory),
+}
+
+impl AdvisoryEvent {
+    /// The advisory the event is about, as it is now or as it was last seen, if it was removed
+    pub fn advisory(&self) -> &ServiceAdvisory {
+        match self {
+            Self::New(advisory) | Self::Removed(advisory) => advisory,
+            Self::Updated { current, .. } => current,
+        }
+    }
+
+    /// Whether the advisory has at least the priority, and affects the category.
+    ///
+    /// # Arguments
+    ///
+    /// * `priority`: The lowest priority to accept, [Priority::VeryLow] accepts all
+    /// * `category`: The category to accept, [Category::All] accepts all. Advisories for all
+    ///   categories are accepted for every category.
+    ///
+    /// returns: bool
+    pub fn matches(&self, priority: &Priority, category: &Category) -> bool {
+        let advisory = self.advisory();
+        urgency(&advisory.priority) <= urgency(priority)
+            && (*category == Category::All
+                || advisory.category == Category::All
+                || advisory.category == *category)
+    }
+}
+
+/// The number of a priority. The lower the number, the more urgent it is.
+fn urgency(priority: &Priority) -> u8 {
+    priority.clone() as u8
+}
+
+impl Store {
+    /// The advisories that were seen last, ordered by their key
+    pub fn advisories(&self) -> Result<Vec<ServiceAdvisory>, Error> {
+        self.all("SELECT data FROM advisories ORDER BY key", [])
+    }
+
+    /// Replaces the seen advisories with the current ones, and returns what changed.
+    ///
+    /// An advisory counts as updated, if it was updated at another time than before. Advisories
+    /// without an update time count as updated, if anything else changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `current`: All advisories, that are currently returned by the API
+    ///
+    /// returns: Result<Vec<AdvisoryEvent>, Error> New and updated advisories in the order of
+    /// `current`, then removed ones ordered by their key
+    pub fn sync_advisories(
+        &mut self,
+        current: &[ServiceAdvisory],
+    ) -> Result<Vec<AdvisoryEvent>, Error> {
+        let mut seen = self.advisories()?;
+        let mut events = Vec::new();
+        for advisory in current {
+            match seen.iter().position(|seen| seen.key == advisory.key) {
+                Some(index) => {
+                    let previous = seen.remove(index);
+                    if previous.updated_at != advisory.updated_at
+                        || (advisory.updated_at.is_none() && previous != *advisory)
+                    {
+                        events.push(AdvisoryEvent::Updated {
+                            previous,
+                            current: advisory.clone(),
+                        });
+                    }
+                }
+                None => events.push(AdvisoryEvent::New(advisory.clone())),
+            }
+        }
+        events.extend(seen.into_iter().map(AdvisoryEvent::Removed));
+
+        let now = now();
+        let transaction = self.connection.transaction()?;
+        {
+            transaction.execute("DELETE FROM advisories", [])?;
+            let mut statement = transaction.prepare_cached(
+                "INSERT INTO advisories (key, updated_at, data, fetched_at)
+                    VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for advisory in current {
+                statement.execute(params![
+                    advisory.key,
+                    advisory.updated_at.map(|updated_at| updated_at.to_string()),
+                    serde_json::to_string(advisory)?,
+                    now,
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(events)
+    }
+}
+
+/// Periodically checks the service advisories for changes.
+///
+/// The advisories are always fetched with [Usage::Normal], as shorter responses leave out the
+/// body of an advisory, which would make every advisory look updated. When the store did not see
+/// any advisories yet, every current advisory is new.
+///
+/// # Example
+///
+/// ```no_run
+/// use transit_api_client::prelude::*;
+/// use transit_api_client::store::{advisories::Tracker, Store};
+///
+/// # tokio_test::block_on(async {
+/// let client = TransitClient::new("<YOUR_API_TOKEN>".to_string());
+/// let store = Store::open("advisories.sqlite").unwrap();
+/// let mut tracker = Tracker::new(store)
+///     .priority(Priority::High)
+///     .category(Category::Transit)
+///     .interval(std::time::Duration::from_secs(600));
+/// tracker
+///     .run(&client, |event| println!("{}", event.advisory().title))
+///     .await
+///     .unwrap();
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct Tracker {
+    store: Store,
+    priority: Priority,
+    category: Category,
+    interval: std::time::Duration,
+}
+
+impl Tracker {
+    /// Creates a tracker, that checks every five minutes and reports all advisories.
+    ///
+    /// # Arguments
+    ///
+    /// * `store`: Where the seen advisories are saved
+    ///
+    /// returns: Tracker
+    pub fn new(store: Store) -> Self {
+        Self {
+            store,
+            priority: Priority::VeryLow,
+            category: Category::All,
+            interval: std::time::Duration::from_secs(300),
+        }
+    }
+
+    /// Only reports advisories of this priority or higher. All advisories are still saved.
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Only reports advisories affecting this category. All advisories are still saved.
+    pub fn category(mut self, category: Category) -> Self {
+        self.category = category;
+        self
+    }
+
+    /// Sets how long to wait between checks
+    pub fn interval(mut self, interval: std::time::Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// The store the seen advisories are saved in
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Stops tracking and returns the store
+    pub fn into_store(self) -> Store {
+        self.store
+    }
+
+    /// Saves the current advisories and returns the changes, that match the priority and
+    /// category of the tracker.
+    ///
+    /// # Arguments
+    ///
+    /// * `current`: All advisories, that are currently returned by the API
+    ///
+    /// returns: Result<Vec<AdvisoryEvent>, Error>
+    pub fn update(&mut self, current: &[ServiceAdvisory]) -> Result<Vec<AdvisoryEvent>, Error> {
+        let mut events = self.store.sync_advisories(current)?;
+        events.retain(|event| event.matches(&self.priority, &self.category));
+        Ok(events)
+    }
+
+    /// Fetches the advisories once and returns the changes.
+    ///
+    /// # Arguments
+    ///
+    /// * `client`: The client to fetch the advisories with
+    ///
+    /// returns: Result<Vec<AdvisoryEvent>, Error>
+    pub async fn check(&mut self, client: &TransitClient) -> Result<Vec<AdvisoryEvent>, Error> {
+        let current = client.service_advisories(vec![], Usage::Normal).await?;
+        self.update(&current)
+    }
+
+    /// Waits for the interval of the tracker, before the next check
+    pub async fn wait(&self) {
+        tokio::time::sleep(self.interval).await;
+    }
+
+    /// Checks the advisories forever, waiting for the interval between the checks. Failed
+    /// requests are logged and skipped, so they do not make every advisory look removed. Only
+    /// returns, if the advisories could not be saved.
+    ///
+    /// # Arguments
+    ///
+    /// * `client`: The client to fetch the advisories with
+    /// * `on_event`: Called with every change
+    pub async fn run(
+        &mut self,
+        client: &TransitClient,
+        mut on_event: impl FnMut(AdvisoryEvent),
+    ) -> Result<(), Error> {
+        loop {
+            match client.service_advisories(vec![], Usage::Normal).await {
+                Ok(current) => self.update(&current)?.into_iter().for_each(&mut on_event),
+                Err(why) => log::warn!("Could not fetch the service advisories: {why:?}"),
+            }
+            self.wait().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::*;
+    use crate::store::{
+        advisories::{AdvisoryEvent, Tracker},
+        Store,
+    };
+    use time::macros::datetime;
+
+    fn advisory(key: u32, priority: Priority, category: Category) -> ServiceAdvisory {
+        ServiceAdvisory {
+            key,
+            priority,
+            title: format!("Advisory {key}"),
+            body: "Route 16 will detour.".to_string(),
+            category,
+            updated_at: Some(datetime!(2023-06-01 9:00)),
+        }
+    }
+
+    #[test]
+    fn sync_advisories() -> Result<(), crate::structs::Error> {
+        let mut store = Store::open_in_memory()?;
+        let first = advisory(1, Priority::High, Category::Transit);
+        let second = advisory(2, Priority::Low, Category::All);
+        assert_eq!(
+            store.sync_advisories(&[first.clone(), second.clone()])?,
+            vec![
+                AdvisoryEvent::New(first.clone()),
+                AdvisoryEvent::New(second.clone())
+            ]
+        );
+        assert_eq!(store.advisories()?, vec![first.clone(), second.clone()]);
+
+        // Nothing changed
+        assert_eq!(
+            store.sync_advisories(&[second.clone(), first.clone()])?,
+            vec![]
+        );
+
+        let mut updated = first.clone();
+        updated.body = "Route 16 will detour on Osborne Street.".to_string();
+        updated.updated_at = Some(datetime!(2023-06-02 9:00));
+        let third = advisory(3, Priority::VeryHigh, Category::HandiTransit);
+        assert_eq!(
+            store.sync_advisories(&[updated.clone(), third.clone()])?,
+            vec![
+                AdvisoryEvent::Updated {
+                    previous: first,
+                    current: updated.clone(),
+                },
+                AdvisoryEvent::New(third.clone()),
+                AdvisoryEvent::Removed(second),
+            ]
+        );
+        assert_eq!(store.advisories()?, vec![updated, third]);
+        Ok(())
+    }
+
+    #[test]
+    fn without_update_time() -> Result<(), crate::structs::Error> {
+        let mut store = Store::open_in_memory()?;
+        let mut advisory = advisory(1, Priority::Medium, Category::Transit);
+        advisory.updated_at = None;
+        store.sync_advisories(&[advisory.clone()])?;
+        assert_eq!(store.sync_advisories(&[advisory.clone()])?, vec![]);
+
+        let mut changed = advisory.clone();
+        changed.title = "Detour".to_string();
+        assert_eq!(
+            store.sync_advisories(&[changed.clone()])?,
+            vec![AdvisoryEvent::Updated {
+                previous: advisory,
+                current: changed,
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn filtered_events() -> Result<(), crate::structs::Error> {
+        let mut tracker = Tracker::new(Store::open_in_memory()?)
+            .priority(Priority::High)
+            .category(Category::Transit);
+        let urgent = advisory(1, Priority::VeryHigh, Category::Transit);
+        let everyone = advisory(2, Priority::High, Category::All);
+        let handi_transit = advisory(3, Priority::VeryHigh, Category::HandiTransit);
+        let low = advisory(4, Priority::Low, Category::Transit);
+        let events = tracker.update(&[
+            urgent.clone(),
+            everyone.clone(),
+            handi_transit.clone(),
+            low.clone(),
+        ])?;
+        assert_eq!(
+            events,
+            vec![AdvisoryEvent::New(urgent), AdvisoryEvent::New(everyone)]
+        );
+        // Filtered advisories are saved anyway
+        assert_eq!(tracker.store().advisories()?.len(), 4);
+
+        let event = AdvisoryEvent::Removed(handi_transit);
+        assert!(event.matches(&Priority::VeryLow, &Category::All));
+        assert!(event.matches(&Priority::VeryHigh, &Category::HandiTransit));
+        assert!(!event.matches(&Priority::VeryLow, &Category::Transit));
+        assert!(!AdvisoryEvent::New(low).matches(&Priority::Medium, &Category::All));
+        Ok(())
+    }
+}
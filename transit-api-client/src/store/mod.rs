@@ -0,0 +1,775 @@
+// TransitTrail - Navigate Winnipeg Transit with a different style
+// Copyright (C) - 2023 Foxx Azalea Pinkerton, Max Fehlinger
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! A persistent store for static data from the API, backed by SQLite.
+//!
+//! Stops, stop features, routes, variants, destinations and streets rarely change, so they can be
+//! fetched once and read from disk afterwards. Every record remembers when it was saved, so
+//! callers can decide when to fetch it again with [Store::is_stale].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use time::Duration;
+//! use transit_api_client::prelude::*;
+//! use transit_api_client::store::{Record, Store};
+//!
+//! # tokio_test::block_on(async {
+//! let client = TransitClient::new("<YOUR_API_TOKEN>".to_string());
+//! let store = Store::open("transit.sqlite").unwrap();
+//!
+//! if store.is_stale(Record::Stop(10064), Duration::days(7)).unwrap() {
+//!     let stop = client.stop_info(10064, Usage::Normal).await.unwrap();
+//!     store.save_stops(&[stop]).unwrap();
+//! }
+//! let stop = store.stop(10064).unwrap().unwrap();
+//! # });
+//! ```
+//!
+
+pub mod adherence;
+pub mod advisories;
+
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::de::DeserializeOwned;
+use time::{Duration, OffsetDateTime};
+
+use crate::structs::{
+    common::Street,
+    destinations::Destination,
+    routes::{Route, Variant},
+    stops::{Feature, Stop},
+    Error,
+};
+
+/// The current version of the database layout. Databases with an older version are migrated
+/// when they are opened.
+pub const SCHEMA_VERSION: u32 = 4;
+
+/// The statements to migrate from the version of their index to the next version
+const MIGRATIONS: &[&str] = &[
+    // 0 -> 1: The initial layout
+    "
+    CREATE TABLE stops (
+        key INTEGER PRIMARY KEY,
+        street INTEGER NOT NULL,
+        cross_street INTEGER NOT NULL,
+        latitude REAL NOT NULL,
+        longitude REAL NOT NULL,
+        data TEXT NOT NULL,
+        fetched_at INTEGER NOT NULL
+    );
+    CREATE INDEX stops_street ON stops (street);
+    CREATE INDEX stops_cross_street ON stops (cross_street);
+
+    CREATE TABLE stop_features (
+        stop INTEGER PRIMARY KEY,
+        data TEXT NOT NULL,
+        fetched_at INTEGER NOT NULL
+    );
+
+    CREATE TABLE routes (
+        key TEXT PRIMARY KEY,
+        data TEXT NOT NULL,
+        fetched_at INTEGER NOT NULL
+    );
+
+    CREATE TABLE stop_routes (
+        stop INTEGER NOT NULL,
+        route TEXT NOT NULL,
+        PRIMARY KEY (stop, route)
+    );
+    CREATE TABLE stop_routes_fetched (
+        stop INTEGER PRIMARY KEY,
+        fetched_at INTEGER NOT NULL
+    );
+
+    CREATE TABLE variants (
+        key TEXT PRIMARY KEY,
+        data TEXT NOT NULL,
+        fetched_at INTEGER NOT NULL
+    );
+
+    CREATE TABLE stop_variants (
+        stop INTEGER NOT NULL,
+        variant TEXT NOT NULL,
+        PRIMARY KEY (stop, variant)
+    );
+
+    CREATE TABLE destinations (
+        variant TEXT NOT NULL,
+        key INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        PRIMARY KEY (variant, key)
+    );
+    CREATE TABLE destinations_fetched (
+        variant TEXT PRIMARY KEY,
+        fetched_at INTEGER NOT NULL
+    );
+
+    CREATE TABLE streets (
+        key INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        data TEXT NOT NULL,
+        fetched_at INTEGER NOT NULL
+    );
+    CREATE INDEX streets_name ON streets (name);
+    ",
+    // 1 -> 2: Observations of the adherence recorder
+    "
+    CREATE TABLE observations (
+        scheduled_stop TEXT NOT NULL,
+        stop INTEGER NOT NULL,
+        route TEXT NOT NULL,
+        variant TEXT NOT NULL,
+        bus INTEGER,
+        cancelled INTEGER NOT NULL,
+        scheduled_arrival TEXT,
+        estimated_arrival TEXT,
+        scheduled_departure TEXT,
+        estimated_departure TEXT,
+        observed_at TEXT NOT NULL,
+        PRIMARY KEY (scheduled_stop, stop, observed_at)
+    );
+    CREATE INDEX observations_scheduled_departure ON observations (scheduled_departure);
+    ",
+    // 2 -> 3: Service advisories seen by the advisory tracker
+    "
+    CREATE TABLE advisories (
+        key INTEGER PRIMARY KEY,
+        updated_at TEXT,
+        data TEXT NOT NULL,
+        fetched_at INTEGER NOT NULL
+    );
+    ",
+    // 3 -> 4: When the variants at a stop were saved
+    "
+    CREATE TABLE stop_variants_fetched (
+        stop INTEGER PRIMARY KEY,
+        fetched_at INTEGER NOT NULL
+    );
+    ",
+];
+
+/// A single record in the [Store], to check when it was saved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Record<'a> {
+    /// A stop by its key
+    Stop(u32),
+
+    /// The features of a stop by the stop's key
+    Features(u32),
+
+    /// A route by its key
+    Route(&'a str),
+
+    /// The routes, that service a stop, by the stop's key
+    RoutesAtStop(u32),
+
+    /// A variant by its key
+    Variant(&'a str),
+
+    /// The variants, that stop at a stop, by the stop's key
+    VariantsAtStop(u32),
+
+    /// The destinations of a variant, by the variant's key
+    Destinations(&'a str),
+
+    /// A street by its key
+    Street(u32),
+}
+
+/// A SQLite database with static data from the API.
+#[derive(Debug)]
+pub struct Store {
+    connection: Connection,
+}
+
+impl Store {
+    /// Opens the database at the path and creates or migrates it, if necessary.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: Where the database file is located
+    ///
+    /// returns: Result<Store, Error>
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// Creates a new database, that only lives in memory.
+    pub fn open_in_memory() -> Result<Self, Error> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(mut connection: Connection) -> Result<Self, Error> {
+        let version: u32 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            return Err(Error::UnsupportedSchemaVersion(version));
+        }
+        let transaction = connection.transaction()?;
+        for migration in &MIGRATIONS[version as usize..] {
+            transaction.execute_batch(migration)?;
+        }
+        transaction.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        transaction.commit()?;
+        Ok(Self { connection })
+    }
+
+    /// The version of the layout of the opened database. Always [SCHEMA_VERSION] after opening.
+    pub fn schema_version(&self) -> Result<u32, Error> {
+        Ok(self
+            .connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?)
+    }
+
+    /// When a record was last saved, or [None] if it was never saved.
+    ///
+    /// # Arguments
+    ///
+    /// * `record`: The record to look up
+    ///
+    /// returns: Result<Option<OffsetDateTime>, Error>
+    pub fn fetched_at(&self, record: Record<'_>) -> Result<Option<OffsetDateTime>, Error> {
+        let (table, column, key): (&str, &str, &dyn rusqlite::ToSql) = match &record {
+            Record::Stop(key) => ("stops", "key", key),
+            Record::Features(key) => ("stop_features", "stop", key),
+            Record::Route(key) => ("routes", "key", key),
+            Record::RoutesAtStop(key) => ("stop_routes_fetched", "stop", key),
+            Record::Variant(key) => ("variants", "key", key),
+            Record::VariantsAtStop(key) => ("stop_variants_fetched", "stop", key),
+            Record::Destinations(key) => ("destinations_fetched", "variant", key),
+            Record::Street(key) => ("streets", "key", key),
+        };
+        let timestamp: Option<i64> = self
+            .connection
+            .query_row(
+                &format!("SELECT fetched_at FROM {table} WHERE {column} = ?1"),
+                [key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(timestamp.and_then(|timestamp| OffsetDateTime::from_unix_timestamp(timestamp).ok()))
+    }
+
+    /// Whether a record is missing or was saved longer than `max_age` ago.
+    ///
+    /// # Arguments
+    ///
+    /// * `record`: The record to check
+    /// * `max_age`: How old the record may be, before it should be fetched again
+    ///
+    /// returns: Result<bool, Error>
+    pub fn is_stale(&self, record: Record<'_>, max_age: Duration) -> Result<bool, Error> {
+        Ok(match self.fetched_at(record)? {
+            Some(fetched_at) => OffsetDateTime::now_utc() - fetched_at > max_age,
+            None => true,
+        })
+    }
+
+    /// Saves stops, replacing older versions of them.
+    pub fn save_stops(&self, stops: &[Stop]) -> Result<(), Error> {
+        let now = now();
+        let mut statement = self.connection.prepare_cached(
+            "INSERT OR REPLACE INTO stops
+                (key, street, cross_street, latitude, longitude, data, fetched_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+        for stop in stops {
+            statement.execute(params![
+                stop.key,
+                stop.street.key,
+                stop.cross_street.key,
+                stop.centre.latitude,
+                stop.centre.longitude,
+                serde_json::to_string(stop)?,
+                now,
+            ])?;
+        }
+        Ok(())
+    }
+
+    /// A stop by its key
+    pub fn stop(&self, key: u32) -> Result<Option<Stop>, Error> {
+        self.get("SELECT data FROM stops WHERE key = ?1", params![key])
+    }
+
+    /// All saved stops, ordered by their key
+    pub fn stops(&self) -> Result<Vec<Stop>, Error> {
+        self.all("SELECT data FROM stops ORDER BY key", [])
+    }
+
+    /// All saved stops on a street, or where the street crosses, ordered by their key
+    ///
+    /// # Arguments
+    ///
+    /// * `street`: The key of the [Street]
+    ///
+    /// returns: Result<Vec<Stop>, Error>
+    pub fn stops_on_street(&self, street: u32) -> Result<Vec<Stop>, Error> {
+        self.all(
+            "SELECT data FROM stops WHERE street = ?1 OR cross_street = ?1 ORDER BY key",
+            params![street],
+        )
+    }
+
+    /// Saves the features of a stop, replacing its older features.
+    pub fn save_features(&self, stop: u32, features: &[Feature]) -> Result<(), Error> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO stop_features (stop, data, fetched_at) VALUES (?1, ?2, ?3)",
+            params![stop, serde_json::to_string(features)?, now()],
+        )?;
+        Ok(())
+    }
+
+    /// The features of a stop, or [None] if they were never saved
+    pub fn features(&self, stop: u32) -> Result<Option<Vec<Feature>>, Error> {
+        self.get(
+            "SELECT data FROM stop_features WHERE stop = ?1",
+            params![stop],
+        )
+    }
+
+    /// Saves routes, replacing older versions of them.
+    pub fn save_routes(&self, routes: &[Route]) -> Result<(), Error> {
+        let now = now();
+        let mut statement = self.connection.prepare_cached(
+            "INSERT OR REPLACE INTO routes (key, data, fetched_at) VALUES (?1, ?2, ?3)",
+        )?;
+        for route in routes {
+            statement.execute(params![
+                route_key(route),
+                serde_json::to_string(route)?,
+                now
+            ])?;
+        }
+        Ok(())
+    }
+
+    /// A route by its key, e.g. `"16"` or `"BLUE"`
+    pub fn route(&self, key: &str) -> Result<Option<Route>, Error> {
+        self.get("SELECT data FROM routes WHERE key = ?1", params![key])
+    }
+
+    /// All saved routes, ordered by their key
+    pub fn routes(&self) -> Result<Vec<Route>, Error> {
+        self.all("SELECT data FROM routes ORDER BY key", [])
+    }
+
+    /// Saves the routes that service a stop, like they are returned by
+    /// [routes_by_stop](crate::TransitClient::routes_by_stop). The routes themselves are saved
+    /// as well.
+    pub fn save_routes_at_stop(&mut self, stop: u32, routes: &[Route]) -> Result<(), Error> {
+        self.save_routes(routes)?;
+        let transaction = self.connection.transaction()?;
+        transaction.execute("DELETE FROM stop_routes WHERE stop = ?1", params![stop])?;
+        for route in routes {
+            transaction.execute(
+                "INSERT OR IGNORE INTO stop_routes (stop, route) VALUES (?1, ?2)",
+                params![stop, route_key(route)],
+            )?;
+        }
+        transaction.execute(
+            "INSERT OR REPLACE INTO stop_routes_fetched (stop, fetched_at) VALUES (?1, ?2)",
+            params![stop, now()],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// The saved routes that service a stop, ordered by their key
+    pub fn routes_at_stop(&self, stop: u32) -> Result<Vec<Route>, Error> {
+        self.all(
+            "SELECT routes.data FROM stop_routes
+                JOIN routes ON routes.key = stop_routes.route
+                WHERE stop_routes.stop = ?1
+                ORDER BY routes.key",
+            params![stop],
+        )
+    }
+
+    /// Saves variants, replacing older versions of them.
+    pub fn save_variants(&self, variants: &[Variant]) -> Result<(), Error> {
+        let now = now();
+        let mut statement = self.connection.prepare_cached(
+            "INSERT OR REPLACE INTO variants (key, data, fetched_at) VALUES (?1, ?2, ?3)",
+        )?;
+        for variant in variants {
+            statement.execute(params![
+                variant.key.to_string(),
+                serde_json::to_string(variant)?,
+                now
+            ])?;
+        }
+        Ok(())
+    }
+
+    /// A variant by its key, e.g. `"16-1-K"`
+    pub fn variant(&self, key: &str) -> Result<Option<Variant>, Error> {
+        self.get("SELECT data FROM variants WHERE key = ?1", params![key])
+    }
+
+    /// All saved variants of a route, ordered by their key
+    ///
+    /// # Arguments
+    ///
+    /// * `route`: The key of the route, e.g. `"16"` or `"BLUE"`
+    ///
+    /// returns: Result<Vec<Variant>, Error>
+    pub fn variants_of_route(&self, route: &str) -> Result<Vec<Variant>, Error> {
+        self.all(
+            "SELECT data FROM variants WHERE key LIKE ?1 || '-%' ORDER BY key",
+            params![route],
+        )
+    }
+
+    /// Saves the variants that stop at a stop, like they are returned by
+    /// [variants_by_stop](crate::TransitClient::variants_by_stop). The variants themselves are
+    /// saved as well.
+    pub fn save_variants_at_stop(&mut self, stop: u32, variants: &[Variant]) -> Result<(), Error> {
+        self.save_variants(variants)?;
+        let transaction = self.connection.transaction()?;
+        transaction.execute("DELETE FROM stop_variants WHERE stop = ?1", params![stop])?;
+        for variant in variants {
+            transaction.execute(
+                "INSERT OR IGNORE INTO stop_variants (stop, variant) VALUES (?1, ?2)",
+                params![stop, variant.key.to_string()],
+            )?;
+        }
+        transaction.execute(
+            "INSERT OR REPLACE INTO stop_variants_fetched (stop, fetched_at) VALUES (?1, ?2)",
+            params![stop, now()],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// The saved variants that stop at a stop, ordered by their key
+    pub fn variants_at_stop(&self, stop: u32) -> Result<Vec<Variant>, Error> {
+        self.all(
+            "SELECT variants.data FROM stop_variants
+                JOIN variants ON variants.key = stop_variants.variant
+                WHERE stop_variants.stop = ?1
+                ORDER BY variants.key",
+            params![stop],
+        )
+    }
+
+    /// Saves the destinations of a variant, replacing its older destinations.
+    pub fn save_destinations(
+        &mut self,
+        variant: &str,
+        destinations: &[Destination],
+    ) -> Result<(), Error> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "DELETE FROM destinations WHERE variant = ?1",
+            params![variant],
+        )?;
+        for (position, destination) in destinations.iter().enumerate() {
+            transaction.execute(
+                "INSERT OR REPLACE INTO destinations (variant, key, name, position)
+                    VALUES (?1, ?2, ?3, ?4)",
+                params![variant, destination.key, destination.name, position],
+            )?;
+        }
+        transaction.execute(
+            "INSERT OR REPLACE INTO destinations_fetched (variant, fetched_at) VALUES (?1, ?2)",
+            params![variant, now()],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// The destinations of a variant in the order they were returned from the API, or [None] if
+    /// they were never saved
+    pub fn destinations(&self, variant: &str) -> Result<Option<Vec<Destination>>, Error> {
+        if self.fetched_at(Record::Destinations(variant))?.is_none() {
+            return Ok(None);
+        }
+        let mut statement = self.connection.prepare_cached(
+            "SELECT key, name FROM destinations WHERE variant = ?1 ORDER BY position",
+        )?;
+        let destinations = statement
+            .query_map(params![variant], |row| {
+                Ok(Destination {
+                    key: row.get(0)?,
+                    name: row.get(1)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Some(destinations))
+    }
+
+    /// Saves streets, replacing older versions of them.
+    pub fn save_streets(&self, streets: &[Street]) -> Result<(), Error> {
+        let now = now();
+        let mut statement = self.connection.prepare_cached(
+            "INSERT OR REPLACE INTO streets (key, name, data, fetched_at) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for street in streets {
+            statement.execute(params![
+                street.key,
+                street.name,
+                serde_json::to_string(street)?,
+                now
+            ])?;
+        }
+        Ok(())
+    }
+
+    /// A street by its key
+    pub fn street(&self, key: u32) -> Result<Option<Street>, Error> {
+        self.get("SELECT data FROM streets WHERE key = ?1", params![key])
+    }
+
+    /// All saved streets whose name contains the text, ignoring ASCII case, ordered by name
+    pub fn streets_named(&self, name: &str) -> Result<Vec<Street>, Error> {
+        let pattern = format!(
+            "%{}%",
+            name.replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        self.all(
+            "SELECT data FROM streets WHERE name LIKE ?1 ESCAPE '\\' ORDER BY name, key",
+            params![pattern],
+        )
+    }
+
+    /// Deserializes the `data` column of the only row of a query
+    fn get<T: DeserializeOwned>(
+        &self,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Option<T>, Error> {
+        let data: Option<String> = self
+            .connection
+            .prepare_cached(sql)?
+            .query_row(params, |row| row.get(0))
+            .optional()?;
+        Ok(match data {
+            Some(data) => Some(serde_json::from_str(&data)?),
+            None => None,
+        })
+    }
+
+    /// Deserializes the `data` column of all rows of a query
+    fn all<T: DeserializeOwned>(
+        &self,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<T>, Error> {
+        let mut statement = self.connection.prepare_cached(sql)?;
+        let rows = statement.query_map(params, |row: &Row| row.get::<_, String>(0))?;
+        let mut out = Vec::new();
+        for data in rows {
+            out.push(serde_json::from_str(&data?)?);
+        }
+        Ok(out)
+    }
+}
+
+/// The key of a route, as it is used in the API
+fn route_key(route: &Route) -> String {
+    match route {
+        Route::Blue(blue) => blue.key.clone(),
+        Route::Regular(regular) => regular.key.to_string(),
+    }
+}
+
+fn now() -> i64 {
+    OffsetDateTime::now_utc().unix_timestamp()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::*;
+    use crate::store::{Record, Store, SCHEMA_VERSION};
+    use time::Duration;
+
+    fn street(key: u32, name: &str) -> Street {
+        Street {
+            key,
+            name: name.to_string(),
+            street_type: None,
+            leg: None,
+        }
+    }
+
+    fn stop(key: u32, street_key: u32, cross_street: u32) -> Stop {
+        Stop {
+            key,
+            name: format!("Stop {key}"),
+            number: key,
+            // Serialized as numbers instead of strings
+            distances: Some(Distances {
+                direct: 12.5,
+                walking: 20.0,
+            }),
+            direction: Direction::Northbound,
+            side: Side::Nearside,
+            street: street(street_key, "Osborne Street"),
+            cross_street: street(cross_street, "Glasgow Avenue"),
+            centre: GeoLocation::new(49.86912, -97.1375),
+            internal_name: None,
+            sequence_on_street: None,
+            icon_style: None,
+        }
+    }
+
+    fn route(key: u32) -> Route {
+        Route::Regular(Regular {
+            key,
+            number: key,
+            name: format!("Route {key}"),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn schema() -> Result<(), crate::structs::Error> {
+        let path =
+            std::env::temp_dir().join(format!("transit-store-{}.sqlite", std::process::id()));
+        {
+            let store = Store::open(&path)?;
+            assert_eq!(store.schema_version()?, SCHEMA_VERSION);
+            store.save_streets(&[street(2715, "Osborne Street")])?;
+        }
+        // Opening again must not run the migrations again
+        let store = Store::open(&path)?;
+        assert_eq!(store.street(2715)?, Some(street(2715, "Osborne Street")));
+
+        // A database of a newer version is not opened
+        store
+            .connection
+            .pragma_update(None, "user_version", SCHEMA_VERSION + 1)?;
+        drop(store);
+        assert!(matches!(
+            Store::open(&path),
+            Err(crate::structs::Error::UnsupportedSchemaVersion(version))
+                if version == SCHEMA_VERSION + 1
+        ));
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn stops() -> Result<(), crate::structs::Error> {
+        let store = Store::open_in_memory()?;
+        assert_eq!(store.stop(10064)?, None);
+        assert!(store.is_stale(Record::Stop(10064), Duration::days(1))?);
+
+        store.save_stops(&[
+            stop(10064, 2715, 1486),
+            stop(10625, 2715, 3300),
+            stop(10001, 1486, 9),
+        ])?;
+        assert_eq!(store.stop(10064)?, Some(stop(10064, 2715, 1486)));
+        assert_eq!(store.stops()?.len(), 3);
+        assert!(!store.is_stale(Record::Stop(10064), Duration::days(1))?);
+        assert!(store.fetched_at(Record::Stop(10064))?.is_some());
+
+        let keys = |stops: Vec<Stop>| stops.into_iter().map(|stop| stop.key).collect::<Vec<_>>();
+        assert_eq!(keys(store.stops_on_street(2715)?), vec![10064, 10625]);
+        assert_eq!(keys(store.stops_on_street(1486)?), vec![10001, 10064]);
+
+        store.save_features(
+            10064,
+            &[Feature {
+                name: "Bench".to_string(),
+                count: 2,
+            }],
+        )?;
+        assert_eq!(store.features(10064)?.unwrap()[0].count, 2);
+        assert_eq!(store.features(10625)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn routes_and_variants() -> Result<(), crate::structs::Error> {
+        let mut store = Store::open_in_memory()?;
+        store.save_routes_at_stop(10064, &[route(16), route(11)])?;
+        store.save_routes_at_stop(10625, &[route(16)])?;
+        assert_eq!(store.routes()?.len(), 2);
+        assert_eq!(store.routes_at_stop(10064)?, vec![route(11), route(16)]);
+        assert_eq!(store.routes_at_stop(10625)?, vec![route(16)]);
+        assert_eq!(store.route("16")?, Some(route(16)));
+        assert!(store.fetched_at(Record::RoutesAtStop(10064))?.is_some());
+
+        // Replaces the routes at the stop
+        store.save_routes_at_stop(10064, &[route(11)])?;
+        assert_eq!(store.routes_at_stop(10064)?, vec![route(11)]);
+
+        let variant = |key: &str| Variant {
+            key: key.parse().unwrap(),
+            name: Some(format!("Variant {key}")),
+        };
+        store.save_variants_at_stop(10064, &[variant("16-1-K"), variant("11-1-G")])?;
+        store.save_variants(&[variant("160-0-U")])?;
+        assert_eq!(store.variant("16-1-K")?, Some(variant("16-1-K")));
+        assert_eq!(store.variants_of_route("16")?, vec![variant("16-1-K")]);
+        assert_eq!(
+            store.variants_at_stop(10064)?,
+            vec![variant("11-1-G"), variant("16-1-K")]
+        );
+        assert!(store.fetched_at(Record::VariantsAtStop(10064))?.is_some());
+        assert_eq!(store.fetched_at(Record::VariantsAtStop(10625))?, None);
+
+        assert_eq!(store.destinations("16-1-K")?, None);
+        let destinations = vec![
+            Destination {
+                key: 3,
+                name: "Downtown".to_string(),
+            },
+            Destination {
+                key: 1,
+                name: "Kingston Row".to_string(),
+            },
+        ];
+        store.save_destinations("16-1-K", &destinations)?;
+        assert_eq!(store.destinations("16-1-K")?, Some(destinations));
+        store.save_destinations("16-1-K", &[])?;
+        assert_eq!(store.destinations("16-1-K")?, Some(vec![]));
+        Ok(())
+    }
+
+    #[test]
+    fn streets() -> Result<(), crate::structs::Error> {
+        let store = Store::open_in_memory()?;
+        store.save_streets(&[
+            street(2715, "Osborne Street"),
+            street(1486, "Glasgow Avenue"),
+            street(2716, "Osborne Street North"),
+            street(9999, "100% Street"),
+        ])?;
+        let names = |streets: Vec<Street>| {
+            streets
+                .into_iter()
+                .map(|street| street.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(store.streets_named("osborne")?),
+            vec!["Osborne Street", "Osborne Street North"]
+        );
+        assert_eq!(names(store.streets_named("%")?), vec!["100% Street"]);
+        assert!(store.streets_named("Portage")?.is_empty());
+        Ok(())
+    }
+}
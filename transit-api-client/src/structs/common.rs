@@ -17,10 +17,15 @@
 //!
 
 use crate::prelude::Stop;
+use crate::structs::trip_planner::Bounds;
 use crate::structs::UrlParameter;
 use serde::{de::Error, Deserialize, Serialize};
 use serde_json::{Map, Number, Value};
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The mean radius of the Earth in metres, used for distances between [GeoLocation]s
+pub const EARTH_RADIUS: f64 = 6_371_000.0;
 
 /// A point on the Earth: A geographic location, represented by longitude and latitude.
 ///
@@ -47,6 +52,129 @@ impl GeoLocation {
             longitude,
         }
     }
+
+    /// The great-circle distance to another point in metres, using the haversine formula.
+    ///
+    /// # Arguments
+    ///
+    /// * `other`: The point to measure the distance to
+    ///
+    /// returns: f64
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use transit_api_client::prelude::GeoLocation;
+    ///
+    /// let the_forks = GeoLocation::new(49.8875, -97.1313);
+    /// let polo_park = GeoLocation::new(49.8858, -97.1992);
+    /// assert!((the_forks.distance(&polo_park) - 4870.0).abs() < 50.0);
+    /// ```
+    pub fn distance(&self, other: &GeoLocation) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.longitude - self.longitude).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS * a.sqrt().asin()
+    }
+
+    /// The initial bearing towards another point, in degrees clockwise from north (0..360).
+    ///
+    /// # Arguments
+    ///
+    /// * `other`: The point to head towards
+    ///
+    /// returns: f64
+    pub fn bearing(&self, other: &GeoLocation) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let d_lon = (other.longitude - self.longitude).to_radians();
+        let y = d_lon.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * d_lon.cos();
+        (y.atan2(x).to_degrees() + 360.0) % 360.0
+    }
+
+    /// The point reached when travelling a distance along a great circle, starting with the
+    /// given bearing.
+    ///
+    /// # Arguments
+    ///
+    /// * `bearing`: The initial bearing, in degrees clockwise from north
+    /// * `distance`: How far to travel in metres
+    ///
+    /// returns: GeoLocation
+    pub fn destination(&self, bearing: f64, distance: f64) -> GeoLocation {
+        let angle = distance / EARTH_RADIUS;
+        let bearing = bearing.to_radians();
+        let lat1 = self.latitude.to_radians();
+        let lon1 = self.longitude.to_radians();
+        let lat2 = (lat1.sin() * angle.cos() + lat1.cos() * angle.sin() * bearing.cos()).asin();
+        let lon2 = lon1
+            + (bearing.sin() * angle.sin() * lat1.cos())
+                .atan2(angle.cos() - lat1.sin() * lat2.sin());
+        GeoLocation::new(
+            lat2.to_degrees(),
+            (lon2.to_degrees() + 540.0) % 360.0 - 180.0,
+        )
+    }
+
+    /// The smallest [Bounds] containing every point within a radius around this point.
+    ///
+    /// # Arguments
+    ///
+    /// * `radius`: The radius in metres
+    ///
+    /// returns: Bounds
+    pub fn bounding_box(&self, radius: f64) -> Bounds {
+        let north = self.destination(0.0, radius);
+        let east = self.destination(90.0, radius);
+        let south = self.destination(180.0, radius);
+        let west = self.destination(270.0, radius);
+        Bounds {
+            maximum: GeoLocation::new(north.latitude, east.longitude),
+            minimum: GeoLocation::new(south.latitude, west.longitude),
+        }
+    }
+
+    /// Whether this point lies inside a polygon, using ray casting.
+    ///
+    /// The polygon does not have to be closed, its last point is connected to its first one.
+    ///
+    /// # Arguments
+    ///
+    /// * `polygon`: The corners of the polygon
+    ///
+    /// returns: bool
+    pub fn is_in_polygon(&self, polygon: &[GeoLocation]) -> bool {
+        let mut inside = false;
+        let mut previous = match polygon.last() {
+            Some(previous) => previous,
+            None => return false,
+        };
+        for current in polygon {
+            if (current.latitude > self.latitude) != (previous.latitude > self.latitude) {
+                let crossing = current.longitude
+                    + (self.latitude - current.latitude) / (previous.latitude - current.latitude)
+                        * (previous.longitude - current.longitude);
+                if self.longitude < crossing {
+                    inside = !inside;
+                }
+            }
+            previous = current;
+        }
+        inside
+    }
+
+    /// How long it takes to walk to another point in a straight line.
+    ///
+    /// # Arguments
+    ///
+    /// * `other`: The point to walk to
+    /// * `walk_speed`: The walking speed in km/h, like [WalkSpeed](crate::filters::TripPlan::WalkSpeed)
+    ///
+    /// returns: Duration
+    pub fn walking_time(&self, other: &GeoLocation, walk_speed: f32) -> time::Duration {
+        time::Duration::seconds_f64(self.distance(other) / (walk_speed as f64 / 3.6))
+    }
 }
 
 impl Eq for GeoLocation {}
@@ -63,14 +191,25 @@ impl<'de> serde::de::Deserialize<'de> for GeoLocation {
         // longitude and latitude fields.
         if map.contains_key("latitude") && map.contains_key("longitude") {
             // the longitude and latitude fields are stored with quotes, so directly asking for
-            // them as a float, would error out.
-            let latitude: f64 = match map.get("latitude").unwrap().as_str() {
-                Some(l) => l.parse().map_err(Error::custom)?,
-                None => return Err(Error::custom("field `latitude` is not of type  `str`")),
+            // them as a float, would error out. They are numbers, if they were serialized by
+            // this crate.
+            let latitude: f64 = match map.get("latitude").unwrap() {
+                Value::String(l) => l.parse().map_err(Error::custom)?,
+                Value::Number(l) => l.as_f64().unwrap_or_default(),
+                _ => {
+                    return Err(Error::custom(
+                        "field `latitude` is not of type `str` or `f64`",
+                    ))
+                }
             };
-            let longitude: f64 = match map.get("longitude").unwrap().as_str() {
-                Some(l) => l.parse().map_err(Error::custom)?,
-                None => return Err(Error::custom("field `longitude` is not of type `str`")),
+            let longitude: f64 = match map.get("longitude").unwrap() {
+                Value::String(l) => l.parse().map_err(Error::custom)?,
+                Value::Number(l) => l.as_f64().unwrap_or_default(),
+                _ => {
+                    return Err(Error::custom(
+                        "field `longitude` is not of type `str` or `f64`",
+                    ))
+                }
             };
 
             return Ok(Self {
@@ -216,6 +355,166 @@ impl Display for PartialLocation<'_> {
     }
 }
 
+impl<'a> TryFrom<&'a str> for PartialLocation<'a> {
+    type Error = &'static str;
+
+    /// Parses a location reference, as produced by [Display], back into a partial location,
+    /// e.g. `stops/10064`, `geo/49.89,-97.13` or `intersections/41059:2265@2871`.
+    ///
+    /// The keys of addresses, monuments and intersections are borrowed from the reference.
+    fn try_from(reference: &'a str) -> Result<Self, Self::Error> {
+        let (kind, key) = reference
+            .split_once('/')
+            .ok_or("Not of the form `type/key`")?;
+        if key.is_empty() {
+            return Err("The key of a location reference can not be empty");
+        }
+        match kind {
+            "addresses" => Ok(Self::Address(key)),
+            "monuments" => Ok(Self::Monument(key)),
+            "intersections" => {
+                key.parse::<IntersectionKey>()?;
+                Ok(Self::Intersection(key))
+            }
+            "geo" => {
+                let (lat, lon) = key
+                    .split_once(',')
+                    .ok_or("A point is not of the form `geo/lat,lon`")?;
+                match (lat.trim().parse(), lon.trim().parse()) {
+                    (Ok(lat), Ok(lon)) => Ok(Self::Point(lat, lon)),
+                    _ => Err("The latitude or longitude of a point is not a number"),
+                }
+            }
+            "stops" => key
+                .parse()
+                .map(Self::Stop)
+                .map_err(|_| "The key of a stop is not a number"),
+            _ => Err("Not equal to `addresses`, `monuments`, `intersections`, `geo` or `stops`"),
+        }
+    }
+}
+
+/// An owned [PartialLocation], that does not borrow the keys of addresses, monuments and
+/// intersections. It can be kept in long-lived structs, be moved across tasks and be
+/// deserialized from owned JSON. It (de)serializes the same way as [PartialLocation].
+///
+/// # Examples
+///
+/// ```
+/// use transit_api_client::prelude::*;
+///
+/// let location: OwnedPartialLocation = "intersections/41059:2265@2871".parse().unwrap();
+/// assert_eq!(
+///     location.as_borrowed(),
+///     PartialLocation::Intersection("41059:2265@2871")
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum OwnedPartialLocation {
+    /// The address of a Location
+    Address(String),
+
+    /// The location is a significant point of interest
+    Monument(String),
+
+    /// The location is at an intersection of two streets
+    Intersection(String),
+
+    /// A geographic point, representing latitude and longitude
+    Point(f64, f64),
+
+    /// A stop with its id
+    Stop(u32),
+}
+
+impl OwnedPartialLocation {
+    /// Borrows the location as a [PartialLocation].
+    pub fn as_borrowed(&self) -> PartialLocation<'_> {
+        match self {
+            Self::Address(key) => PartialLocation::Address(key),
+            Self::Monument(key) => PartialLocation::Monument(key),
+            Self::Intersection(key) => PartialLocation::Intersection(key),
+            Self::Point(lat, lon) => PartialLocation::Point(*lat, *lon),
+            Self::Stop(key) => PartialLocation::Stop(*key),
+        }
+    }
+}
+
+impl Default for OwnedPartialLocation {
+    fn default() -> Self {
+        Self::Point(Default::default(), Default::default())
+    }
+}
+
+impl Display for OwnedPartialLocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.as_borrowed().fmt(f)
+    }
+}
+
+impl FromStr for OwnedPartialLocation {
+    type Err = &'static str;
+
+    /// Parses a location reference like `stops/10064` or `geo/49.89,-97.13`.
+    fn from_str(reference: &str) -> Result<Self, Self::Err> {
+        PartialLocation::try_from(reference).map(Self::from)
+    }
+}
+
+impl From<PartialLocation<'_>> for OwnedPartialLocation {
+    fn from(value: PartialLocation<'_>) -> Self {
+        match value {
+            PartialLocation::Address(key) => Self::Address(key.to_string()),
+            PartialLocation::Monument(key) => Self::Monument(key.to_string()),
+            PartialLocation::Intersection(key) => Self::Intersection(key.to_string()),
+            PartialLocation::Point(lat, lon) => Self::Point(lat, lon),
+            PartialLocation::Stop(key) => Self::Stop(key),
+        }
+    }
+}
+
+impl From<&Location> for OwnedPartialLocation {
+    fn from(value: &Location) -> Self {
+        match value {
+            Location::Address(address) => Self::Address(address.key.to_string()),
+            Location::Monument(monument) => Self::Monument(monument.key.to_string()),
+            Location::Intersection(intersection) => Self::Intersection(intersection.key.clone()),
+            Location::Point(point) => point.into(),
+            Location::Stop(stop) => stop.into(),
+        }
+    }
+}
+
+impl From<Location> for OwnedPartialLocation {
+    fn from(value: Location) -> Self {
+        Self::from(&value)
+    }
+}
+
+impl From<&Stop> for OwnedPartialLocation {
+    fn from(value: &Stop) -> Self {
+        Self::Stop(value.key)
+    }
+}
+
+impl From<Stop> for OwnedPartialLocation {
+    fn from(value: Stop) -> Self {
+        Self::Stop(value.key)
+    }
+}
+
+impl From<&GeoLocation> for OwnedPartialLocation {
+    fn from(value: &GeoLocation) -> Self {
+        Self::Point(value.latitude, value.longitude)
+    }
+}
+
+impl From<GeoLocation> for OwnedPartialLocation {
+    fn from(value: GeoLocation) -> Self {
+        Self::from(&value)
+    }
+}
+
 /// Represents a Street, as it is returned from the API
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Street {
@@ -228,7 +527,8 @@ pub struct Street {
     /// [Usage::Normal](super::Usage::Normal) in the request.
     pub name: String,
 
-    /// Optionally a Street Type may be specified, e.g. Road, Boulevard, Street, etc.
+    /// Optionally a Street Type may be specified, e.g. Road, Boulevard, Street, etc. Abbreviated
+    /// in short responses, e.g. Rd, Blvd, St.
     #[serde(rename = "type")]
     pub street_type: Option<String>,
 
@@ -240,15 +540,19 @@ pub struct Street {
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum StreetLeg {
     /// The North part of the street (N)
+    #[serde(alias = "N")]
     North,
 
     /// The East part of the street (E)
+    #[serde(alias = "E")]
     East,
 
     /// The South part of the street (S)
+    #[serde(alias = "S")]
     South,
 
     /// The West part of the street (W)
+    #[serde(alias = "W")]
     West,
 }
 
@@ -303,7 +607,8 @@ pub struct Monument {
     /// What the point of interest is called
     pub name: String,
 
-    /// Which categories the point of interest has
+    /// Which categories the point of interest has. Left out of short responses.
+    #[serde(default)]
     pub categories: Vec<String>,
 
     /// The address of the point of interest
@@ -327,15 +632,289 @@ pub struct Intersection {
     pub centre: GeoLocation,
 }
 
+impl Intersection {
+    /// Parses the [key](Intersection::key) of the intersection into its parts.
+    ///
+    /// returns: Result<IntersectionKey, &str>
+    pub fn parsed_key(&self) -> Result<IntersectionKey, &'static str> {
+        self.key.parse()
+    }
+}
+
+/// The key of an [Intersection], like `41059:2265@2871`. It is made up of the key of the node
+/// on the street network, the key of the street and the key of the cross street.
+///
+/// # Examples
+///
+/// ```
+/// use transit_api_client::structs::common::IntersectionKey;
+///
+/// let key: IntersectionKey = "41059:2265@2871".parse().unwrap();
+/// assert_eq!(key.street(), 2265);
+/// assert_eq!(key.to_string(), "41059:2265@2871");
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct IntersectionKey {
+    node: u32,
+    street: u32,
+    cross_street: u32,
+}
+
+impl IntersectionKey {
+    /// The key of the node on the street network, where the streets meet
+    pub fn node(&self) -> u32 {
+        self.node
+    }
+
+    /// The key of the main [Street]
+    pub fn street(&self) -> u32 {
+        self.street
+    }
+
+    /// The key of the [Street] crossing the main street
+    pub fn cross_street(&self) -> u32 {
+        self.cross_street
+    }
+}
+
+impl FromStr for IntersectionKey {
+    type Err = &'static str;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        let (node, streets) = key
+            .split_once(':')
+            .ok_or("Not of the form `node:street@cross-street`")?;
+        let (street, cross_street) = streets
+            .split_once('@')
+            .ok_or("Not of the form `node:street@cross-street`")?;
+        match (node.parse(), street.parse(), cross_street.parse()) {
+            (Ok(node), Ok(street), Ok(cross_street)) => Ok(Self {
+                node,
+                street,
+                cross_street,
+            }),
+            _ => Err("The parts of an intersection key are not numbers"),
+        }
+    }
+}
+
+impl Display for IntersectionKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}@{}", self.node, self.street, self.cross_street)
+    }
+}
+
+impl Serialize for IntersectionKey {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for IntersectionKey {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let key = String::deserialize(deserializer)?;
+        key.parse().map_err(D::Error::custom)
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::structs::common::StreetLeg;
+    use crate::prelude::{Direction, Side, Stop};
+    use crate::structs::common::{
+        GeoLocation, IntersectionKey, Location, OwnedPartialLocation, PartialLocation, StreetLeg,
+    };
     use tokio_test::assert_err;
 
+    const THE_FORKS: GeoLocation = GeoLocation {
+        latitude: 49.8875,
+        longitude: -97.1313,
+    };
+    const POLO_PARK: GeoLocation = GeoLocation {
+        latitude: 49.8858,
+        longitude: -97.1992,
+    };
+
     #[test]
     fn try_from() -> Result<(), &'static str> {
         assert_eq!(StreetLeg::try_from("East")?, StreetLeg::East);
         assert_err!(StreetLeg::try_from("not a valid street leg"));
         Ok(())
     }
+
+    #[test]
+    fn intersection_key() -> Result<(), &'static str> {
+        let key: IntersectionKey = "41059:2265@2871".parse()?;
+        assert_eq!(key.node(), 41059);
+        assert_eq!(key.street(), 2265);
+        assert_eq!(key.cross_street(), 2871);
+        assert_eq!(key.to_string(), "41059:2265@2871");
+
+        for invalid in ["", "41059:2265", "41059@2871", "a:2265@2871", "41059:2265@"] {
+            assert!(invalid.parse::<IntersectionKey>().is_err(), "{invalid}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn intersection_key_serde() -> Result<(), serde_json::Error> {
+        let key: IntersectionKey = "41059:2265@2871".parse().unwrap();
+        assert_eq!(serde_json::to_string(&key)?, "\"41059:2265@2871\"");
+        assert_eq!(
+            serde_json::from_str::<IntersectionKey>("\"41059:2265@2871\"")?,
+            key
+        );
+        assert!(serde_json::from_str::<IntersectionKey>("\"41059\"").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn location_reference() -> Result<(), &'static str> {
+        let locations = [
+            PartialLocation::Address("136590"),
+            PartialLocation::Monument("2771"),
+            PartialLocation::Intersection("41059:2265@2871"),
+            PartialLocation::Point(49.89, -97.13),
+            PartialLocation::Stop(10064),
+        ];
+        for location in locations {
+            let reference = location.to_string();
+            assert_eq!(PartialLocation::try_from(reference.as_str())?, location);
+        }
+
+        assert_eq!(
+            PartialLocation::try_from("geo/49.89, -97.13")?,
+            PartialLocation::Point(49.89, -97.13)
+        );
+        for invalid in [
+            "",
+            "stops",
+            "stops/",
+            "stops/abc",
+            "geo/49.89",
+            "intersections/41059",
+            "streets/2265",
+        ] {
+            assert_err!(PartialLocation::try_from(invalid));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn owned_partial_location() -> Result<(), &'static str> {
+        let location: OwnedPartialLocation = "addresses/136590".parse()?;
+        assert_eq!(
+            location,
+            OwnedPartialLocation::Address("136590".to_string())
+        );
+        assert_eq!(location.as_borrowed(), PartialLocation::Address("136590"));
+        assert_eq!(location.to_string(), "addresses/136590");
+        assert!("monuments/".parse::<OwnedPartialLocation>().is_err());
+
+        assert_eq!(
+            OwnedPartialLocation::from(PartialLocation::Intersection("41059:2265@2871")),
+            OwnedPartialLocation::Intersection("41059:2265@2871".to_string())
+        );
+        assert_eq!(
+            OwnedPartialLocation::from(THE_FORKS),
+            OwnedPartialLocation::Point(49.8875, -97.1313)
+        );
+        let stop = Stop {
+            key: 10064,
+            name: "Northbound Osborne at Glasgow".to_string(),
+            number: 10064,
+            distances: None,
+            direction: Direction::Northbound,
+            side: Side::Farside,
+            street: Default::default(),
+            cross_street: Default::default(),
+            centre: GeoLocation::new(49.86912, -97.1375),
+            internal_name: None,
+            sequence_on_street: None,
+            icon_style: None,
+        };
+        assert_eq!(
+            OwnedPartialLocation::from(&stop),
+            OwnedPartialLocation::Stop(10064)
+        );
+        assert_eq!(
+            OwnedPartialLocation::from(Location::Stop(stop)),
+            OwnedPartialLocation::Stop(10064)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn owned_partial_location_serde() -> Result<(), serde_json::Error> {
+        let locations = [
+            OwnedPartialLocation::Monument("2771".to_string()),
+            OwnedPartialLocation::Point(49.89, -97.13),
+            OwnedPartialLocation::Stop(10064),
+        ];
+        for location in locations {
+            let json = serde_json::to_string(&location)?;
+            assert_eq!(
+                json,
+                serde_json::to_string(&location.as_borrowed())?,
+                "{location}"
+            );
+            assert_eq!(
+                serde_json::from_reader::<_, OwnedPartialLocation>(json.as_bytes())?,
+                location
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn distance_and_bearing() {
+        assert!((THE_FORKS.distance(&POLO_PARK) - 4868.2).abs() < 1.0);
+        assert_eq!(THE_FORKS.distance(&THE_FORKS), 0.0);
+        // Polo Park is almost due west of The Forks
+        assert!((THE_FORKS.bearing(&POLO_PARK) - 267.8).abs() < 0.1);
+        assert!((POLO_PARK.bearing(&THE_FORKS) - 87.7).abs() < 0.1);
+    }
+
+    #[test]
+    fn destination() {
+        let bearing = THE_FORKS.bearing(&POLO_PARK);
+        let distance = THE_FORKS.distance(&POLO_PARK);
+        let destination = THE_FORKS.destination(bearing, distance);
+        assert!(destination.distance(&POLO_PARK) < 0.01);
+
+        let north = THE_FORKS.destination(0.0, 1000.0);
+        assert!((north.longitude - THE_FORKS.longitude).abs() < 1e-9);
+        assert!((THE_FORKS.distance(&north) - 1000.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn bounding_box() {
+        let bounds = THE_FORKS.bounding_box(500.0);
+        assert!(bounds.contains(&THE_FORKS));
+        assert!(bounds.contains(&THE_FORKS.destination(45.0, 499.0)));
+        assert!(!bounds.contains(&POLO_PARK));
+        assert!(bounds.centre().distance(&THE_FORKS) < 1.0);
+        assert!(THE_FORKS.is_in_polygon(&bounds.corners()));
+    }
+
+    #[test]
+    fn is_in_polygon() {
+        // Roughly the Perimeter Highway
+        let perimeter = [
+            GeoLocation::new(49.9694, -97.2606),
+            GeoLocation::new(49.9644, -96.9861),
+            GeoLocation::new(49.7894, -96.9847),
+            GeoLocation::new(49.7733, -97.2519),
+        ];
+        assert!(THE_FORKS.is_in_polygon(&perimeter));
+        assert!(POLO_PARK.is_in_polygon(&perimeter));
+        // Selkirk
+        assert!(!GeoLocation::new(50.1436, -96.8839).is_in_polygon(&perimeter));
+        assert!(!THE_FORKS.is_in_polygon(&[]));
+    }
+
+    #[test]
+    fn walking_time() {
+        let time = THE_FORKS.walking_time(&POLO_PARK, 4.0);
+        assert_eq!(time.whole_minutes(), 73);
+    }
 }
@@ -19,7 +19,7 @@
 use std::fmt::Display;
 use std::str::FromStr;
 
-use serde::{de, Deserialize};
+use serde::{de, Deserialize, Serialize};
 use serde_json::Value;
 
 pub mod common;
@@ -41,17 +41,22 @@ impl Display for UrlParameter {
     }
 }
 
-#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 /// If the API should yield shorter, longer, or normal names.
+///
+/// All three are parsed into the same structures: Abbreviations like `NB` are accepted as
+/// aliases, and data left out of short responses falls back to its default.
 pub enum Usage {
     #[default]
     /// No modification to the length of the outputs
     Normal,
 
-    /// Yields more verbose names
+    /// Yields more verbose names and optional data, like the internal name of a stop
     Long,
 
-    /// Yields terser names
+    /// Yields terser names, like `NB` instead of `Northbound`, and leaves out optional data.
+    /// Cuts the size of the responses.
     Short,
 }
 
@@ -118,7 +123,13 @@ where
     <T as FromStr>::Err: Display,
 {
     let value = <Value>::deserialize(deserializer)?;
-    let string_value = value.as_str().ok_or(de::Error::custom("unknown type"))?;
+    // Values that were serialized by this crate are not strings anymore
+    let string_value = match value {
+        Value::String(s) => s,
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        _ => return Err(de::Error::custom("unknown type")),
+    };
     let t_value: T = string_value.parse().map_err(de::Error::custom)?;
 
     Ok(t_value)
@@ -132,6 +143,24 @@ pub enum Error {
 
     /// If an error occurred during the requests to the API
     Reqwest(reqwest::Error),
+
+    /// If a file could not be read or written
+    Io(std::io::Error),
+
+    /// If a GTFS feed is malformed or can not answer the request
+    Gtfs(String),
+
+    /// If an error occurred while reading from or writing to the [Store](crate::store::Store)
+    Sqlite(rusqlite::Error),
+
+    /// If a [Store](crate::store::Store) was created by a newer version of this crate. Contains
+    /// the version of its layout, which is newer than
+    /// [SCHEMA_VERSION](crate::store::SCHEMA_VERSION).
+    UnsupportedSchemaVersion(u32),
+
+    /// If a query has invalid or conflicting parameters, e.g. a
+    /// [TripPlanQuery](crate::filters::TripPlanQuery)
+    InvalidQuery(&'static str),
 }
 
 impl From<reqwest::Error> for Error {
@@ -146,6 +175,18 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(value: rusqlite::Error) -> Self {
+        Self::Sqlite(value)
+    }
+}
+
 time::serde::format_description!(
     datetime_formatter,
     PrimitiveDateTime,
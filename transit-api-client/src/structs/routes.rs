@@ -17,8 +17,13 @@
 //! consistent method of service delivery.
 //!
 
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
 use crate::prelude::badges::Style;
-use serde::{Deserialize, Serialize};
+use crate::structs::stops::BusType;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 /// Represents a NON-BLUE route.
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
@@ -29,22 +34,25 @@ pub struct Regular {
     /// The line number of the route
     pub number: u32,
 
-    /// The name of the route, e.g. where it's going
+    /// The name of the route, e.g. where it's going. Empty, if left out of a short response.
+    #[serde(default)]
     pub name: String,
 
-    /// Who is buying the route
-    #[serde(rename = "customer-type")]
+    /// Who is buying the route. Left out of short responses.
+    #[serde(rename = "customer-type", default)]
     pub customer_type: Customer,
 
-    /// If the route skips specific stops on the way
+    /// If the route skips specific stops on the way. Left out of short responses.
+    #[serde(default)]
     pub coverage: Coverage,
 
     /// What is on the badge of the route
     #[serde(rename = "badge-label")]
     pub badge_label: u32,
 
-    /// How this route's badge should be styled. For more info, see [badges]
-    #[serde(rename = "badge-style")]
+    /// How this route's badge should be styled. For more info, see [badges]. Left out of short
+    /// responses.
+    #[serde(rename = "badge-style", default)]
     pub badge_style: Style,
 
     /// Variants of the current route, e.g. if the route splits up, where it's destination is.
@@ -63,19 +71,21 @@ pub struct Blue {
     /// The line number of the route
     pub number: String,
 
-    /// Who is buying the route
-    #[serde(rename = "customer-type")]
+    /// Who is buying the route. Left out of short responses.
+    #[serde(rename = "customer-type", default)]
     pub customer_type: Customer,
 
-    /// If the route skips specific stops on the way
+    /// If the route skips specific stops on the way. Left out of short responses.
+    #[serde(default)]
     pub coverage: Coverage,
 
     /// What is on the badge of the route
     #[serde(rename = "badge-label")]
     pub badge_label: String,
 
-    /// How this route's badge should be styled. For more info, see [badges]
-    #[serde(rename = "badge-style")]
+    /// How this route's badge should be styled. For more info, see [badges]. Left out of short
+    /// responses.
+    #[serde(rename = "badge-style", default)]
     pub badge_style: Style,
 
     /// Variants of the current route, e.g. if the route splits up, where it's destination is.
@@ -103,6 +113,33 @@ impl Default for Route {
     }
 }
 
+impl Route {
+    /// What is on the badge of the route, e.g. "16" or "BLUE"
+    pub fn badge_label(&self) -> String {
+        match self {
+            Self::Blue(blue) => blue.badge_label.clone(),
+            Self::Regular(regular) => regular.badge_label.to_string(),
+        }
+    }
+
+    /// How the badge of the route should be styled
+    pub fn badge_style(&self) -> &Style {
+        match self {
+            Self::Blue(blue) => &blue.badge_style,
+            Self::Regular(regular) => &regular.badge_style,
+        }
+    }
+
+    /// The name of the route. BLUE routes do not have a name, and short responses leave it out.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::Blue(_) => None,
+            Self::Regular(regular) if regular.name.is_empty() => None,
+            Self::Regular(regular) => Some(&regular.name),
+        }
+    }
+}
+
 /// The type of service provided by this route.
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Customer {
@@ -161,12 +198,154 @@ pub enum Coverage {
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Variant {
     ///  A unique identifier for this variant.
-    pub key: String,
+    pub key: VariantKey,
 
     /// The variant name.
     pub name: Option<String>,
 }
 
+/// The key of a [Variant], like `16-1-K`, `19-0-#` or `BLUE-0-S`: the key of the route, the
+/// direction the variant goes in, and a letter for the pattern of the variant.
+///
+/// Variants created from Google Maps results or GTFS feeds only know their route, so their keys
+/// are just the key of the route, like `16` or `BLUE`, without a direction or pattern.
+///
+/// Keys are ordered by route (numbered routes before BLUE routes), then direction, then pattern.
+/// Keys without a direction come first.
+///
+/// # Example
+///
+/// ```
+/// use transit_api_client::prelude::*;
+///
+/// let key: VariantKey = "16-1-K".parse().unwrap();
+/// assert_eq!(key.route(), &BusType::Regular(16));
+/// assert_eq!(key.direction(), Some(1));
+/// assert_eq!(key.pattern(), Some("K"));
+/// assert_eq!(key.to_string(), "16-1-K");
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct VariantKey {
+    route: BusType,
+    direction: Option<u8>,
+    pattern: Option<String>,
+}
+
+impl VariantKey {
+    /// The key of the route of the variant, e.g. `16` or `BLUE`
+    pub fn route(&self) -> &BusType {
+        &self.route
+    }
+
+    /// The direction the variant goes in, `0` or `1`. Variants of a route with the same
+    /// direction go the same way. [None], if the key only has a route.
+    pub fn direction(&self) -> Option<u8> {
+        self.direction
+    }
+
+    /// The pattern of the variant, usually a single letter like `K`. [None], if the key only has
+    /// a route.
+    pub fn pattern(&self) -> Option<&str> {
+        self.pattern.as_deref()
+    }
+}
+
+impl From<BusType> for VariantKey {
+    /// The key of a variant, of which only the route is known
+    fn from(route: BusType) -> Self {
+        Self {
+            route,
+            direction: None,
+            pattern: None,
+        }
+    }
+}
+
+impl Default for VariantKey {
+    /// The key of route 0, which does not exist
+    fn default() -> Self {
+        BusType::Regular(0).into()
+    }
+}
+
+impl FromStr for VariantKey {
+    type Err = &'static str;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        let mut parts = key.splitn(3, '-');
+        let (route, rest) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(route), None, None) => (route, None),
+            (Some(route), Some(direction), Some(pattern)) => (route, Some((direction, pattern))),
+            _ => return Err("Not of the form `route-direction-pattern` or `route`"),
+        };
+        if route.is_empty() || matches!(rest, Some((_, ""))) {
+            return Err("The route and the pattern of a variant key can not be empty");
+        }
+        let route = match route.parse() {
+            Ok(number) => BusType::Regular(number),
+            Err(_) => BusType::Blue(route.to_string()),
+        };
+        let (direction, pattern) = match rest {
+            Some((direction, pattern)) => {
+                let direction = direction
+                    .parse()
+                    .map_err(|_| "The direction of a variant key is not a number")?;
+                (Some(direction), Some(pattern.to_string()))
+            }
+            None => (None, None),
+        };
+        Ok(Self {
+            route,
+            direction,
+            pattern,
+        })
+    }
+}
+
+impl Display for VariantKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (self.direction, &self.pattern) {
+            (Some(direction), Some(pattern)) => {
+                write!(f, "{}-{direction}-{pattern}", self.route)
+            }
+            _ => write!(f, "{}", self.route),
+        }
+    }
+}
+
+impl Ord for VariantKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let route = match (&self.route, &other.route) {
+            (BusType::Regular(a), BusType::Regular(b)) => a.cmp(b),
+            (BusType::Regular(_), BusType::Blue(_)) => Ordering::Less,
+            (BusType::Blue(_), BusType::Regular(_)) => Ordering::Greater,
+            (BusType::Blue(a), BusType::Blue(b)) => a.cmp(b),
+        };
+        route
+            .then_with(|| self.direction.cmp(&other.direction))
+            .then_with(|| self.pattern.cmp(&other.pattern))
+    }
+}
+
+impl PartialOrd for VariantKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Serialize for VariantKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for VariantKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let key = String::deserialize(deserializer)?;
+        key.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 pub mod badges {
     //! We will now be providing additional branding information for routes:
     //!
@@ -201,3 +380,73 @@ pub mod badges {
         pub class_name: Vec<String>,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::*;
+
+    #[test]
+    fn variant_key() {
+        let key: VariantKey = "19-0-#".parse().unwrap();
+        assert_eq!(key.route(), &BusType::Regular(19));
+        assert_eq!(key.direction(), Some(0));
+        assert_eq!(key.pattern(), Some("#"));
+
+        let key: VariantKey = "BLUE-1-S".parse().unwrap();
+        assert_eq!(key.route(), &BusType::Blue("BLUE".to_string()));
+        assert_eq!(key.to_string(), "BLUE-1-S");
+
+        let key: VariantKey = "16".parse().unwrap();
+        assert_eq!(key.route(), &BusType::Regular(16));
+        assert_eq!(key.direction(), None);
+        assert_eq!(key.pattern(), None);
+        assert_eq!(key.to_string(), "16");
+
+        for invalid in ["", "16-1", "16-x-K", "-0-K", "16-0-"] {
+            assert!(invalid.parse::<VariantKey>().is_err(), "{invalid}");
+        }
+
+        let variant: Variant = serde_json::from_str(r#"{"key": "25-0-U", "name": null}"#).unwrap();
+        assert_eq!(variant.key, "25-0-U".parse().unwrap());
+    }
+
+    #[test]
+    fn variant_key_order() {
+        let mut keys: Vec<VariantKey> = ["BLUE-0-S", "16-1-K", "16-0-D", "2-0-A", "16-1-G", "16"]
+            .iter()
+            .map(|key| key.parse().unwrap())
+            .collect();
+        keys.sort();
+        let keys: Vec<String> = keys.iter().map(VariantKey::to_string).collect();
+        assert_eq!(
+            keys,
+            vec!["2-0-A", "16", "16-0-D", "16-1-G", "16-1-K", "BLUE-0-S"]
+        );
+    }
+
+    #[test]
+    fn variant_key_serde() -> Result<(), crate::structs::Error> {
+        let key: VariantKey = "17-1-G".parse().unwrap();
+        assert_eq!(serde_json::to_string(&key)?, "\"17-1-G\"");
+        assert_eq!(serde_json::from_str::<VariantKey>("\"17-1-G\"")?, key);
+        assert!(serde_json::from_str::<VariantKey>("\"17-1\"").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn short_route() -> Result<(), serde_json::Error> {
+        let route: Route = serde_json::from_str(r#"{"key": 16, "number": 16, "badge-label": 16}"#)?;
+        assert_eq!(route.name(), None);
+        assert_eq!(route.badge_label(), "16");
+        assert_eq!(route.badge_style(), &badges::Style::default());
+
+        let route: Route = serde_json::from_str(
+            r#"{"key": "BLUE", "number": "BLUE", "badge-label": "BLUE", "coverage": "rapid transit"}"#,
+        )?;
+        match route {
+            Route::Blue(blue) => assert_eq!(blue.coverage, Coverage::RapidTransit),
+            Route::Regular(regular) => panic!("{regular:?} is not BLUE"),
+        }
+        Ok(())
+    }
+}
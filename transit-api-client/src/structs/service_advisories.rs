@@ -23,7 +23,7 @@ use time::PrimitiveDateTime;
 use super::{datetime_formatter, UrlParameter};
 
 /// A service advisory containing data about the advisory
-#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 pub struct ServiceAdvisory {
     /// A unique key to identify the advisory
     pub key: u32,
@@ -34,29 +34,17 @@ pub struct ServiceAdvisory {
     /// A title ascribed to the advisory.
     pub title: String,
 
-    /// The content of the advisory.
+    /// The content of the advisory. Empty, if left out of a short response.
+    #[serde(default)]
     pub body: String,
 
     /// Service advisories belong to a category
     pub category: Category,
 
-    /// Timestamp of when the advisory was last updated.
+    /// Timestamp of when the advisory was last updated, if the API sent one.
     #[serde(rename = "updated-at")]
-    #[serde(with = "datetime_formatter")]
-    pub updated_at: PrimitiveDateTime,
-}
-
-impl Default for ServiceAdvisory {
-    fn default() -> Self {
-        Self {
-            key: Default::default(),
-            priority: Default::default(),
-            title: Default::default(),
-            body: Default::default(),
-            category: Default::default(),
-            updated_at: crate::UNIX_EPOCH,
-        }
-    }
+    #[serde(default, with = "datetime_formatter::option")]
+    pub updated_at: Option<PrimitiveDateTime>,
 }
 
 /// A numerical indicator of how urgent the advisory is. The lower the number, the more urgent it is
@@ -18,7 +18,7 @@
 
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
-use time::PrimitiveDateTime;
+use time::{Duration, PrimitiveDateTime};
 
 use super::{
     common::{GeoLocation, Street},
@@ -32,7 +32,8 @@ pub struct Stop {
     /// A unique identifier for this stop.
     pub key: u32,
 
-    /// The stop name
+    /// The stop name, e.g. "Northbound Osborne at Glasgow" or "NB Osborne@Glasgow" in short
+    /// responses
     pub name: String,
 
     /// The stop number
@@ -45,7 +46,8 @@ pub struct Stop {
     /// Specifies which direction buses which service the stop are heading.
     pub direction: Direction,
 
-    /// Specifies which side of the intersection the stop lies on.
+    /// Specifies which side of the intersection the stop lies on. Left out of short responses.
+    #[serde(default)]
     pub side: Side,
 
     /// The street on which the stop is located
@@ -58,7 +60,7 @@ pub struct Stop {
     /// A geographical point describing where the stop is located.
     pub centre: GeoLocation,
 
-    /// The internal name use by the API
+    /// The internal name use by the API. Only in long responses.
     #[serde(rename = "internal-name")]
     pub internal_name: Option<String>,
 
@@ -114,16 +116,20 @@ impl Eq for Distances {}
 /// Specifies which direction buses which service the stop are heading.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Direction {
-    /// The bus is going North
+    /// The bus is going North (NB)
+    #[serde(alias = "NB")]
     Northbound,
 
-    /// The bus is going East
+    /// The bus is going East (EB)
+    #[serde(alias = "EB")]
     Eastbound,
 
-    /// The bus is going South
+    /// The bus is going South (SB)
+    #[serde(alias = "SB")]
     Southbound,
 
-    /// The bus is going West
+    /// The bus is going West (WB)
+    #[serde(alias = "WB")]
     Westbound,
 }
 
@@ -147,29 +153,31 @@ pub enum Side {
     /// The stop is directly on the opposite side
     ///
     /// **Example**: `10168`
-    #[serde(rename = "Direct Opposite")]
+    #[serde(rename = "Direct Opposite", alias = "DO")]
     DirectOpposite,
 
     /// The stop is on the far side
     ///
     /// **Example**: `10095`
+    #[serde(alias = "FS")]
     Farside,
 
     /// The stop is on the far- and opposite side of the street
     ///
     /// **Example**: `10081`
-    #[serde(rename = "Farside Opposite")]
+    #[serde(rename = "Farside Opposite", alias = "FO")]
     FarsideOpposite,
 
     /// The stop is on the nearside of the street
     ///
     /// **Example**: `10076`
+    #[serde(alias = "NS")]
     Nearside,
 
     /// The stop is on the near- and opposite side of the street
     ///
     /// **Example**: `10077`
-    #[serde(rename = "Nearside Opposite")]
+    #[serde(rename = "Nearside Opposite", alias = "NO")]
     NearsideOpposite,
 
     /// No side of the street available for this stop
@@ -235,15 +243,141 @@ pub struct ScheduledStop {
     pub bus: Option<Bus>,
 }
 
-/// Information about the arrival and departure times
+impl ScheduledStop {
+    /// How much later than scheduled the bus is estimated to depart. Negative, if it is
+    /// estimated to depart early. Uses the arrival, if there is no departure time.
+    ///
+    /// returns: Option<Duration>
+    pub fn delay(&self) -> Option<Duration> {
+        self.times.time()?.delay()
+    }
+
+    /// Whether the bus is early, on time, late, cancelled or has already departed.
+    ///
+    /// # Arguments
+    ///
+    /// * `now`: The current time, in the time zone of the schedule
+    /// * `thresholds`: When a bus counts as early or late
+    ///
+    /// returns: DepartureStatus
+    pub fn status(&self, now: PrimitiveDateTime, thresholds: &Thresholds) -> DepartureStatus {
+        if self.cancelled {
+            return DepartureStatus::Cancelled;
+        }
+        if matches!(self.times.best(), Some(departure) if departure < now) {
+            return DepartureStatus::Departed;
+        }
+        match self.delay() {
+            Some(delay) if thresholds.is_early(delay) => DepartureStatus::Early,
+            Some(delay) if thresholds.is_late(delay) => DepartureStatus::Late,
+            _ => DepartureStatus::OnTime,
+        }
+    }
+
+    /// In how many whole minutes the bus is estimated to depart. Negative, if it has already
+    /// departed.
+    ///
+    /// # Arguments
+    ///
+    /// * `now`: The current time, in the time zone of the schedule
+    ///
+    /// returns: Option<i64>
+    pub fn minutes_until_departure(&self, now: PrimitiveDateTime) -> Option<i64> {
+        Some((self.times.best()? - now).whole_minutes())
+    }
+}
+
+/// When a departure is early or late
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Thresholds {
+    /// A bus that departs more than this before its scheduled time is early
+    pub early: Duration,
+
+    /// A bus that departs more than this after its scheduled time is late
+    pub late: Duration,
+}
+
+impl Default for Thresholds {
+    /// One minute early to three minutes late is on time
+    fn default() -> Self {
+        Self {
+            early: Duration::minutes(1),
+            late: Duration::minutes(3),
+        }
+    }
+}
+
+impl Thresholds {
+    /// Whether a departure with this delay is early
+    pub fn is_early(&self, delay: Duration) -> bool {
+        delay < -self.early
+    }
+
+    /// Whether a departure with this delay is late
+    pub fn is_late(&self, delay: Duration) -> bool {
+        delay > self.late
+    }
+}
+
+/// How a scheduled stop keeps to its schedule, see [ScheduledStop::status]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum DepartureStatus {
+    /// The bus departs earlier than scheduled
+    Early,
+
+    /// The bus departs on time, or there is no estimate
+    OnTime,
+
+    /// The bus departs later than scheduled
+    Late,
+
+    /// The scheduled stop has been cancelled
+    Cancelled,
+
+    /// The bus has already departed
+    Departed,
+}
+
+impl Display for DepartureStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Early => write!(f, "Early"),
+            Self::OnTime => write!(f, "On time"),
+            Self::Late => write!(f, "Late"),
+            Self::Cancelled => write!(f, "Cancelled"),
+            Self::Departed => write!(f, "Departed"),
+        }
+    }
+}
+
+/// Information about the arrival and departure times. The first stop of a trip has no arrival,
+/// and the last stop has no departure.
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct ScheduledTimes {
     /// Times of when the bus is scheduled and estimated to arrive
-    pub arrival: Time,
+    pub arrival: Option<Time>,
 
     /// Times of when the bus is scheduled and estimated to depart
-    pub departure: Time,
+    pub departure: Option<Time>,
+}
+
+impl ScheduledTimes {
+    /// The departure, or the arrival if the bus does not depart from the stop
+    pub fn time(&self) -> Option<&Time> {
+        self.departure.as_ref().or(self.arrival.as_ref())
+    }
+
+    /// When the bus is scheduled to depart, or to arrive if it does not depart from the stop
+    pub fn scheduled(&self) -> Option<PrimitiveDateTime> {
+        Some(self.time()?.scheduled)
+    }
+
+    /// The best available time of the bus at the stop: the estimated departure, or the scheduled
+    /// one if there is no estimate. Uses the arrival, if the bus does not depart from the stop.
+    pub fn best(&self) -> Option<PrimitiveDateTime> {
+        Some(self.time()?.best())
+    }
 }
 
 /// Holds scheduled and estimated times for departure or arrival
@@ -253,17 +387,23 @@ pub struct Time {
     #[serde(with = "datetime_formatter")]
     pub scheduled: PrimitiveDateTime,
 
-    /// When the bus is estimated
-    #[serde(with = "datetime_formatter")]
-    pub estimated: PrimitiveDateTime,
+    /// When the bus is estimated. Usually missing for past and future dates.
+    #[serde(default, with = "datetime_formatter::option")]
+    pub estimated: Option<PrimitiveDateTime>,
 }
 
-impl Default for Time {
-    fn default() -> Self {
-        Self {
-            scheduled: crate::UNIX_EPOCH,
-            estimated: crate::UNIX_EPOCH,
-        }
+impl Time {
+    /// How much later than scheduled the bus is estimated. Negative, if it is estimated to be
+    /// early. `None`, if there is no estimate.
+    ///
+    /// returns: Option<Duration>
+    pub fn delay(&self) -> Option<Duration> {
+        Some(self.estimated? - self.scheduled)
+    }
+
+    /// The estimated time, or the scheduled time if there is no estimate
+    pub fn best(&self) -> PrimitiveDateTime {
+        self.estimated.unwrap_or(self.scheduled)
     }
 }
 
@@ -297,19 +437,20 @@ pub struct FoxxRoute {
     /// The bus name
     pub name: Option<String>,
 
-    /// The customer's the bus services
-    #[serde(rename = "customer-type")]
+    /// The customer's the bus services. Left out of short responses.
+    #[serde(rename = "customer-type", default)]
     pub customer_type: CustomerType,
 
-    /// The bus coverage
+    /// The bus coverage. Left out of short responses.
+    #[serde(default)]
     pub coverage: Coverage,
 
     /// The Badge Label
     #[serde(rename = "badge-label")]
     pub badge_label: BusType,
 
-    /// The Badge Style
-    #[serde(rename = "badge-style")]
+    /// The Badge Style. Left out of short responses.
+    #[serde(rename = "badge-style", default)]
     pub badge_style: BadgeStyle,
 
     /// The bus variants
@@ -317,7 +458,7 @@ pub struct FoxxRoute {
 }
 
 /// The bus type
-#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum BusType {
     /// The bus is a regular bus
@@ -341,9 +482,10 @@ impl Display for BusType {
 }
 
 /// The customer's the bus services
-#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 pub enum CustomerType {
     /// The bus is a regular bus
+    #[default]
     #[serde(rename = "regular")]
     Regular,
 
@@ -370,7 +512,7 @@ pub enum CustomerType {
 }
 
 /// Styling for the badge
-#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 pub struct BadgeStyle {
     /// Classes for the badge
     #[serde(rename = "class-names")]
@@ -389,7 +531,7 @@ pub struct BadgeStyle {
 }
 
 /// A Struct just holding the field class_name
-#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 pub struct ClassNamesHolder {
     /// The class name
     #[serde(rename = "class-name")]
@@ -440,3 +582,306 @@ impl Display for FoxxClassNames {
         )
     }
 }
+
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::prelude::*;
+    use crate::timezone;
+    use time::macros::datetime;
+    use time::{Duration, PrimitiveDateTime};
+
+    /// Stop 10064 Northbound Osborne at Glasgow, as returned by the API
+    pub(crate) const STOP: &str = r#"{
+        "key": 10064, "name": "Northbound Osborne at Glasgow", "number": 10064,
+        "direction": "Northbound", "side": "Nearside",
+        "street": {"key": 2715, "name": "Osborne Street", "type": "Street"},
+        "cross-street": {"key": 1486, "name": "Glasgow Avenue", "type": "Avenue"},
+        "centre": {"geographic": {"latitude": "49.86912", "longitude": "-97.1375"}}
+    }"#;
+
+    /// Route 16 Selkirk-Osborne, as returned by the API. Can be read as a [Route] or a
+    /// [FoxxRoute].
+    pub(crate) const ROUTE: &str = r##"{
+        "key": 16, "number": 16, "name": "Route 16 Selkirk-Osborne",
+        "customer-type": "regular", "coverage": "regular", "badge-label": 16,
+        "badge-style": {
+            "class-names": {"class-name": ["badge-label", "regular"]},
+            "background-color": "#ffffff", "border-color": "#d9d9d9", "color": "#000000"
+        }
+    }"##;
+
+    /// The schedule of stop 10064, where route 16 passes at the scheduled stops
+    pub(crate) fn schedule(scheduled_stops: Vec<ScheduledStop>) -> Schedule {
+        Schedule {
+            stop: serde_json::from_str(STOP).unwrap(),
+            route_schedules: vec![RouteSchedule {
+                route: serde_json::from_str(ROUTE).unwrap(),
+                scheduled_stops,
+            }],
+        }
+    }
+
+    /// A bus of route 16 departing from the stop, without an arrival or a known bus
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the scheduled stop
+    /// * `variant`: The key of the variant, "16-1-K" and "16-0-D" get their real names
+    /// * `scheduled`: When the bus is scheduled to depart
+    /// * `estimated`: When the bus is estimated to depart
+    pub(crate) fn departure(
+        key: &str,
+        variant: &str,
+        scheduled: PrimitiveDateTime,
+        estimated: PrimitiveDateTime,
+    ) -> ScheduledStop {
+        let name = match variant {
+            "16-1-K" => "Selkirk-Osborne to Kingston Row",
+            "16-0-D" => "Selkirk-Osborne to Downtown, via Portage & Main",
+            _ => variant,
+        };
+        ScheduledStop {
+            key: key.to_string(),
+            cancelled: false,
+            times: ScheduledTimes {
+                arrival: None,
+                departure: Some(Time {
+                    scheduled,
+                    estimated: Some(estimated),
+                }),
+            },
+            variant: Variant {
+                key: variant.parse().unwrap(),
+                name: Some(name.to_string()),
+            },
+            bus: None,
+        }
+    }
+
+    fn scheduled_stop(
+        scheduled: PrimitiveDateTime,
+        estimated: Option<PrimitiveDateTime>,
+    ) -> ScheduledStop {
+        ScheduledStop {
+            key: "1".to_string(),
+            cancelled: false,
+            times: ScheduledTimes {
+                arrival: None,
+                departure: Some(Time {
+                    scheduled,
+                    estimated,
+                }),
+            },
+            variant: Default::default(),
+            bus: None,
+        }
+    }
+
+    #[test]
+    fn first_and_last_stop() -> Result<(), serde_json::Error> {
+        let first: ScheduledTimes = serde_json::from_str(
+            r#"{"departure": {"scheduled": "2023-06-01T07:00:00", "estimated": "2023-06-01T07:02:00"}}"#,
+        )?;
+        assert_eq!(first.arrival, None);
+        assert_eq!(first.scheduled(), Some(datetime!(2023-06-01 7:00)));
+        assert_eq!(first.best(), Some(datetime!(2023-06-01 7:02)));
+
+        let last: ScheduledTimes =
+            serde_json::from_str(r#"{"arrival": {"scheduled": "2023-06-01T07:30:00"}}"#)?;
+        assert_eq!(last.departure, None);
+        assert_eq!(
+            last.arrival,
+            Some(Time {
+                scheduled: datetime!(2023-06-01 7:30),
+                estimated: None,
+            })
+        );
+        assert_eq!(last.scheduled(), Some(datetime!(2023-06-01 7:30)));
+        assert_eq!(last.best(), Some(datetime!(2023-06-01 7:30)));
+
+        let neither: ScheduledTimes = serde_json::from_str("{}")?;
+        assert_eq!(neither, ScheduledTimes::default());
+        assert_eq!(neither.time(), None);
+        assert_eq!(neither.best(), None);
+
+        // Missing times are not replaced by a sentinel, when serialized again
+        let json = serde_json::to_value(&last)?;
+        assert_eq!(json["departure"], serde_json::Value::Null);
+        assert_eq!(json["arrival"]["estimated"], serde_json::Value::Null);
+        assert_eq!(serde_json::from_value::<ScheduledTimes>(json)?, last);
+        Ok(())
+    }
+
+    #[test]
+    fn delay() {
+        let late = scheduled_stop(
+            datetime!(2023-06-01 7:00),
+            Some(datetime!(2023-06-01 7:04:30)),
+        );
+        assert_eq!(late.delay(), Some(Duration::seconds(270)));
+
+        let early = scheduled_stop(datetime!(2023-06-01 7:00), Some(datetime!(2023-06-01 6:58)));
+        assert_eq!(early.delay(), Some(Duration::minutes(-2)));
+
+        let unknown = scheduled_stop(datetime!(2023-06-01 7:00), None);
+        assert_eq!(unknown.delay(), None);
+
+        let mut arrival_only = scheduled_stop(datetime!(2023-06-01 7:00), None);
+        arrival_only.times = ScheduledTimes {
+            arrival: Some(Time {
+                scheduled: datetime!(2023-06-01 7:00),
+                estimated: Some(datetime!(2023-06-01 7:01)),
+            }),
+            departure: None,
+        };
+        assert_eq!(arrival_only.delay(), Some(Duration::minutes(1)));
+    }
+
+    #[test]
+    fn status() {
+        let now = datetime!(2023-06-01 6:50);
+        let thresholds = Thresholds::default();
+        let status = |estimated| {
+            scheduled_stop(datetime!(2023-06-01 7:00), Some(estimated)).status(now, &thresholds)
+        };
+
+        // A second late is not late
+        assert_eq!(
+            status(datetime!(2023-06-01 7:00:01)),
+            DepartureStatus::OnTime
+        );
+        assert_eq!(status(datetime!(2023-06-01 7:03)), DepartureStatus::OnTime);
+        assert_eq!(status(datetime!(2023-06-01 7:03:01)), DepartureStatus::Late);
+        assert_eq!(status(datetime!(2023-06-01 6:59)), DepartureStatus::OnTime);
+        assert_eq!(
+            status(datetime!(2023-06-01 6:58:59)),
+            DepartureStatus::Early
+        );
+        assert_eq!(
+            status(datetime!(2023-06-01 6:49)),
+            DepartureStatus::Departed
+        );
+        assert_eq!(
+            scheduled_stop(datetime!(2023-06-01 7:00), None).status(now, &thresholds),
+            DepartureStatus::OnTime
+        );
+
+        let strict = Thresholds {
+            early: Duration::ZERO,
+            late: Duration::ZERO,
+        };
+        let stop = scheduled_stop(
+            datetime!(2023-06-01 7:00),
+            Some(datetime!(2023-06-01 7:00:01)),
+        );
+        assert_eq!(stop.status(now, &strict), DepartureStatus::Late);
+
+        let mut cancelled = stop;
+        cancelled.cancelled = true;
+        assert_eq!(cancelled.status(now, &strict), DepartureStatus::Cancelled);
+    }
+
+    #[test]
+    fn status_in_winter() {
+        // 13:00 UTC is 7:00 in Winnipeg in January, not 8:00 as in summer
+        let now = timezone::local(datetime!(2024-01-10 13:00 UTC));
+        let stop = scheduled_stop(datetime!(2024-01-10 7:30), Some(datetime!(2024-01-10 7:30)));
+        assert_eq!(
+            stop.status(now, &Thresholds::default()),
+            DepartureStatus::OnTime
+        );
+        assert_eq!(stop.minutes_until_departure(now), Some(30));
+
+        let stop = scheduled_stop(datetime!(2024-01-10 7:00), Some(datetime!(2024-01-10 6:59)));
+        assert_eq!(
+            stop.status(now, &Thresholds::default()),
+            DepartureStatus::Departed
+        );
+    }
+
+    #[test]
+    fn minutes_until_departure() {
+        let stop = scheduled_stop(
+            datetime!(2023-06-01 7:00),
+            Some(datetime!(2023-06-01 7:02:30)),
+        );
+        assert_eq!(
+            stop.minutes_until_departure(datetime!(2023-06-01 6:50)),
+            Some(12)
+        );
+        assert_eq!(
+            stop.minutes_until_departure(datetime!(2023-06-01 7:02)),
+            Some(0)
+        );
+        assert_eq!(
+            stop.minutes_until_departure(datetime!(2023-06-01 7:05)),
+            Some(-2)
+        );
+
+        let unestimated = scheduled_stop(datetime!(2023-06-01 7:00), None);
+        assert_eq!(
+            unestimated.minutes_until_departure(datetime!(2023-06-01 6:50)),
+            Some(10)
+        );
+        let mut unknown = unestimated;
+        unknown.times = ScheduledTimes::default();
+        assert_eq!(
+            unknown.minutes_until_departure(datetime!(2023-06-01 6:50)),
+            None
+        );
+    }
+
+    #[test]
+    fn short_and_long_stop() -> Result<(), serde_json::Error> {
+        let short: Stop = serde_json::from_str(
+            r#"{
+                "key": 10064, "name": "NB Osborne@Glasgow", "number": 10064, "direction": "NB",
+                "street": {"key": 2715, "name": "Osborne St", "type": "St"},
+                "cross-street": {"key": 1486, "name": "Glasgow Av", "type": "Av", "leg": "W"},
+                "centre": {"geographic": {"latitude": "49.86912", "longitude": "-97.1375"}}
+            }"#,
+        )?;
+        assert_eq!(short.direction, Direction::Northbound);
+        assert_eq!(short.side, Side::NA);
+        assert_eq!(short.street.street_type, Some("St".to_string()));
+        assert_eq!(short.cross_street.leg, Some(StreetLeg::West));
+
+        let long: Stop = serde_json::from_str(
+            r#"{
+                "key": 10064, "name": "Northbound Osborne Street at Glasgow Avenue",
+                "number": 10064, "direction": "Northbound", "side": "Nearside",
+                "street": {"key": 2715, "name": "Osborne Street", "type": "Street"},
+                "cross-street": {"key": 1486, "name": "Glasgow Avenue", "type": "Avenue"},
+                "centre": {
+                    "utm": {"zone": "14U", "x": 633838, "y": 5525742},
+                    "geographic": {"latitude": "49.86912", "longitude": "-97.1375"}
+                },
+                "internal-name": "OsborneGlasgowNB", "sequence-on-street": 12,
+                "icon-style": "blue"
+            }"#,
+        )?;
+        assert_eq!(long.side, Side::Nearside);
+        assert_eq!(long.internal_name, Some("OsborneGlasgowNB".to_string()));
+        assert_eq!(long.centre, short.centre);
+        Ok(())
+    }
+
+    #[test]
+    fn short_route_schedule() -> Result<(), serde_json::Error> {
+        let schedule: RouteSchedule = serde_json::from_str(
+            r#"{
+                "route": {"key": 16, "number": 16, "badge-label": 16},
+                "scheduled-stops": [{
+                    "key": "1", "cancelled": "false",
+                    "times": {"departure": {"scheduled": "2023-06-01T07:00:00"}},
+                    "variant": {"key": "16-1-K"}
+                }]
+            }"#,
+        )?;
+        assert_eq!(schedule.route.name, None);
+        assert_eq!(schedule.route.coverage, Coverage::default());
+        assert_eq!(schedule.route.badge_style, BadgeStyle::default());
+        assert_eq!(schedule.scheduled_stops[0].variant.name, None);
+        Ok(())
+    }
+}
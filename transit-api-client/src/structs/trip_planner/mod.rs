@@ -52,8 +52,8 @@ impl From<DirectionsLeg> for Plan {
             .unwrap()
             .to_offset(offset!(-5));
         let mut times = Times {
-            start: PrimitiveDateTime::new(start_time.date(), start_time.time()),
-            end: PrimitiveDateTime::new(end_time.date(), end_time.time()),
+            start: Some(PrimitiveDateTime::new(start_time.date(), start_time.time())),
+            end: Some(PrimitiveDateTime::new(end_time.date(), end_time.time())),
             durations: Durations::default(),
         };
         let segments: Vec<Segment> = leg.steps.into_iter().map(|step| step.into()).collect();
@@ -78,30 +78,20 @@ impl From<DirectionsLeg> for Plan {
 
 /// Time information about the [Plan]/[Segment]: when it starts/ends and how much time is
 /// spent with what.
-#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Times {
     /// When the ride/walk of the plan/segment starts
-    #[serde(with = "datetime_formatter")]
-    pub start: PrimitiveDateTime,
+    #[serde(default, with = "datetime_formatter::option")]
+    pub start: Option<PrimitiveDateTime>,
 
     /// When the ride/walk of the plan/segment end
-    #[serde(with = "datetime_formatter")]
-    pub end: PrimitiveDateTime,
+    #[serde(default, with = "datetime_formatter::option")]
+    pub end: Option<PrimitiveDateTime>,
 
     /// How much time is spent on different transport options (walking, riding, waiting, total time)
     pub durations: Durations,
 }
 
-impl Default for Times {
-    fn default() -> Self {
-        Self {
-            start: crate::UNIX_EPOCH,
-            end: crate::UNIX_EPOCH,
-            durations: Default::default(),
-        }
-    }
-}
-
 /// Times for how long is spent riding/walking/waiting and total
 #[derive(Clone, Default, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Durations {
@@ -132,6 +122,69 @@ pub struct Bounds {
     pub minimum: GeoLocation,
 }
 
+impl Bounds {
+    /// The smallest bounds containing all the points. Returns [None], if there are no points.
+    ///
+    /// # Arguments
+    ///
+    /// * `points`: The points to enclose
+    ///
+    /// returns: Option<Bounds>
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a GeoLocation>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        let mut bounds = Bounds {
+            maximum: first.clone(),
+            minimum: first.clone(),
+        };
+        for point in points {
+            bounds.extend(point);
+        }
+        Some(bounds)
+    }
+
+    /// Grows the bounds, so they contain the point.
+    pub fn extend(&mut self, point: &GeoLocation) {
+        self.maximum.latitude = self.maximum.latitude.max(point.latitude);
+        self.maximum.longitude = self.maximum.longitude.max(point.longitude);
+        self.minimum.latitude = self.minimum.latitude.min(point.latitude);
+        self.minimum.longitude = self.minimum.longitude.min(point.longitude);
+    }
+
+    /// Whether the point lies within the bounds, including their edges.
+    pub fn contains(&self, point: &GeoLocation) -> bool {
+        (self.minimum.latitude..=self.maximum.latitude).contains(&point.latitude)
+            && (self.minimum.longitude..=self.maximum.longitude).contains(&point.longitude)
+    }
+
+    /// Whether the bounds share at least one point with other bounds.
+    pub fn intersects(&self, other: &Bounds) -> bool {
+        self.minimum.latitude <= other.maximum.latitude
+            && other.minimum.latitude <= self.maximum.latitude
+            && self.minimum.longitude <= other.maximum.longitude
+            && other.minimum.longitude <= self.maximum.longitude
+    }
+
+    /// The point in the middle of the bounds.
+    pub fn centre(&self) -> GeoLocation {
+        GeoLocation::new(
+            (self.minimum.latitude + self.maximum.latitude) / 2.0,
+            (self.minimum.longitude + self.maximum.longitude) / 2.0,
+        )
+    }
+
+    /// The corners of the bounds, counter-clockwise starting at the minimum. Can be used as a
+    /// polygon for [GeoLocation::is_in_polygon].
+    pub fn corners(&self) -> [GeoLocation; 4] {
+        [
+            self.minimum.clone(),
+            GeoLocation::new(self.minimum.latitude, self.maximum.longitude),
+            self.maximum.clone(),
+            GeoLocation::new(self.maximum.latitude, self.minimum.longitude),
+        ]
+    }
+}
+
 /// Differentiate between stops at the origin, a stop, or the end of the trip
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum TripStop {
@@ -191,6 +244,24 @@ impl Default for TripStop {
     }
 }
 
+impl TripStop {
+    /// A name of the stop or location, that can be shown to people
+    pub fn name(&self) -> String {
+        match self {
+            Self::Origin(location) | Self::Destination(location) => location.name(),
+            Self::Stop(stop) => stop.name.clone(),
+        }
+    }
+
+    /// Where the stop or location is
+    pub fn centre(&self) -> &GeoLocation {
+        match self {
+            Self::Origin(location) | Self::Destination(location) => location.centre(),
+            Self::Stop(stop) => &stop.centre,
+        }
+    }
+}
+
 /// A representation of [Location](crate::structs::common::Location), that is serialized and
 /// deserialized as an untagged enum.
 /// It represents a position or a point on the map that is significant or by address.
@@ -217,6 +288,34 @@ pub enum Location {
     Stop(Stop),
 }
 
+impl Location {
+    /// A name of the location, that can be shown to people, e.g. "1 Portage Avenue",
+    /// "The Forks" or "Portage Avenue & Main Street"
+    pub fn name(&self) -> String {
+        match self {
+            Self::Address(address) => format!("{} {}", address.street_number, address.street.name),
+            Self::Monument(monument) => monument.name.clone(),
+            Self::Intersection(intersection) => format!(
+                "{} & {}",
+                intersection.street.name, intersection.cross_street.name
+            ),
+            Self::Point(point) => format!("{}, {}", point.latitude, point.longitude),
+            Self::Stop(stop) => stop.name.clone(),
+        }
+    }
+
+    /// Where the location is
+    pub fn centre(&self) -> &GeoLocation {
+        match self {
+            Self::Address(address) => &address.centre,
+            Self::Monument(monument) => &monument.address.centre,
+            Self::Intersection(intersection) => &intersection.centre,
+            Self::Point(point) => point,
+            Self::Stop(stop) => &stop.centre,
+        }
+    }
+}
+
 /// Basic information about a stop on the Trip.
 #[derive(Clone, Default, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Stop {
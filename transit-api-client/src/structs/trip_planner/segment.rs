@@ -21,7 +21,7 @@ use crate::prelude::{Blue, Regular};
 use crate::structs::trip_planner::Durations;
 use crate::structs::{
     routes::{Route, Variant},
-    stops::Bus,
+    stops::{Bus, BusType},
     trip_planner::{Bounds, Times, TripStop},
 };
 use google_maps_api_client::{DirectionsStep, TravelMode};
@@ -46,6 +46,26 @@ pub enum Segment {
     Transfer(Transfer),
 }
 
+impl Segment {
+    /// When the segment starts and ends
+    pub fn times(&self) -> &Times {
+        match self {
+            Self::Walk(walk) => &walk.times,
+            Self::Ride(ride) => &ride.times,
+            Self::Transfer(transfer) => &transfer.times,
+        }
+    }
+
+    /// The geographic boundaries of the segment, if they are known
+    pub fn bounds(&self) -> Option<&Bounds> {
+        match self {
+            Self::Walk(walk) => walk.bounds.as_ref(),
+            Self::Ride(ride) => ride.bounds.as_ref(),
+            Self::Transfer(transfer) => transfer.bounds.as_ref(),
+        }
+    }
+}
+
 impl From<DirectionsStep> for Segment {
     fn from(step: DirectionsStep) -> Self {
         match step.travel_mode {
@@ -96,7 +116,7 @@ impl From<DirectionsStep> for Segment {
                                     border_color: line.text_color.expect("Border colour not given!"),
                                 },
                                 variants: Some(vec![Variant {
-                                    key: "BLUE".to_string(),
+                                    key: BusType::Blue("BLUE".to_string()).into(),
                                     name: Some(format!("BLUE to {}", transit_details.headsign.expect("Could not get the headsign of the line! Is the key wrong?"))),
                                 }]),
                                 ..Default::default()
@@ -116,7 +136,7 @@ impl From<DirectionsStep> for Segment {
                                     border_color: line.text_color.expect("Border colour not given!"),
                                 },
                                 variants: Some(vec![Variant {
-                                    key: short_name.to_string(),
+                                    key: BusType::Regular(short_name).into(),
                                     name: Some(format!("{} to {}", short_name, transit_details.headsign.expect("Could not get the headsign of the line! Is the key wrong?"))),
                                 }]),
                                 ..Default::default()
@@ -124,8 +144,8 @@ impl From<DirectionsStep> for Segment {
                         }
                     },
                     times: Times {
-                        start: PrimitiveDateTime::new(start_time.date(), start_time.time()),
-                        end: PrimitiveDateTime::new(end_time.date(), end_time.time()),
+                        start: Some(PrimitiveDateTime::new(start_time.date(), start_time.time())),
+                        end: Some(PrimitiveDateTime::new(end_time.date(), end_time.time())),
                         durations: Durations {
                             riding: (step.duration.value / 60) as u32,
                             total: (step.duration.value / 60) as u32,
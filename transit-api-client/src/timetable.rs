@@ -0,0 +1,306 @@
+// TransitTrail - Navigate Winnipeg Transit with a different style
+// Copyright (C) - 2023 Foxx Azalea Pinkerton, Max Fehlinger
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Timetables of a stop for a whole day.
+//!
+//! The API only returns the schedule of a stop for a short window of time, two hours by default.
+//! A [Timetable] stitches the schedules of several windows together, and lays them out like a
+//! printed timetable: by route, then by hour, with the variant of every departure.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use time::macros::date;
+//! use transit_api_client::prelude::*;
+//!
+//! # tokio_test::block_on(async {
+//! let client = TransitClient::new("<YOUR_API_TOKEN>".to_string());
+//! let timetable = client.stop_timetable(10064, date!(2023-06-01), Usage::Normal).await.unwrap();
+//! for route in timetable.routes() {
+//!     for hour in &route.hours {
+//!         let minutes: Vec<u8> = hour.departures.iter().map(|entry| entry.minute()).collect();
+//!         println!("{} {:02}: {minutes:?}", route.route.key, hour.hour);
+//!     }
+//! }
+//! # });
+//! ```
+//!
+
+use std::collections::{HashMap, HashSet};
+
+use time::{Date, PrimitiveDateTime};
+
+use crate::structs::{
+    routes::{Variant, VariantKey},
+    stops::{FoxxRoute, RouteSchedule, Schedule, ScheduledStop},
+};
+
+/// The scheduled stops of a stop for a whole day
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Timetable {
+    /// The day of the timetable
+    pub date: Date,
+
+    /// The schedule of the whole day. Every scheduled stop appears once, and is ordered by its
+    /// scheduled time.
+    pub schedule: Schedule,
+}
+
+impl Timetable {
+    /// Stitches the schedules of a stop together. Scheduled stops that appear in more than one
+    /// schedule are only kept once, and scheduled stops that are not scheduled on `date` are
+    /// left out.
+    ///
+    /// # Arguments
+    ///
+    /// * `date`: The day of the timetable
+    /// * `schedules`: The schedules of the same stop, for windows during the day
+    ///
+    /// returns: Option<Timetable> [None], if there are no schedules
+    pub fn stitch(date: Date, schedules: impl IntoIterator<Item = Schedule>) -> Option<Self> {
+        let mut schedules = schedules.into_iter();
+        let mut stitched = schedules.next()?;
+        let mut seen = HashSet::new();
+
+        let mut route_schedules = Vec::new();
+        for route_schedule in stitched
+            .route_schedules
+            .drain(..)
+            .chain(schedules.flat_map(|schedule| schedule.route_schedules))
+        {
+            let position = route_schedules.iter().position(|existing: &RouteSchedule| {
+                existing.route.key == route_schedule.route.key
+            });
+            let target = match position {
+                Some(position) => &mut route_schedules[position],
+                None => {
+                    route_schedules.push(RouteSchedule {
+                        route: route_schedule.route,
+                        scheduled_stops: Vec::new(),
+                    });
+                    route_schedules.last_mut().unwrap()
+                }
+            };
+            for scheduled_stop in route_schedule.scheduled_stops {
+                if matches!(scheduled_stop.times.scheduled(), Some(time) if time.date() == date)
+                    && seen.insert(scheduled_stop.key.clone())
+                {
+                    target.scheduled_stops.push(scheduled_stop);
+                }
+            }
+        }
+
+        for route_schedule in &mut route_schedules {
+            route_schedule
+                .scheduled_stops
+                .sort_by_key(|scheduled_stop| scheduled_stop.times.scheduled());
+        }
+        route_schedules.retain(|route_schedule| !route_schedule.scheduled_stops.is_empty());
+        stitched.route_schedules = route_schedules;
+
+        Some(Self {
+            date,
+            schedule: stitched,
+        })
+    }
+
+    /// Lays out the timetable like a printed one, with one table per route.
+    ///
+    /// returns: Vec<RouteTimetable> In the order the routes appear in the schedule
+    pub fn routes(&self) -> Vec<RouteTimetable<'_>> {
+        self.schedule
+            .route_schedules
+            .iter()
+            .map(|route_schedule| {
+                let mut counts: HashMap<&VariantKey, usize> = HashMap::new();
+                let mut variants: Vec<&Variant> = Vec::new();
+                for scheduled_stop in &route_schedule.scheduled_stops {
+                    let count = counts.entry(&scheduled_stop.variant.key).or_default();
+                    if *count == 0 {
+                        variants.push(&scheduled_stop.variant);
+                    }
+                    *count += 1;
+                }
+                variants.sort_by(|a, b| {
+                    counts[&b.key]
+                        .cmp(&counts[&a.key])
+                        .then_with(|| a.key.cmp(&b.key))
+                });
+
+                let mut hours: Vec<Hour> = Vec::new();
+                for scheduled_stop in &route_schedule.scheduled_stops {
+                    // Stitched timetables only contain scheduled stops with a time
+                    let time = match scheduled_stop.times.scheduled() {
+                        Some(time) => time,
+                        None => continue,
+                    };
+                    let entry = Entry {
+                        time,
+                        variant: variants
+                            .iter()
+                            .position(|variant| variant.key == scheduled_stop.variant.key)
+                            .unwrap(),
+                        scheduled_stop,
+                    };
+                    match hours.last_mut() {
+                        Some(hour) if hour.hour == time.hour() => hour.departures.push(entry),
+                        _ => hours.push(Hour {
+                            hour: time.hour(),
+                            departures: vec![entry],
+                        }),
+                    }
+                }
+
+                RouteTimetable {
+                    route: &route_schedule.route,
+                    variants,
+                    hours,
+                }
+            })
+            .collect()
+    }
+}
+
+/// The timetable of a single route at a stop
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RouteTimetable<'a> {
+    /// The route
+    pub route: &'a FoxxRoute,
+
+    /// The variants of the route that stop here, the one with the most departures first
+    pub variants: Vec<&'a Variant>,
+
+    /// The departures, by hour of the day
+    pub hours: Vec<Hour<'a>>,
+}
+
+impl<'a> RouteTimetable<'a> {
+    /// All departures of a variant
+    ///
+    /// # Arguments
+    ///
+    /// * `variant`: The key of the variant
+    ///
+    /// returns: Vec<&Entry>
+    pub fn departures_of(&self, variant: &VariantKey) -> Vec<&Entry<'a>> {
+        self.hours
+            .iter()
+            .flat_map(|hour| &hour.departures)
+            .filter(|entry| &self.variants[entry.variant].key == variant)
+            .collect()
+    }
+}
+
+/// The departures within an hour
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Hour<'a> {
+    /// The hour of the day, from 0 to 23
+    pub hour: u8,
+
+    /// The departures, in order
+    pub departures: Vec<Entry<'a>>,
+}
+
+/// A single departure in a timetable
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Entry<'a> {
+    /// When the bus is scheduled to depart
+    pub time: PrimitiveDateTime,
+
+    /// The index of the variant in [RouteTimetable::variants]
+    pub variant: usize,
+
+    /// The scheduled stop itself
+    pub scheduled_stop: &'a ScheduledStop,
+}
+
+impl Entry<'_> {
+    /// The minute of the hour the bus departs
+    pub fn minute(&self) -> u8 {
+        self.time.minute()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::*;
+    use crate::structs::stops::{self, test::departure};
+    use crate::timetable::Timetable;
+    use time::macros::{date, datetime};
+    use time::PrimitiveDateTime;
+
+    /// The schedule of stop 10064, with the scheduled stops of route 16 as (key, variant, time)
+    fn schedule(scheduled_stops: &[(&str, &str, PrimitiveDateTime)]) -> Schedule {
+        stops::test::schedule(
+            scheduled_stops
+                .iter()
+                .map(|(key, variant, time)| departure(key, variant, *time, *time))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn stitch() {
+        let morning = schedule(&[
+            ("1", "16-1-K", datetime!(2023-06-01 6:50)),
+            ("2", "16-0-D", datetime!(2023-06-01 7:05)),
+            ("3", "16-1-K", datetime!(2023-06-01 7:20)),
+        ]);
+        // Overlaps the first window, and runs past midnight
+        let evening = schedule(&[
+            ("3", "16-1-K", datetime!(2023-06-01 7:20)),
+            ("4", "16-1-K", datetime!(2023-06-01 7:50)),
+            ("5", "16-1-K", datetime!(2023-06-02 0:10)),
+        ]);
+        let timetable = Timetable::stitch(date!(2023 - 06 - 01), vec![evening, morning]).unwrap();
+
+        let keys: Vec<&str> = timetable.schedule.route_schedules[0]
+            .scheduled_stops
+            .iter()
+            .map(|scheduled_stop| scheduled_stop.key.as_str())
+            .collect();
+        assert_eq!(keys, vec!["1", "2", "3", "4"]);
+
+        let routes = timetable.routes();
+        assert_eq!(routes.len(), 1);
+        let route = &routes[0];
+        assert_eq!(route.route.key, BusType::Regular(16));
+        let variants: Vec<String> = route.variants.iter().map(|v| v.key.to_string()).collect();
+        assert_eq!(variants, vec!["16-1-K", "16-0-D"]);
+
+        assert_eq!(route.hours.len(), 2);
+        assert_eq!(route.hours[0].hour, 6);
+        assert_eq!(route.hours[1].hour, 7);
+        let minutes: Vec<u8> = route.hours[1]
+            .departures
+            .iter()
+            .map(|e| e.minute())
+            .collect();
+        assert_eq!(minutes, vec![5, 20, 50]);
+        assert_eq!(route.hours[1].departures[0].variant, 1);
+        assert_eq!(
+            route.hours[1].departures[0].time,
+            datetime!(2023-06-01 7:05)
+        );
+
+        assert_eq!(route.departures_of(&"16-0-D".parse().unwrap()).len(), 1);
+        assert_eq!(route.departures_of(&"16-1-K".parse().unwrap()).len(), 3);
+    }
+
+    #[test]
+    fn stitch_nothing() {
+        assert_eq!(Timetable::stitch(date!(2023 - 06 - 01), vec![]), None);
+    }
+}
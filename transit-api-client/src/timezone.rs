@@ -0,0 +1,131 @@
+// TransitTrail - Navigate Winnipeg Transit with a different style
+// Copyright (C) - 2023 Foxx Azalea Pinkerton, Max Fehlinger
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! The time zone of Winnipeg (America/Winnipeg), in which the API gives all times.
+//!
+//! Central Daylight Time (UTC-5) starts at 2:00 on the second Sunday in March and ends at 2:00
+//! on the first Sunday in November. Central Standard Time (UTC-6) is used for the rest of the
+//! year.
+//!
+//! # Example
+//!
+//! ```
+//! use time::macros::datetime;
+//! use transit_api_client::timezone;
+//!
+//! assert_eq!(
+//!     timezone::local(datetime!(2023-01-15 12:00 UTC)),
+//!     datetime!(2023-01-15 6:00)
+//! );
+//! assert_eq!(
+//!     timezone::local(datetime!(2023-06-01 12:00 UTC)),
+//!     datetime!(2023-06-01 7:00)
+//! );
+//! ```
+//!
+
+use time::{macros::offset, Date, Duration, Month, OffsetDateTime, PrimitiveDateTime, UtcOffset};
+
+/// The offset from UTC in Winnipeg at a point in time
+///
+/// # Arguments
+///
+/// * `time`: The point in time, in any offset
+///
+/// returns: UtcOffset
+pub fn offset(time: OffsetDateTime) -> UtcOffset {
+    let year = time.to_offset(offset!(UTC)).year();
+    let daylight_start = sunday(year, Month::March, 2)
+        .with_hms(8, 0, 0)
+        .expect("valid time")
+        .assume_utc();
+    let daylight_end = sunday(year, Month::November, 1)
+        .with_hms(7, 0, 0)
+        .expect("valid time")
+        .assume_utc();
+    if (daylight_start..daylight_end).contains(&time) {
+        offset!(-5)
+    } else {
+        offset!(-6)
+    }
+}
+
+/// The time on a clock in Winnipeg at a point in time, like the times of the API
+///
+/// # Arguments
+///
+/// * `time`: The point in time, in any offset
+///
+/// returns: PrimitiveDateTime
+pub fn local(time: OffsetDateTime) -> PrimitiveDateTime {
+    let local = time.to_offset(offset(time));
+    PrimitiveDateTime::new(local.date(), local.time())
+}
+
+/// The current time, in the offset of Winnipeg
+pub fn now() -> OffsetDateTime {
+    let now = OffsetDateTime::now_utc();
+    now.to_offset(offset(now))
+}
+
+/// The `n`th Sunday of a month
+fn sunday(year: i32, month: Month, n: u8) -> Date {
+    let first = Date::from_calendar_date(year, month, 1).expect("valid date");
+    let until_sunday = (7 - first.weekday().number_days_from_sunday()) % 7;
+    first + Duration::days(until_sunday as i64 + 7 * (n as i64 - 1))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::timezone;
+    use time::macros::{datetime, offset};
+
+    #[test]
+    fn daylight_saving_time() {
+        // Daylight saving time in 2023 lasted from March 12 until November 5
+        assert_eq!(
+            timezone::local(datetime!(2023-01-15 12:00 UTC)),
+            datetime!(2023-01-15 6:00)
+        );
+        assert_eq!(
+            timezone::local(datetime!(2023-03-12 7:59 UTC)),
+            datetime!(2023-03-12 1:59)
+        );
+        assert_eq!(
+            timezone::local(datetime!(2023-03-12 8:00 UTC)),
+            datetime!(2023-03-12 3:00)
+        );
+        assert_eq!(
+            timezone::local(datetime!(2023-06-01 12:00 UTC)),
+            datetime!(2023-06-01 7:00)
+        );
+        assert_eq!(
+            timezone::local(datetime!(2023-11-05 6:59 UTC)),
+            datetime!(2023-11-05 1:59)
+        );
+        assert_eq!(
+            timezone::local(datetime!(2023-11-05 7:00 UTC)),
+            datetime!(2023-11-05 1:00)
+        );
+        assert_eq!(
+            timezone::local(datetime!(2023-12-24 18:00 -6)),
+            datetime!(2023-12-24 18:00)
+        );
+        assert_eq!(
+            timezone::offset(datetime!(2024-01-10 12:00 UTC)),
+            offset!(-6)
+        );
+    }
+}
@@ -0,0 +1,419 @@
+// TransitTrail - Navigate Winnipeg Transit with a different style
+// Copyright (C) - 2023 Foxx Azalea Pinkerton, Max Fehlinger
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+
+//!
+//! Finds out where buses are, from the schedules of the stops they pass.
+//!
+//! The API does not tell where a bus is, but the schedules of stops tell which bus will pass the
+//! stop and when. Combining the schedules of the stops along a variant, the last stop a bus
+//! passed and the next stop it will reach can be inferred.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use time::OffsetDateTime;
+//! use transit_api_client::prelude::*;
+//! use transit_api_client::{timezone, tracking};
+//!
+//! # tokio_test::block_on(async {
+//! let client = TransitClient::new("<YOUR_API_TOKEN>".to_string());
+//! let mut schedules = Vec::new();
+//! for stop in [10064, 10625, 10630] {
+//!     schedules.push(client.stop_schedule(stop, vec![], Usage::Normal).await.unwrap());
+//! }
+//! let now = timezone::local(OffsetDateTime::now_utc());
+//! for (route, vehicles) in tracking::by_route(tracking::track(&schedules, now)) {
+//!     println!("{} buses on route {route}", vehicles.len());
+//! }
+//! # });
+//! ```
+//!
+
+use std::collections::{BTreeMap, HashMap};
+
+use time::{Duration, PrimitiveDateTime};
+
+use crate::structs::{
+    routes::{Variant, VariantKey},
+    stops::{Bus, Schedule},
+};
+
+/// A bus passing a stop
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Passage {
+    /// The key of the stop
+    pub stop: u32,
+
+    /// The name of the stop
+    pub name: String,
+
+    /// When the bus (is estimated to have) departed from the stop
+    pub time: PrimitiveDateTime,
+}
+
+/// Where a bus is on its way
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Vehicle {
+    /// The bus itself
+    pub bus: Bus,
+
+    /// The key of the route the bus is serving
+    pub route: String,
+
+    /// The variant the bus is serving
+    pub variant: Variant,
+
+    /// The last of the stops the bus passed, if it passed any of them yet
+    pub last_stop: Option<Passage>,
+
+    /// The next stop the bus will reach
+    pub next_stop: Passage,
+}
+
+impl Vehicle {
+    /// How long it takes until the bus reaches its next stop
+    pub fn time_to_next_stop(&self, now: PrimitiveDateTime) -> Duration {
+        self.next_stop.time - now
+    }
+
+    /// How far the bus got from its last stop to the next stop, between 0 and 1, assuming it
+    /// travels at a constant speed. [None], if it did not pass a stop yet.
+    pub fn progress(&self, now: PrimitiveDateTime) -> Option<f64> {
+        let last = self.last_stop.as_ref()?;
+        let total = (self.next_stop.time - last.time).as_seconds_f64();
+        if total <= 0.0 {
+            return Some(1.0);
+        }
+        Some(((now - last.time).as_seconds_f64() / total).clamp(0.0, 1.0))
+    }
+}
+
+/// Passages of a bus on the same variant that are further apart than this belong to different
+/// trips
+pub const MAX_GAP: Duration = Duration::minutes(30);
+
+/// Infers the position of every bus from the schedules of stops.
+///
+/// The passages of a bus are split into trips, at changes of the variant (e.g. the return trip
+/// in the other direction), at gaps longer than [MAX_GAP] and where the bus passes a stop again.
+/// The position of a bus is inferred from the trip with the next passage, so the last stop is
+/// never one of a previous trip.
+///
+/// Only buses that still have one of the stops ahead of them are returned, as the position of
+/// the other ones is unknown. Cancelled scheduled stops, scheduled stops without a bus and
+/// scheduled stops without a time are ignored.
+///
+/// # Arguments
+///
+/// * `schedules`: The schedules of the stops, ideally all stops along the variants of interest
+/// * `now`: The current time, in the time zone of the schedules
+///
+/// returns: Vec<Vehicle> Ordered by the time they reach their next stop
+pub fn track(schedules: &[Schedule], now: PrimitiveDateTime) -> Vec<Vehicle> {
+    struct Seen<'a> {
+        bus: &'a Bus,
+        route: String,
+        variant: &'a Variant,
+        passage: Passage,
+    }
+
+    let mut passages: HashMap<(u32, &VariantKey), Vec<Seen>> = HashMap::new();
+    for schedule in schedules {
+        for route_schedule in &schedule.route_schedules {
+            for scheduled_stop in &route_schedule.scheduled_stops {
+                let bus = match &scheduled_stop.bus {
+                    Some(bus) if !scheduled_stop.cancelled => bus,
+                    _ => continue,
+                };
+                let time = match scheduled_stop.times.best() {
+                    Some(time) => time,
+                    None => continue,
+                };
+                passages
+                    .entry((bus.key, &scheduled_stop.variant.key))
+                    .or_default()
+                    .push(Seen {
+                        bus,
+                        route: route_schedule.route.key.to_string(),
+                        variant: &scheduled_stop.variant,
+                        passage: Passage {
+                            stop: schedule.stop.key,
+                            name: schedule.stop.name.clone(),
+                            time,
+                        },
+                    });
+            }
+        }
+    }
+
+    let mut buses: HashMap<u32, Vehicle> = HashMap::new();
+    for mut seen in passages.into_values() {
+        seen.sort_by_key(|seen| seen.passage.time);
+        let mut start = 0;
+        for end in 1..=seen.len() {
+            let split = end == seen.len() || {
+                let previous = &seen[end - 1].passage;
+                seen[end].passage.time - previous.time > MAX_GAP
+                    || seen[start..end]
+                        .iter()
+                        .any(|seen_before| seen_before.passage.stop == seen[end].passage.stop)
+            };
+            if !split {
+                continue;
+            }
+            let trip = &seen[start..end];
+            start = end;
+
+            let next = match trip.iter().position(|seen| seen.passage.time > now) {
+                Some(next) => next,
+                None => continue,
+            };
+            let last = next.checked_sub(1).map(|last| trip[last].passage.clone());
+            let next = &trip[next];
+            if matches!(buses.get(&next.bus.key), Some(vehicle) if vehicle.next_stop.time <= next.passage.time)
+            {
+                continue;
+            }
+            buses.insert(
+                next.bus.key,
+                Vehicle {
+                    bus: next.bus.clone(),
+                    route: next.route.clone(),
+                    variant: next.variant.clone(),
+                    last_stop: last,
+                    next_stop: next.passage.clone(),
+                },
+            );
+        }
+    }
+
+    let mut vehicles: Vec<Vehicle> = buses.into_values().collect();
+    vehicles.sort_by(|a, b| {
+        a.next_stop
+            .time
+            .cmp(&b.next_stop.time)
+            .then_with(|| a.bus.key.cmp(&b.bus.key))
+    });
+    vehicles
+}
+
+/// Groups vehicles by the key of their route, keeping their order.
+///
+/// # Arguments
+///
+/// * `vehicles`: The vehicles, as returned by [track]
+///
+/// returns: BTreeMap<String, Vec<Vehicle>>
+pub fn by_route(vehicles: Vec<Vehicle>) -> BTreeMap<String, Vec<Vehicle>> {
+    let mut routes: BTreeMap<String, Vec<Vehicle>> = BTreeMap::new();
+    for vehicle in vehicles {
+        routes
+            .entry(vehicle.route.clone())
+            .or_default()
+            .push(vehicle);
+    }
+    routes
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::*;
+    use crate::structs::stops::{self, test::departure};
+    use crate::tracking;
+    use time::macros::datetime;
+    use time::{Duration, PrimitiveDateTime};
+
+    /// Route 16 passing a stop with a bus on a variant
+    fn passing(stop: u32, bus: u32, variant: &str, time: PrimitiveDateTime) -> ScheduledStop {
+        ScheduledStop {
+            bus: Some(Bus {
+                key: bus,
+                bike_rack: true,
+                wifi: false,
+            }),
+            ..departure(&format!("{bus}-{stop}"), variant, time, time)
+        }
+    }
+
+    /// The schedule of a stop with the given scheduled stops
+    fn stop_schedule(stop: u32, scheduled_stops: Vec<ScheduledStop>) -> Schedule {
+        let mut schedule = stops::test::schedule(scheduled_stops);
+        schedule.stop.key = stop;
+        schedule.stop.number = stop;
+        schedule.stop.name = format!("Stop {stop}");
+        schedule
+    }
+
+    /// The schedule of a stop, where route 16 passes with the buses at the given times
+    fn schedule(stop: u32, buses: &[(u32, PrimitiveDateTime)]) -> Schedule {
+        let scheduled_stops = buses
+            .iter()
+            .map(|(bus, time)| passing(stop, *bus, "16-1-K", *time))
+            .collect();
+        stop_schedule(stop, scheduled_stops)
+    }
+
+    #[test]
+    fn track() {
+        // Bus 511 is between the first and second stop, 512 did not reach the first one yet and
+        // 513 already passed the last one.
+        let schedules = vec![
+            schedule(
+                10001,
+                &[
+                    (511, datetime!(2023-06-01 7:00)),
+                    (512, datetime!(2023-06-01 7:20)),
+                    (513, datetime!(2023-06-01 6:40)),
+                ],
+            ),
+            schedule(
+                10002,
+                &[
+                    (511, datetime!(2023-06-01 7:06)),
+                    (512, datetime!(2023-06-01 7:26)),
+                    (513, datetime!(2023-06-01 6:46)),
+                ],
+            ),
+            schedule(
+                10003,
+                &[
+                    (511, datetime!(2023-06-01 7:10)),
+                    (512, datetime!(2023-06-01 7:30)),
+                    (513, datetime!(2023-06-01 6:50)),
+                ],
+            ),
+        ];
+        let now = datetime!(2023-06-01 7:03);
+        let vehicles = tracking::track(&schedules, now);
+        assert_eq!(vehicles.len(), 2);
+
+        let first = &vehicles[0];
+        assert_eq!(first.bus.key, 511);
+        assert_eq!(first.route, "16");
+        assert_eq!(first.variant.key.to_string(), "16-1-K");
+        assert_eq!(first.last_stop.as_ref().unwrap().stop, 10001);
+        assert_eq!(first.next_stop.stop, 10002);
+        assert_eq!(first.time_to_next_stop(now), Duration::minutes(3));
+        assert_eq!(first.progress(now), Some(0.5));
+
+        let second = &vehicles[1];
+        assert_eq!(second.bus.key, 512);
+        assert_eq!(second.last_stop, None);
+        assert_eq!(second.next_stop.stop, 10001);
+        assert_eq!(second.progress(now), None);
+
+        let routes = tracking::by_route(vehicles);
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes["16"].len(), 2);
+    }
+
+    #[test]
+    fn return_trip() {
+        // Bus 511 goes out to the loop and comes back on the other variant
+        let schedules = vec![
+            stop_schedule(
+                10001,
+                vec![
+                    passing(10001, 511, "16-1-K", datetime!(2023-06-01 7:00)),
+                    passing(10001, 511, "16-0-D", datetime!(2023-06-01 7:25)),
+                ],
+            ),
+            stop_schedule(
+                10002,
+                vec![
+                    passing(10002, 511, "16-1-K", datetime!(2023-06-01 7:05)),
+                    passing(10002, 511, "16-0-D", datetime!(2023-06-01 7:20)),
+                ],
+            ),
+        ];
+
+        // At the loop, the last stop of the trip out is not the last stop of the return trip
+        let vehicles = tracking::track(&schedules, datetime!(2023-06-01 7:15));
+        assert_eq!(vehicles.len(), 1);
+        assert_eq!(vehicles[0].variant.key.to_string(), "16-0-D");
+        assert_eq!(vehicles[0].last_stop, None);
+        assert_eq!(vehicles[0].next_stop.stop, 10002);
+
+        let vehicles = tracking::track(&schedules, datetime!(2023-06-01 7:22));
+        assert_eq!(vehicles[0].variant.key.to_string(), "16-0-D");
+        assert_eq!(vehicles[0].last_stop.as_ref().unwrap().stop, 10002);
+        assert_eq!(vehicles[0].next_stop.stop, 10001);
+    }
+
+    #[test]
+    fn next_trip() {
+        // Bus 511 runs the same variant twice, an hour apart
+        let schedules = vec![
+            schedule(
+                10001,
+                &[
+                    (511, datetime!(2023-06-01 7:00)),
+                    (511, datetime!(2023-06-01 8:00)),
+                ],
+            ),
+            schedule(
+                10002,
+                &[
+                    (511, datetime!(2023-06-01 7:05)),
+                    (511, datetime!(2023-06-01 8:05)),
+                ],
+            ),
+        ];
+        let vehicles = tracking::track(&schedules, datetime!(2023-06-01 7:30));
+        assert_eq!(vehicles.len(), 1);
+        assert_eq!(vehicles[0].last_stop, None);
+        assert_eq!(vehicles[0].next_stop.stop, 10001);
+        assert_eq!(vehicles[0].next_stop.time, datetime!(2023-06-01 8:00));
+    }
+
+    #[test]
+    fn cancelled_and_unknown_times() {
+        let mut cancelled = passing(10002, 511, "16-1-K", datetime!(2023-06-01 7:05));
+        cancelled.cancelled = true;
+        let mut unknown = passing(10001, 512, "16-1-K", datetime!(2023-06-01 7:20));
+        unknown.times = ScheduledTimes {
+            arrival: None,
+            departure: None,
+        };
+        let schedules = vec![
+            stop_schedule(
+                10001,
+                vec![
+                    passing(10001, 511, "16-1-K", datetime!(2023-06-01 7:00)),
+                    unknown,
+                ],
+            ),
+            stop_schedule(
+                10002,
+                vec![
+                    cancelled,
+                    passing(10002, 512, "16-1-K", datetime!(2023-06-01 7:25)),
+                ],
+            ),
+            schedule(10003, &[(511, datetime!(2023-06-01 7:10))]),
+        ];
+        let vehicles = tracking::track(&schedules, datetime!(2023-06-01 7:03));
+        assert_eq!(vehicles.len(), 2);
+
+        // The cancelled stop is skipped
+        assert_eq!(vehicles[0].bus.key, 511);
+        assert_eq!(vehicles[0].last_stop.as_ref().unwrap().stop, 10001);
+        assert_eq!(vehicles[0].next_stop.stop, 10003);
+
+        // The stop without a time is not the next one
+        assert_eq!(vehicles[1].bus.key, 512);
+        assert_eq!(vehicles[1].last_stop, None);
+        assert_eq!(vehicles[1].next_stop.stop, 10002);
+    }
+}